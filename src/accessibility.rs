@@ -0,0 +1,131 @@
+use bevy::prelude::*;
+use itertools::Either;
+
+use crate::{
+    board::TILE_SIZE, food::Food, snake::SnakeSegment,
+    theme::ActiveTheme,
+};
+
+/// How big the diamond behind an apple is, compared to
+/// a cell
+const FOOD_CUE_SCALE: f32 = 0.75;
+
+/// Shape cues from the `Accessibility` presets. The
+/// colors come in through `ActiveTheme`, this adds the
+/// outlines behind snakes and the diamonds behind
+/// apples that go with them.
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(add_shape_cues)
+            .add_system(sync_cue_visibility);
+    }
+}
+
+/// A sprite drawn behind a snake segment or apple, as
+/// a child of it so it follows every move.
+#[derive(Component)]
+pub struct ShapeCue;
+
+/// Gives new segments and apples their cues, and
+/// redoes every cue when the palette changes.
+fn add_shape_cues(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    segments: Query<Entity, With<SnakeSegment>>,
+    new_segments: Query<Entity, Added<SnakeSegment>>,
+    food: Query<Entity, With<Food>>,
+    new_food: Query<Entity, Added<Food>>,
+    cues: Query<Entity, With<ShapeCue>>,
+) {
+    let (segments, food) = if theme.is_changed() {
+        for cue in cues.iter() {
+            commands.entity(cue).despawn_recursive();
+        }
+        (
+            Either::Left(segments.iter()),
+            Either::Left(food.iter()),
+        )
+    } else {
+        (
+            Either::Right(new_segments.iter()),
+            Either::Right(new_food.iter()),
+        )
+    };
+
+    if let Some(color) = theme.palette.outline {
+        for entity in segments {
+            commands.entity(entity).with_children(
+                |parent| {
+                    parent
+                        .spawn_bundle(cue_sprite(
+                            color,
+                            Vec2::splat(TILE_SIZE),
+                            Quat::IDENTITY,
+                        ))
+                        .insert(ShapeCue);
+                },
+            );
+        }
+    }
+    if let Some(color) = theme.palette.food_cue {
+        for entity in food {
+            commands.entity(entity).with_children(
+                |parent| {
+                    parent
+                        .spawn_bundle(cue_sprite(
+                            color,
+                            Vec2::splat(
+                                TILE_SIZE * FOOD_CUE_SCALE,
+                            ),
+                            Quat::from_rotation_z(
+                                std::f32::consts::FRAC_PI_4,
+                            ),
+                        ))
+                        .insert(ShapeCue);
+                },
+            );
+        }
+    }
+}
+
+fn cue_sprite(
+    color: Color,
+    size: Vec2,
+    rotation: Quat,
+) -> SpriteBundle {
+    SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(size),
+            ..Default::default()
+        },
+        // just behind its parent, still above the grass
+        transform: Transform {
+            translation: Vec3::new(0.0, 0.0, -0.5),
+            rotation,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Children don't inherit visibility, so cues are
+/// hidden along with the sprite they're behind, like
+/// the trailing tail between ticks.
+fn sync_cue_visibility(
+    parents: Query<&Visibility, Without<ShapeCue>>,
+    mut cues: Query<
+        (&Parent, &mut Visibility),
+        With<ShapeCue>,
+    >,
+) {
+    for (parent, mut visibility) in cues.iter_mut() {
+        if let Ok(parent) = parents.get(parent.0) {
+            if visibility.is_visible != parent.is_visible {
+                visibility.is_visible = parent.is_visible;
+            }
+        }
+    }
+}
@@ -0,0 +1,560 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{GameMode, LocalPlayer, Paused, RunState},
+    gamepad::PadAssignments,
+    persistence,
+    rules::Direction,
+    scoring::Restarting,
+};
+
+const BINDINGS_FILE: &str = "bindings.ron";
+/// Saved sets of bindings, so people sharing a machine
+/// can each keep their own
+pub const PROFILES: usize = 3;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BindingProfiles::load())
+            .init_resource::<Actions>()
+            .init_resource::<BindingCapture>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                read_actions.after(InputSystem),
+            )
+            .add_system(save_bindings)
+            .add_system_set(
+                SystemSet::on_update(RunState::Playing)
+                    .with_system(pause_game)
+                    .with_system(restart_game),
+            )
+            .add_system_set(
+                SystemSet::on_update(RunState::Menu)
+                    .with_system(capture_binding),
+            );
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+    Restart,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::Up,
+        Action::Down,
+        Action::Left,
+        Action::Right,
+        Action::Pause,
+        Action::Restart,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Action::Up => "action.up",
+            Action::Down => "action.down",
+            Action::Left => "action.left",
+            Action::Right => "action.right",
+            Action::Pause => "action.pause",
+            Action::Restart => "action.restart",
+        }
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        match self {
+            Action::Up => Some(Direction::Up),
+            Action::Down => Some(Direction::Down),
+            Action::Left => Some(Direction::Left),
+            Action::Right => Some(Direction::Right),
+            Action::Pause | Action::Restart => None,
+        }
+    }
+}
+
+/// A key or gamepad button an action is bound to. Pad
+/// buttons match on every pad, which pads count is
+/// up to whoever reads them.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Trigger {
+    Key(KeyCode),
+    Pad(GamepadButtonType),
+}
+
+impl Trigger {
+    pub fn name(&self) -> String {
+        match self {
+            Trigger::Key(key) => format!("{:?}", key),
+            Trigger::Pad(button) => {
+                format!("Pad {:?}", button)
+            }
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct Bindings {
+    pub actions: HashMap<Action, Vec<Trigger>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Trigger::*;
+        let actions = [
+            (
+                Action::Up,
+                vec![
+                    Key(KeyCode::Up),
+                    Key(KeyCode::W),
+                    Pad(GamepadButtonType::DPadUp),
+                ],
+            ),
+            (
+                Action::Down,
+                vec![
+                    Key(KeyCode::Down),
+                    Key(KeyCode::S),
+                    Pad(GamepadButtonType::DPadDown),
+                ],
+            ),
+            (
+                Action::Left,
+                vec![
+                    Key(KeyCode::Left),
+                    Key(KeyCode::A),
+                    Pad(GamepadButtonType::DPadLeft),
+                ],
+            ),
+            (
+                Action::Right,
+                vec![
+                    Key(KeyCode::Right),
+                    Key(KeyCode::D),
+                    Pad(GamepadButtonType::DPadRight),
+                ],
+            ),
+            (
+                Action::Pause,
+                vec![
+                    Key(KeyCode::P),
+                    Key(KeyCode::Space),
+                    Pad(GamepadButtonType::Start),
+                ],
+            ),
+            (
+                Action::Restart,
+                vec![
+                    Key(KeyCode::R),
+                    Pad(GamepadButtonType::Select),
+                ],
+            ),
+        ];
+        Bindings {
+            actions: actions.into_iter().collect(),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn triggers(&self, action: Action) -> &[Trigger] {
+        self.actions
+            .get(&action)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn action_for(
+        &self,
+        trigger: Trigger,
+    ) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| {
+            self.triggers(*action).contains(&trigger)
+        })
+    }
+
+    /// Adds `trigger` to `action`. A trigger only ever
+    /// does one thing, so it's taken away from whatever
+    /// action had it before, which is returned.
+    pub fn bind(
+        &mut self,
+        action: Action,
+        trigger: Trigger,
+    ) -> Option<Action> {
+        let previous = self.action_for(trigger);
+        if previous == Some(action) {
+            return None;
+        }
+        if let Some(previous) = previous {
+            self.actions
+                .entry(previous)
+                .or_default()
+                .retain(|bound| *bound != trigger);
+        }
+        self.actions
+            .entry(action)
+            .or_default()
+            .push(trigger);
+        previous
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.actions.remove(&action);
+    }
+
+    pub fn pad_direction(
+        &self,
+        pad: Gamepad,
+        buttons: &Input<GamepadButton>,
+    ) -> Option<Direction> {
+        let pressed = |trigger: &Trigger| match trigger {
+            Trigger::Pad(button) => {
+                buttons.pressed(GamepadButton(pad, *button))
+            }
+            Trigger::Key(_) => false,
+        };
+        Action::ALL.into_iter().find_map(|action| {
+            action.direction().filter(|_| {
+                self.triggers(action).iter().any(pressed)
+            })
+        })
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct BindingProfiles {
+    pub active: usize,
+    pub profiles: Vec<Bindings>,
+}
+
+impl Default for BindingProfiles {
+    fn default() -> Self {
+        BindingProfiles {
+            active: 0,
+            profiles: vec![Bindings::default(); PROFILES],
+        }
+    }
+}
+
+impl BindingProfiles {
+    pub fn load() -> Self {
+        let mut profiles: BindingProfiles =
+            persistence::load(BINDINGS_FILE);
+        profiles
+            .profiles
+            .resize_with(PROFILES, Bindings::default);
+        profiles.active %= PROFILES;
+        profiles
+    }
+
+    pub fn save(&self) {
+        persistence::save(BINDINGS_FILE, self);
+    }
+
+    pub fn current(&self) -> &Bindings {
+        &self.profiles[self.active]
+    }
+
+    pub fn current_mut(&mut self) -> &mut Bindings {
+        &mut self.profiles[self.active]
+    }
+
+    pub fn reset(&mut self) {
+        *self.current_mut() = Bindings::default();
+    }
+
+    pub fn next_profile(&mut self) {
+        self.active = (self.active + 1) % PROFILES;
+    }
+}
+
+fn save_bindings(profiles: Res<BindingProfiles>) {
+    if profiles.is_changed() && !profiles.is_added() {
+        profiles.save();
+    }
+}
+
+/// Which actions the local player is holding this
+/// frame, from the keyboard and any pad steering the
+/// local snake. Pause and restart work from every pad.
+#[derive(Default)]
+pub struct Actions {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        Action::ALL.into_iter().find_map(|action| {
+            action
+                .direction()
+                .filter(|_| self.pressed(action))
+        })
+    }
+
+    pub fn just_pressed_direction(
+        &self,
+    ) -> Option<Direction> {
+        Action::ALL.into_iter().find_map(|action| {
+            action
+                .direction()
+                .filter(|_| self.just_pressed(action))
+        })
+    }
+}
+
+fn read_actions(
+    profiles: Res<BindingProfiles>,
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    gamepads: Res<Gamepads>,
+    assignments: Res<PadAssignments>,
+    local: Res<LocalPlayer>,
+    mut actions: ResMut<Actions>,
+) {
+    actions.pressed.clear();
+    actions.just_pressed.clear();
+    let bindings = profiles.current();
+    for action in Action::ALL {
+        let pads: Vec<Gamepad> = gamepads
+            .iter()
+            .copied()
+            .filter(|pad| {
+                action.direction().is_none()
+                    || assignments.snake_for(*pad)
+                        == Some(local.0)
+            })
+            .collect();
+        let mut pressed = false;
+        let mut just_pressed = false;
+        for trigger in bindings.triggers(action) {
+            match trigger {
+                Trigger::Key(key) => {
+                    pressed |= keys.pressed(*key);
+                    just_pressed |= keys.just_pressed(*key);
+                }
+                Trigger::Pad(button) => {
+                    for pad in &pads {
+                        let button =
+                            GamepadButton(*pad, *button);
+                        pressed |= buttons.pressed(button);
+                        just_pressed |=
+                            buttons.just_pressed(button);
+                    }
+                }
+            }
+        }
+        if pressed {
+            actions.pressed.insert(action);
+        }
+        if just_pressed {
+            actions.just_pressed.insert(action);
+        }
+    }
+}
+
+/// Pausing a LAN game would stall every other player,
+/// so it only works offline.
+fn pause_game(
+    actions: Res<Actions>,
+    mode: Res<GameMode>,
+    mut paused: ResMut<Paused>,
+) {
+    if *mode != GameMode::Lan
+        && actions.just_pressed(Action::Pause)
+    {
+        paused.0 = !paused.0;
+    }
+}
+
+/// Starts the game over in the same mode. LAN games
+/// can't be restarted by one player.
+fn restart_game(
+    actions: Res<Actions>,
+    mode: Res<GameMode>,
+    mut state: ResMut<State<RunState>>,
+    mut restarting: ResMut<Restarting>,
+) {
+    if *mode == GameMode::Lan
+        || !actions.just_pressed(Action::Restart)
+    {
+        return;
+    }
+    match state.restart() {
+        Ok(()) => restarting.0 = true,
+        Err(error) => {
+            warn!(?error, "failed to restart the game")
+        }
+    }
+}
+
+/// The controls menu is waiting for a key or button to
+/// bind to `action`. Escape cancels.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BindingCapture {
+    pub action: Option<Action>,
+    /// What happened to the last capture, shown in the
+    /// menu
+    pub message: Option<CaptureMessage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureMessage {
+    Cancelled,
+    Reset,
+    Bound {
+        trigger: Trigger,
+        action: Action,
+    },
+    /// `trigger` was bound to `from` before
+    Moved {
+        trigger: Trigger,
+        from: Action,
+        to: Action,
+    },
+}
+
+fn capture_binding(
+    keys: Res<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut capture: ResMut<BindingCapture>,
+    mut profiles: ResMut<BindingProfiles>,
+) {
+    let action = match capture.action {
+        Some(action) => action,
+        None => return,
+    };
+    let trigger = if keys.just_pressed(KeyCode::Escape) {
+        *capture = BindingCapture {
+            action: None,
+            message: Some(CaptureMessage::Cancelled),
+        };
+        return;
+    } else if let Some(key) = keys.get_just_pressed().next()
+    {
+        Trigger::Key(*key)
+    } else if let Some(button) =
+        buttons.get_just_pressed().next()
+    {
+        Trigger::Pad(button.1)
+    } else {
+        return;
+    };
+
+    let message = match profiles
+        .current_mut()
+        .bind(action, trigger)
+    {
+        Some(previous) => CaptureMessage::Moved {
+            trigger,
+            from: previous,
+            to: action,
+        },
+        None => CaptureMessage::Bound { trigger, action },
+    };
+    *capture = BindingCapture {
+        action: None,
+        message: Some(message),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_a_new_trigger_adds_it() {
+        let mut bindings = Bindings::default();
+        let trigger = Trigger::Key(KeyCode::K);
+        assert_eq!(
+            bindings.bind(Action::Up, trigger),
+            None
+        );
+        assert_eq!(
+            bindings.action_for(trigger),
+            Some(Action::Up)
+        );
+        assert_eq!(bindings.triggers(Action::Up).len(), 4);
+    }
+
+    #[test]
+    fn binding_a_used_trigger_moves_it() {
+        let mut bindings = Bindings::default();
+        let trigger = Trigger::Key(KeyCode::W);
+        assert_eq!(
+            bindings.bind(Action::Down, trigger),
+            Some(Action::Up)
+        );
+        assert!(!bindings
+            .triggers(Action::Up)
+            .contains(&trigger));
+        assert_eq!(
+            bindings.action_for(trigger),
+            Some(Action::Down)
+        );
+    }
+
+    #[test]
+    fn binding_a_trigger_again_changes_nothing() {
+        let mut bindings = Bindings::default();
+        let trigger =
+            Trigger::Pad(GamepadButtonType::Start);
+        assert_eq!(
+            bindings.bind(Action::Pause, trigger),
+            None
+        );
+        assert_eq!(bindings, Bindings::default());
+    }
+
+    #[test]
+    fn clear_unbinds_everything() {
+        let mut bindings = Bindings::default();
+        bindings.clear(Action::Restart);
+        assert!(bindings
+            .triggers(Action::Restart)
+            .is_empty());
+        assert_eq!(
+            bindings.action_for(Trigger::Key(KeyCode::R)),
+            None
+        );
+    }
+}
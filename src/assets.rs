@@ -2,15 +2,13 @@ use bevy::prelude::*;
 use bevy_asset_loader::{
     AssetCollection, AssetCollectionApp,
 };
-use kayak_ui::font::KayakFont;
 
 pub struct AssetsPlugin;
 
 impl Plugin for AssetsPlugin {
     fn build(&self, app: &mut App) {
         app.init_collection::<AudioAssets>()
-            .init_collection::<ImageAssets>()
-            .init_collection::<FontAssets>();
+            .init_collection::<ImageAssets>();
     }
 }
 
@@ -20,48 +18,38 @@ pub struct AudioAssets {
     pub gameover: Handle<bevy_kira_audio::AudioSource>,
     #[asset(path = "apple.ogg")]
     pub apple: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "turn.wav")]
+    pub turn: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "win.wav")]
+    pub win: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "new_best.wav")]
+    pub new_best: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "menu_music.wav")]
+    pub menu_music: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "game_music.wav")]
+    pub game_music: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "tension.wav")]
+    pub tension: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "stinger.wav")]
+    pub stinger: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "beacon.wav")]
+    pub beacon: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "wall_ahead.wav")]
+    pub wall_ahead: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "body_ahead.wav")]
+    pub body_ahead: Handle<bevy_kira_audio::AudioSource>,
+    #[asset(path = "focus.wav")]
+    pub focus: Handle<bevy_kira_audio::AudioSource>,
 }
 
 #[derive(AssetCollection)]
 pub struct ImageAssets {
-    #[asset(path = "snake_heads", folder(typed))]
-    pub snake_heads: Vec<Handle<Image>>,
-    #[asset(path = "apple.png")]
-    pub apple: Handle<Image>,
-    #[asset(path = "green_panel.png")]
-    pub green_panel: Handle<Image>,
-    #[asset(path = "blue_button09.png")]
-    pub blue_button09: Handle<Image>,
-    #[asset(path = "blue_button10.png")]
-    pub blue_button10: Handle<Image>,
     #[asset(path = "grey_box.png")]
     pub box_unchecked: Handle<Image>,
     #[asset(path = "green_boxCheckmark.png")]
     pub box_checked: Handle<Image>,
-    #[asset(texture_atlas(
-        tile_size_x = 16.,
-        tile_size_y = 16.,
-        columns = 3,
-        rows = 1,
-        padding_x = 0.,
-        padding_y = 0.
-    ))]
-    #[asset(path = "grass.png")]
-    pub grass: Handle<TextureAtlas>,
-    #[asset(texture_atlas(
-        tile_size_x = 136.,
-        tile_size_y = 136.,
-        columns = 4,
-        rows = 30,
-        padding_x = 0.,
-        padding_y = 0.
-    ))]
-    #[asset(path = "snake_sprites.png")]
-    pub snake: Handle<TextureAtlas>,
-}
-
-#[derive(AssetCollection)]
-pub struct FontAssets {
-    #[asset(path = "roboto.kayak_font")]
-    pub roboto: Handle<KayakFont>,
+    #[asset(path = "blue_sliderRight.png")]
+    pub slider: Handle<Image>,
+    #[asset(path = "grey_sliderHorizontal.png")]
+    pub slider_track: Handle<Image>,
 }
@@ -0,0 +1,343 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Position,
+    rules::{Direction, GameState},
+};
+
+/// How long `/start` and `/end` may take. Their replies
+/// are ignored, but servers often set up or tear down a
+/// game there, which the move budget is too short for.
+const LIFECYCLE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Snakes here never starve, they're always at full
+/// health.
+const HEALTH: u32 = 100;
+
+/// A snake server speaking the Battlesnake API, called
+/// at `/start`, `/move` and `/end` under its URL. Only
+/// plain `http://` is supported, matches are meant to
+/// run against servers on localhost.
+pub struct BattlesnakeBot {
+    url: String,
+    budget: Duration,
+    game_id: String,
+}
+
+impl BattlesnakeBot {
+    pub fn new(url: &str, budget: Duration) -> Self {
+        BattlesnakeBot {
+            url: url.trim_end_matches('/').to_string(),
+            budget,
+            game_id: String::new(),
+        }
+    }
+
+    pub fn start(
+        &mut self,
+        state: &GameState,
+        snake: usize,
+    ) {
+        self.game_id = format!(
+            "bevy-snake-{:016x}",
+            rand::random::<u64>()
+        );
+        if let Err(error) = self.call(
+            "start",
+            state,
+            snake,
+            LIFECYCLE_TIMEOUT,
+        ) {
+            warn!(url = %self.url, %error, "/start failed");
+        }
+    }
+
+    /// `None` keeps the current direction, as it does
+    /// for process bots.
+    pub fn decide(
+        &mut self,
+        state: &GameState,
+        snake: usize,
+    ) -> Option<Direction> {
+        let body = match self.call(
+            "move",
+            state,
+            snake,
+            self.budget,
+        ) {
+            Ok(body) => body,
+            Err(error) => {
+                debug!(
+                    url = %self.url,
+                    %error,
+                    "/move failed"
+                );
+                return None;
+            }
+        };
+        let reply =
+            serde_json::from_str::<MoveResponse>(&body)
+                .ok()?;
+        match reply.direction.as_str() {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    pub fn end(&mut self, state: &GameState, snake: usize) {
+        if let Err(error) = self.call(
+            "end",
+            state,
+            snake,
+            LIFECYCLE_TIMEOUT,
+        ) {
+            warn!(url = %self.url, %error, "/end failed");
+        }
+    }
+
+    fn call(
+        &self,
+        endpoint: &str,
+        state: &GameState,
+        snake: usize,
+        timeout: Duration,
+    ) -> io::Result<String> {
+        let request = GameRequest::new(
+            &self.game_id,
+            self.budget,
+            state,
+            snake,
+        );
+        post(
+            &format!("{}/{}", self.url, endpoint),
+            &serde_json::to_string(&request).unwrap(),
+            timeout,
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct MoveResponse {
+    #[serde(rename = "move")]
+    direction: String,
+}
+
+/// The body of every call, see
+/// https://docs.battlesnake.com/api/requests
+#[derive(Serialize)]
+struct GameRequest {
+    game: Game,
+    turn: u64,
+    board: Board,
+    you: Battlesnake,
+}
+
+#[derive(Serialize)]
+struct Game {
+    id: String,
+    ruleset: Ruleset,
+    map: String,
+    timeout: u128,
+    source: String,
+}
+
+#[derive(Serialize)]
+struct Ruleset {
+    name: String,
+    version: String,
+    settings: RulesetSettings,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RulesetSettings {
+    /// Walls are sent as hazards that take all of a
+    /// snake's health, so servers steer clear of them
+    /// instead of treating them as damage tiles.
+    hazard_damage_per_turn: u32,
+}
+
+#[derive(Serialize)]
+struct Board {
+    height: u16,
+    width: u16,
+    food: Vec<Position>,
+    /// Battlesnake has no walls, hazards are the
+    /// closest thing its schema offers, see
+    /// `RulesetSettings`
+    hazards: Vec<Position>,
+    snakes: Vec<Battlesnake>,
+}
+
+#[derive(Serialize)]
+struct Battlesnake {
+    id: String,
+    name: String,
+    health: u32,
+    body: Vec<Position>,
+    latency: String,
+    head: Position,
+    length: usize,
+    shout: String,
+    squad: String,
+}
+
+impl GameRequest {
+    fn new(
+        game_id: &str,
+        timeout: Duration,
+        state: &GameState,
+        you: usize,
+    ) -> Self {
+        // both count `y` up from the bottom row, so
+        // positions map straight across
+        let snake = |index: usize| {
+            let snake = &state.snakes[index];
+            Battlesnake {
+                id: format!("snake-{}", index),
+                name: format!("Snake {}", index + 1),
+                health: HEALTH,
+                body: snake
+                    .body
+                    .segments
+                    .iter()
+                    .copied()
+                    .collect(),
+                latency: "0".to_string(),
+                head: snake.head(),
+                length: snake.body.segments.len(),
+                shout: String::new(),
+                squad: String::new(),
+            }
+        };
+        let ruleset = if state.snakes.len() > 1 {
+            "standard"
+        } else {
+            "solo"
+        };
+
+        GameRequest {
+            game: Game {
+                id: game_id.to_string(),
+                ruleset: Ruleset {
+                    name: ruleset.to_string(),
+                    version: env!("CARGO_PKG_VERSION")
+                        .to_string(),
+                    settings: RulesetSettings {
+                        hazard_damage_per_turn: HEALTH,
+                    },
+                },
+                map: "standard".to_string(),
+                timeout: timeout.as_millis(),
+                source: "custom".to_string(),
+            },
+            turn: state.tick,
+            board: Board {
+                height: state.rules.board_size,
+                width: state.rules.board_size,
+                food: state.food.clone(),
+                hazards: state.walls.clone(),
+                snakes: (0..state.snakes.len())
+                    .filter(|index| {
+                        state.snakes[*index].is_alive()
+                    })
+                    .map(snake)
+                    .collect(),
+            },
+            you: snake(you),
+        }
+    }
+}
+
+/// A bare-bones HTTP/1.0 POST. 1.0 keeps servers from
+/// answering with chunked bodies, the response ends
+/// when they close the connection. `timeout` covers the
+/// whole call, not each read and write.
+fn post(
+    url: &str,
+    body: &str,
+    timeout: Duration,
+) -> io::Result<String> {
+    let deadline = Instant::now() + timeout;
+    let remaining = || {
+        deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "out of time",
+                )
+            })
+    };
+    let invalid = |message: &str| {
+        io::Error::new(io::ErrorKind::InvalidInput, message)
+    };
+    let rest =
+        url.strip_prefix("http://").ok_or_else(|| {
+            invalid("only http:// is supported")
+        })?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid("unknown host"))?;
+
+    let mut stream =
+        TcpStream::connect_timeout(&addr, remaining()?)?;
+    stream.set_write_timeout(Some(remaining()?))?;
+    stream.write_all(
+        format!(
+            "POST {} HTTP/1.0\r\n\
+             Host: {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n{}",
+            path,
+            host,
+            body.len(),
+            body
+        )
+        .as_bytes(),
+    )?;
+
+    let mut response = vec![];
+    let mut buffer = [0; 4096];
+    loop {
+        stream.set_read_timeout(Some(remaining()?))?;
+        match stream.read(&mut buffer)? {
+            0 => break,
+            read => {
+                response.extend_from_slice(&buffer[..read])
+            }
+        }
+    }
+    let response = String::from_utf8(response)
+        .map_err(|_| invalid("response isn't UTF-8"))?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| invalid("malformed response"))?;
+    let status = head.split_whitespace().nth(1);
+    if status != Some("200") {
+        return Err(io::Error::other(
+            head.lines().next().unwrap_or("").to_string(),
+        ));
+    }
+    Ok(body.to_string())
+}
@@ -0,0 +1,363 @@
+//! Plays many games without a window or audio and
+//! prints statistics about how the bots did.
+//!
+//! `snake-sim --games 10000 --builtin greedy`
+//! `snake-sim --seed 100 --board-size 12 --modifier
+//! obstacles --bot ./my_bot --json`
+//!
+//! Bots are chosen the same way as for `bevy-snake`,
+//! with `--bot`, `--battlesnake` and `--builtin`.
+
+use std::{
+    collections::BTreeMap, ops::Range, thread,
+    time::Instant,
+};
+
+use bevy_snake::{
+    bot::{play, BotKind, BotSettings, Bots, BuiltinBot},
+    rules::{GameOverReason, GameState},
+    settings::{GameRules, Modifier},
+};
+use serde::Serialize;
+
+struct SimSettings {
+    games: u64,
+    rules: GameRules,
+    max_ticks: u64,
+    threads: usize,
+    json: bool,
+}
+
+impl SimSettings {
+    fn from_args() -> Self {
+        let mut settings = SimSettings {
+            games: 1000,
+            rules: GameRules::default(),
+            max_ticks: 10_000,
+            threads: thread::available_parallelism()
+                .map_or(1, |threads| threads.get()),
+            json: false,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => settings.json = true,
+                "--games" => {
+                    if let Some(Ok(games)) =
+                        args.next().map(|arg| arg.parse())
+                    {
+                        settings.games = games;
+                    }
+                }
+                "--board-size" => {
+                    match args
+                        .next()
+                        .map(|arg| arg.parse::<u16>())
+                    {
+                        Some(Ok(size))
+                            if GameRules::BOARD_SIZES
+                                .contains(&size) =>
+                        {
+                            settings.rules.board_size = size
+                        }
+                        _ => eprintln!(
+                            "board size must be within \
+                             {:?}",
+                            GameRules::BOARD_SIZES
+                        ),
+                    }
+                }
+                "--max-ticks" => {
+                    if let Some(Ok(ticks)) =
+                        args.next().map(|arg| arg.parse())
+                    {
+                        settings.max_ticks = ticks;
+                    }
+                }
+                "--threads" => {
+                    if let Some(Ok(threads)) =
+                        args.next().map(|arg| arg.parse())
+                    {
+                        settings.threads =
+                            usize::max(threads, 1);
+                    }
+                }
+                "--modifier" => {
+                    match args.next().and_then(|name| {
+                        parse_modifier(&name)
+                    }) {
+                        Some(modifier) => settings
+                            .rules
+                            .modifiers
+                            .push(modifier),
+                        None => eprintln!(
+                            "unknown modifier, expected \
+                             one of {:?}",
+                            Modifier::ALL
+                        ),
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+/// `double-apples`, `DoubleApples` and `doubleapples`
+/// all name the same modifier.
+fn parse_modifier(name: &str) -> Option<Modifier> {
+    let name = name.replace(['-', '_'], "").to_lowercase();
+    Modifier::ALL.into_iter().find(|modifier| {
+        format!("{:?}", modifier).to_lowercase() == name
+    })
+}
+
+struct GameResult {
+    ticks: u64,
+    snakes: Vec<SnakeResult>,
+}
+
+struct SnakeResult {
+    score: u32,
+    /// `None` if the game hit `max_ticks` first
+    reason: Option<GameOverReason>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    games: u64,
+    seeds: Range<u64>,
+    board_size: u16,
+    modifiers: Vec<Modifier>,
+    games_per_second: f64,
+    mean_ticks: f64,
+    median_ticks: f64,
+    snakes: Vec<SnakeReport>,
+}
+
+#[derive(Serialize)]
+struct SnakeReport {
+    controller: String,
+    mean_score: f64,
+    median_score: f64,
+    max_score: u32,
+    win_rate: f64,
+    /// how each game ended for this snake, games that
+    /// hit `max_ticks` count as `"Timeout"`
+    endings: BTreeMap<String, u64>,
+}
+
+fn main() {
+    let settings = SimSettings::from_args();
+    let mut bots = BotSettings::from_args();
+    if bots.bots.is_empty() {
+        bots.bots.push((
+            0,
+            BotKind::Builtin(BuiltinBot::Greedy),
+        ));
+    }
+    let first_seed = bots.seed.unwrap_or(0);
+    let seeds = first_seed..first_seed + settings.games;
+
+    let started = Instant::now();
+    let results = simulate(&settings, &bots, seeds.clone());
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let players =
+        results.first().map_or(1, |game| game.snakes.len());
+    let report = Report {
+        games: settings.games,
+        seeds,
+        board_size: settings.rules.board_size,
+        modifiers: settings.rules.modifiers.clone(),
+        games_per_second: results.len() as f64 / elapsed,
+        mean_ticks: mean(
+            results.iter().map(|game| game.ticks),
+        ),
+        median_ticks: median(
+            results.iter().map(|game| game.ticks),
+        ),
+        snakes: (0..players)
+            .map(|snake| {
+                snake_report(&bots, &results, snake)
+            })
+            .collect(),
+    };
+
+    if settings.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap()
+        );
+    } else {
+        print_table(&report);
+    }
+}
+
+/// Splits the seeds over `settings.threads` workers.
+/// Every worker starts its own bots, external bots are
+/// separate processes per worker.
+fn simulate(
+    settings: &SimSettings,
+    bots: &BotSettings,
+    seeds: Range<u64>,
+) -> Vec<GameResult> {
+    let threads = settings.threads as u64;
+    let chunk =
+        ((settings.games + threads - 1) / threads).max(1);
+    thread::scope(|scope| {
+        (seeds.start..seeds.end)
+            .step_by(chunk as usize)
+            .map(|start| {
+                let end = (start + chunk).min(seeds.end);
+                scope.spawn(move || {
+                    let mut bots = Bots::spawn(bots);
+                    (start..end)
+                        .map(|seed| {
+                            play_seed(
+                                settings, &mut bots, seed,
+                            )
+                        })
+                        .collect::<Vec<GameResult>>()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    })
+}
+
+fn play_seed(
+    settings: &SimSettings,
+    bots: &mut Bots,
+    seed: u64,
+) -> GameResult {
+    let mut state = GameState::new(
+        settings.rules.clone(),
+        bots.players(),
+        seed,
+    );
+    play(bots, &mut state, settings.max_ticks);
+    GameResult {
+        ticks: state.tick,
+        snakes: state
+            .snakes
+            .iter()
+            .map(|snake| SnakeResult {
+                score: snake.score,
+                reason: snake.game_over,
+            })
+            .collect(),
+    }
+}
+
+fn snake_report(
+    bots: &BotSettings,
+    results: &[GameResult],
+    snake: usize,
+) -> SnakeReport {
+    let scores = || {
+        results.iter().map(move |game| {
+            u64::from(game.snakes[snake].score)
+        })
+    };
+    let mut endings = BTreeMap::new();
+    for game in results {
+        let ending = match game.snakes[snake].reason {
+            Some(reason) => format!("{:?}", reason),
+            None => "Timeout".to_string(),
+        };
+        *endings.entry(ending).or_insert(0) += 1;
+    }
+    let wins = endings.get("Win").copied().unwrap_or(0);
+
+    SnakeReport {
+        controller: bots
+            .bots
+            .iter()
+            .rev()
+            .find(|(index, _)| *index == snake)
+            .map_or("none".to_string(), |(_, kind)| {
+                kind.name()
+            }),
+        mean_score: mean(scores()),
+        median_score: median(scores()),
+        max_score: scores().max().unwrap_or(0) as u32,
+        win_rate: wins as f64 / results.len().max(1) as f64,
+        endings,
+    }
+}
+
+fn mean(values: impl Iterator<Item = u64>) -> f64 {
+    let (sum, count) = values
+        .fold((0, 0), |(sum, count), value| {
+            (sum + value, count + 1)
+        });
+    sum as f64 / u64::max(count, 1) as f64
+}
+
+fn median(values: impl Iterator<Item = u64>) -> f64 {
+    let mut values = values.collect::<Vec<u64>>();
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[middle - 1] + values[middle]) as f64 / 2.0
+    } else {
+        values[middle] as f64
+    }
+}
+
+fn print_table(report: &Report) {
+    println!(
+        "{} games, seeds {}..{}, {}x{} board, \
+         modifiers {:?}",
+        report.games,
+        report.seeds.start,
+        report.seeds.end,
+        report.board_size,
+        report.board_size,
+        report.modifiers
+    );
+    println!(
+        "{:.0} games/s, ticks per game: mean {:.1}, \
+         median {:.1}",
+        report.games_per_second,
+        report.mean_ticks,
+        report.median_ticks
+    );
+    println!();
+    println!(
+        "{:<6}{:<20}{:>8}{:>8}{:>6}{:>8}  endings",
+        "snake",
+        "controller",
+        "mean",
+        "median",
+        "max",
+        "wins"
+    );
+    for (index, snake) in report.snakes.iter().enumerate() {
+        let endings = snake
+            .endings
+            .iter()
+            .map(|(ending, count)| {
+                format!("{} {}", ending, count)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        println!(
+            "{:<6}{:<20}{:>8.2}{:>8.1}{:>6}{:>7.1}%  {}",
+            index,
+            snake.controller,
+            snake.mean_score,
+            snake.median_score,
+            snake.max_score,
+            snake.win_rate * 100.0,
+            endings
+        );
+    }
+}
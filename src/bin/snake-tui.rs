@@ -0,0 +1,384 @@
+//! Plays snake in the terminal, for SSH sessions and
+//! machines without a display. Uses the same rules,
+//! settings and saved runs as the windowed game.
+//!
+//! `snake-tui` plays a classic game, `snake-tui --daily`
+//! the daily challenge.
+
+use std::{
+    io::{self, Stdout, Write},
+    time::{Duration, Instant},
+};
+
+use bevy_snake::{
+    board::Position,
+    daily::{DailyChallenge, DailyDate, DailyHistory},
+    rules::{Direction, GameState, Snake},
+    scoring::{Run, Speedruns},
+    settings::{ControlScheme, GameRules, GameSettings},
+};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal::{
+        self, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+/// Colors for each snake's body, the local player is
+/// always the first.
+const SNAKE_COLORS: [Color; 4] = [
+    Color::Green,
+    Color::Blue,
+    Color::Magenta,
+    Color::Yellow,
+];
+
+enum Mode {
+    Classic,
+    Daily { ranked: bool },
+}
+
+/// Puts the terminal back the way it was, even if the
+/// game panics.
+struct Terminal {
+    out: Stdout,
+}
+
+impl Terminal {
+    fn enter() -> io::Result<Self> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(Terminal { out })
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        execute!(self.out, Show, LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+enum Input {
+    Turn(Direction),
+    Restart,
+    Quit,
+}
+
+fn read_input(
+    timeout: Duration,
+) -> io::Result<Option<Input>> {
+    if !event::poll(timeout)? {
+        return Ok(None);
+    }
+    let input = match event::read()? {
+        Event::Key(KeyEvent { code, modifiers }) => {
+            match code {
+                KeyCode::Up | KeyCode::Char('w') => {
+                    Some(Input::Turn(Direction::Up))
+                }
+                KeyCode::Down | KeyCode::Char('s') => {
+                    Some(Input::Turn(Direction::Down))
+                }
+                KeyCode::Left | KeyCode::Char('a') => {
+                    Some(Input::Turn(Direction::Left))
+                }
+                KeyCode::Right | KeyCode::Char('d') => {
+                    Some(Input::Turn(Direction::Right))
+                }
+                KeyCode::Char('r') | KeyCode::Enter => {
+                    Some(Input::Restart)
+                }
+                KeyCode::Char('c')
+                    if modifiers.contains(
+                        KeyModifiers::CONTROL,
+                    ) =>
+                {
+                    Some(Input::Quit)
+                }
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    Some(Input::Quit)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    Ok(input)
+}
+
+fn main() -> io::Result<()> {
+    let daily =
+        std::env::args().any(|arg| arg == "--daily");
+    let settings = GameSettings::load();
+    let mut runs = Speedruns::load();
+    let mut history = DailyHistory::load();
+
+    let mut terminal = Terminal::enter()?;
+    execute!(terminal.out, Clear(ClearType::All))?;
+    loop {
+        let today = DailyDate::today();
+        let (mode, rules) = if daily {
+            let ranked = history.start_attempt(today);
+            (
+                Mode::Daily { ranked },
+                DailyChallenge::for_date(today).rules,
+            )
+        } else {
+            (Mode::Classic, settings.classic_rules())
+        };
+
+        let started = Instant::now();
+        let finished = play(
+            &mut terminal,
+            rules,
+            settings.control_scheme,
+            &mode,
+            runs.best_score(),
+            started,
+        )?;
+        let (score, time) = finished.result;
+
+        runs.record(Run {
+            time,
+            score,
+            scheme: settings.control_scheme,
+        });
+        if let Mode::Daily { ranked: true } = mode {
+            history.finish_attempt(today, score, time);
+        }
+        if finished.quit {
+            return Ok(());
+        }
+    }
+}
+
+struct Finished {
+    result: (u32, Duration),
+    /// quit instead of asking for another game
+    quit: bool,
+}
+
+fn play(
+    terminal: &mut Terminal,
+    rules: GameRules,
+    scheme: ControlScheme,
+    mode: &Mode,
+    best: u32,
+    started: Instant,
+) -> io::Result<Finished> {
+    let seed = rules.seed.unwrap_or_else(rand::random);
+    let tick = rules.speed.tick_duration();
+    let mut state = GameState::new(rules, 1, seed);
+    let mut next_tick = Instant::now() + tick;
+    let mut turn = None;
+
+    while !state.is_over() {
+        draw(terminal, &state, mode, best, started, false)?;
+        let now = Instant::now();
+        if now >= next_tick {
+            state.step(&[turn.take()]);
+            next_tick += tick;
+            continue;
+        }
+        match read_input(next_tick - now)? {
+            // only the first turn of a tick counts, like
+            // in the windowed game
+            Some(Input::Turn(pressed))
+                if turn.is_none() =>
+            {
+                turn = scheme.steer(
+                    state.snakes[0].direction,
+                    pressed,
+                )
+            }
+            Some(Input::Quit) => {
+                return Ok(Finished {
+                    result: (
+                        state.snakes[0].score,
+                        started.elapsed(),
+                    ),
+                    quit: true,
+                })
+            }
+            _ => {}
+        }
+    }
+
+    let result = (state.snakes[0].score, started.elapsed());
+    draw(terminal, &state, mode, best, started, true)?;
+    loop {
+        match read_input(Duration::from_secs(60))? {
+            Some(Input::Restart) => {
+                return Ok(Finished {
+                    result,
+                    quit: false,
+                })
+            }
+            Some(Input::Quit) => {
+                return Ok(Finished { result, quit: true })
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(
+    terminal: &mut Terminal,
+    state: &GameState,
+    mode: &Mode,
+    best: u32,
+    started: Instant,
+    over: bool,
+) -> io::Result<()> {
+    let out = &mut terminal.out;
+    let size = state.rules.board_size;
+    queue!(out, ResetColor)?;
+
+    // every cell is two columns wide so the board looks
+    // square
+    let width = usize::from(size) * 2;
+    let border = "─".repeat(width);
+    queue!(
+        out,
+        MoveTo(0, 0),
+        Print(format!("┌{}┐", border)),
+        MoveTo(0, size + 1),
+        Print(format!("└{}┘", border))
+    )?;
+    // redrawing every cell instead of clearing the
+    // screen keeps the board from flickering
+    let empty = format!("│{}│", " ".repeat(width));
+    for row in 1..=size {
+        queue!(out, MoveTo(0, row), Print(&empty))?;
+    }
+
+    let cell = |position: Position| {
+        MoveTo(1 + position.x * 2, size - position.y)
+    };
+    for wall in &state.walls {
+        queue!(
+            out,
+            cell(*wall),
+            SetForegroundColor(Color::DarkGrey),
+            Print("██")
+        )?;
+    }
+    for food in &state.food {
+        queue!(
+            out,
+            cell(*food),
+            SetForegroundColor(Color::Red),
+            Print("● ")
+        )?;
+    }
+    for (index, snake) in state.snakes.iter().enumerate() {
+        let color = if snake.is_alive() {
+            SNAKE_COLORS[index % SNAKE_COLORS.len()]
+        } else {
+            Color::DarkGrey
+        };
+        queue!(out, SetForegroundColor(color))?;
+        for (segment, position) in
+            snake.body.segments.iter().enumerate()
+        {
+            queue!(
+                out,
+                cell(*position),
+                Print(segment_glyph(snake, segment))
+            )?;
+        }
+    }
+
+    let score = state.snakes[0].score;
+    let mode = match mode {
+        Mode::Classic => "Classic".to_string(),
+        Mode::Daily { ranked: true } => {
+            format!("Daily {}", DailyDate::today())
+        }
+        Mode::Daily { ranked: false } => {
+            format!(
+                "Daily {} (practice)",
+                DailyDate::today()
+            )
+        }
+    };
+    queue!(
+        out,
+        ResetColor,
+        MoveTo(0, size + 2),
+        Print(format!(
+            "{}  Score: {}  Best: {}  Time: {}s",
+            mode,
+            score,
+            best.max(score),
+            started.elapsed().as_secs()
+        )),
+        Clear(ClearType::UntilNewLine),
+        MoveTo(0, size + 3),
+        Print(if over {
+            "Game over! r: play again  q: quit"
+        } else {
+            "arrows/wasd: move  q: quit"
+        }),
+        Clear(ClearType::UntilNewLine)
+    )?;
+    out.flush()
+}
+
+/// Box-drawing glyphs joining each segment to its
+/// neighbours. Cells are two columns, the second one
+/// continues the line when the segment connects to the
+/// right.
+fn segment_glyph(snake: &Snake, index: usize) -> String {
+    let segments = &snake.body.segments;
+    let position = segments[index];
+    let towards = |other: Option<&Position>| {
+        other.and_then(|other| {
+            Direction::ALL.into_iter().find(|direction| {
+                direction.step(position, u16::MAX)
+                    == Some(*other)
+            })
+        })
+    };
+    let previous = index
+        .checked_sub(1)
+        .and_then(|previous| segments.get(previous));
+    let links = [
+        towards(previous),
+        towards(segments.get(index + 1)),
+    ];
+    let has = |direction| links.contains(&Some(direction));
+
+    let first = if index == 0 {
+        match snake.direction {
+            Direction::Up => '▲',
+            Direction::Down => '▼',
+            Direction::Left => '◀',
+            Direction::Right => '▶',
+        }
+    } else {
+        use Direction::*;
+        match (has(Up), has(Down), has(Left), has(Right)) {
+            (true, true, _, _) => '┃',
+            (_, _, true, true) => '━',
+            (true, _, true, _) => '┛',
+            (true, _, _, true) => '┗',
+            (_, true, true, _) => '┓',
+            (_, true, _, true) => '┏',
+            // the tail only has one neighbour
+            (true, ..) => '╹',
+            (_, true, ..) => '╻',
+            (_, _, true, _) => '╸',
+            _ => '╺',
+        }
+    };
+    let second =
+        if has(Direction::Right) { '━' } else { ' ' };
+    format!("{}{}", first, second)
+}
@@ -3,67 +3,138 @@ use bevy::{
     prelude::*,
     sprite::{SpriteSheetBundle, TextureAtlasSprite},
 };
-use itertools::Itertools;
 use rand::{
     distributions::WeightedIndex, prelude::Distribution,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    assets::ImageAssets, colors::MATERIALS, food::Food,
+    colors::tinted,
+    common::{GameRng, LocalPlayer, MainCamera},
+    food::Food,
+    grass::GrassTiles,
+    settings::GameRules,
+    skins::Skins,
+    snake::{SnakeSegment, SnakeTextureSelection},
+    theme::ActiveTheme,
 };
 
-const TILE_SIZE: f32 = 30.0;
+pub(crate) const TILE_SIZE: f32 = 30.0;
 const TILE_SPACER: f32 = 0.0;
+/// Room left around a board the camera zooms out for
+const CAMERA_MARGIN: f32 = 1.1;
 
 #[derive(
-    Debug, PartialEq, Copy, Clone, Eq, Hash, Component,
+    Debug,
+    PartialEq,
+    Copy,
+    Clone,
+    Eq,
+    Hash,
+    Component,
+    Serialize,
+    Deserialize,
 )]
 pub struct Position {
-    pub x: u8,
-    pub y: u8,
+    pub x: u16,
+    pub y: u16,
 }
 
+#[derive(Component)]
+pub struct Wall;
+
 #[derive(Component)]
 pub struct Board {
-    pub size: u8,
+    pub size: u16,
     physical_size: f32,
 }
 
 impl Board {
-    fn new(size: u8) -> Self {
+    fn new(size: u16) -> Self {
         let physical_size = f32::from(size) * TILE_SIZE
-            + f32::from(size + 1) * TILE_SPACER;
+            + (f32::from(size) + 1.0) * TILE_SPACER;
         Board {
             size,
             physical_size,
         }
     }
-    fn cell_position_to_physical(&self, pos: u8) -> f32 {
+    pub fn cell_center(&self, position: Position) -> Vec2 {
+        Vec2::new(
+            self.cell_position_to_physical(position.x),
+            self.cell_position_to_physical(position.y),
+        )
+    }
+    fn cell_position_to_physical(&self, pos: u16) -> f32 {
         let offset =
             -self.physical_size / 2.0 + 0.5 * TILE_SIZE;
 
         offset
             + f32::from(pos) * TILE_SIZE
-            + f32::from(pos + 1) * TILE_SPACER
+            + (f32::from(pos) + 1.0) * TILE_SPACER
+    }
+}
+
+/// Zooms the camera out until the board fits in the
+/// window. Boards that already fit are drawn at their
+/// own size.
+pub fn fit_camera(
+    windows: Res<Windows>,
+    boards: Query<&Board>,
+    mut cameras: Query<
+        &mut OrthographicProjection,
+        With<MainCamera>,
+    >,
+) {
+    let (window, board) =
+        match (windows.get_primary(), boards.iter().next())
+        {
+            (Some(window), Some(board)) => (window, board),
+            _ => return,
+        };
+    let shortest = window.width().min(window.height());
+    let scale = (board.physical_size * CAMERA_MARGIN
+        / shortest)
+        .max(1.0);
+    for mut projection in cameras.iter_mut() {
+        if projection.scale != scale {
+            projection.scale = scale;
+        }
     }
 }
 
+/// Spawns the board for the current `GameRules`,
+/// replacing any board that already exists. Grass is
+/// drawn from `GameRng` with the theme's weights so
+/// seeded games share a layout, `grass` turns it into
+/// meshes.
 pub fn spawn_board(
     mut commands: Commands,
-    images: Res<ImageAssets>,
+    rules: Res<GameRules>,
+    theme: Res<ActiveTheme>,
+    mut rng: ResMut<GameRng>,
+    boards: Query<Entity, With<Board>>,
 ) {
-    let board = Board::new(20);
+    for entity in boards.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
 
-    let grass = images.grass.clone();
+    let board = Board::new(rules.board_size);
 
-    let mut rng = rand::thread_rng();
-    let weights = vec![3, 3, 1];
-    let dist = WeightedIndex::new(weights).unwrap();
+    let rng = &mut rng.0;
+    let dist =
+        WeightedIndex::new(&theme.source.grass.weights)
+            .unwrap();
+    // picked in the same order as when every cell was
+    // its own sprite, so seeds keep their layout
+    let size = usize::from(board.size);
+    let tiles = (0..size * size)
+        .map(|_| dist.sample(rng))
+        .collect::<Vec<usize>>();
 
     commands
         .spawn_bundle(SpriteBundle {
             sprite: Sprite {
-                color: MATERIALS.board,
+                color: theme.palette.board,
                 custom_size: Some(Vec2::new(
                     board.physical_size,
                     board.physical_size,
@@ -72,46 +143,51 @@ pub fn spawn_board(
             },
             ..Default::default()
         })
-        .with_children(|builder| {
-            for tile in (0..board.size)
-                .cartesian_product(0..board.size)
-            {
-                builder.spawn_bundle(SpriteSheetBundle {
-                    texture_atlas: grass.clone(),
-                    sprite: TextureAtlasSprite {
-                        index: dist.sample(&mut rng),
-                        custom_size: Some(Vec2::new(
-                            TILE_SIZE, TILE_SIZE,
-                        )),
-                        ..TextureAtlasSprite::default()
-                    },
-                    transform: Transform::from_xyz(
-                        board.cell_position_to_physical(
-                            tile.0,
-                        ),
-                        board.cell_position_to_physical(
-                            tile.1,
-                        ),
-                        1.0,
-                    ),
-                    ..Default::default()
-                });
-            }
-        })
-        .insert(board);
+        .insert(board)
+        .insert(GrassTiles(tiles));
 }
 
+/// Fills in a segment sprite on an entity reserved
+/// with `Commands::spawn`, so its id is known straight
+/// away. It starts out as a body piece of the snake's
+/// skin, `render_snake_segments` picks the right one.
 pub struct SpawnSnakeSegment {
+    pub entity: Entity,
+    pub snake: usize,
     pub position: Position,
 }
 
 impl Command for SpawnSnakeSegment {
     fn write(self, world: &mut World) {
-        let snake = world
-            .get_resource::<ImageAssets>()
-            .unwrap()
-            .snake
-            .clone();
+        let (atlas, index, color) = {
+            let local = world
+                .get_resource::<LocalPlayer>()
+                .unwrap()
+                .0;
+            let selection = world
+                .get_resource::<SnakeTextureSelection>()
+                .unwrap();
+            let tint = world
+                .get_resource::<ActiveTheme>()
+                .unwrap()
+                .palette
+                .snake_tint;
+            // drawn once a skin has loaded
+            match world
+                .get_resource::<Skins>()
+                .unwrap()
+                .for_snake(selection, self.snake, local)
+            {
+                Some(skin) => (
+                    skin.atlas.clone(),
+                    skin.frames.body,
+                    tinted(skin.tint, tint),
+                ),
+                None => {
+                    (Handle::default(), 0, Color::WHITE)
+                }
+            }
+        };
 
         let (x, y) = {
             let board = world
@@ -129,13 +205,19 @@ impl Command for SpawnSnakeSegment {
             )
         };
 
-        world
-            .spawn()
+        let mut entity =
+            match world.get_entity_mut(self.entity) {
+                Some(entity) => entity,
+                // despawned before it was filled in
+                None => return,
+            };
+        entity
             .insert_bundle(SpriteSheetBundle {
-                texture_atlas: snake,
+                texture_atlas: atlas,
                 transform: Transform::from_xyz(x, y, 2.0),
                 sprite: TextureAtlasSprite {
-                    index: 116,
+                    index,
+                    color,
                     custom_size: Some(Vec2::new(
                         TILE_SIZE, TILE_SIZE,
                     )),
@@ -143,21 +225,76 @@ impl Command for SpawnSnakeSegment {
                 },
                 ..Default::default()
             })
-            .insert(self.position);
+            .insert(self.position)
+            .insert(SnakeSegment { snake: self.snake });
     }
 }
 
+/// Fills in an apple sprite on a reserved entity, like
+/// `SpawnSnakeSegment`.
 pub struct SpawnApple {
+    pub entity: Entity,
     pub position: Position,
 }
 
 impl Command for SpawnApple {
     fn write(self, world: &mut World) {
-        let apple = world
-            .get_resource::<ImageAssets>()
+        let (apple, tint) = {
+            let theme = world
+                .get_resource::<ActiveTheme>()
+                .unwrap();
+            (theme.apple.clone(), theme.palette.food_tint)
+        };
+        let (x, y) = {
+            let board = world
+                .query::<&Board>()
+                .iter(&world)
+                .next()
+                .unwrap();
+            (
+                board.cell_position_to_physical(
+                    self.position.x,
+                ),
+                board.cell_position_to_physical(
+                    self.position.y,
+                ),
+            )
+        };
+
+        let mut entity =
+            match world.get_entity_mut(self.entity) {
+                Some(entity) => entity,
+                None => return,
+            };
+        entity
+            .insert_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color: tint,
+                    custom_size: Some(Vec2::new(
+                        TILE_SIZE, TILE_SIZE,
+                    )),
+                    ..Sprite::default()
+                },
+                texture: apple,
+                transform: Transform::from_xyz(x, y, 2.0),
+                ..Default::default()
+            })
+            .insert(self.position)
+            .insert(Food);
+    }
+}
+
+pub struct SpawnWall {
+    pub position: Position,
+}
+
+impl Command for SpawnWall {
+    fn write(self, world: &mut World) {
+        let color = world
+            .get_resource::<ActiveTheme>()
             .unwrap()
-            .apple
-            .clone();
+            .palette
+            .wall;
         let (x, y) = {
             let board = world
                 .query::<&Board>()
@@ -174,22 +311,20 @@ impl Command for SpawnApple {
             )
         };
 
-        // Get resources, edit entity, etc.
         world
             .spawn()
             .insert_bundle(SpriteBundle {
                 sprite: Sprite {
-                    // color: MATERIALS.food,
+                    color,
                     custom_size: Some(Vec2::new(
                         TILE_SIZE, TILE_SIZE,
                     )),
                     ..Sprite::default()
                 },
-                texture: apple,
                 transform: Transform::from_xyz(x, y, 2.0),
                 ..Default::default()
             })
             .insert(self.position)
-            .insert(Food);
+            .insert(Wall);
     }
 }
@@ -0,0 +1,601 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, Command, Stdio},
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    battlesnake::BattlesnakeBot,
+    board::Position,
+    rules::{Direction, GameState, Snake},
+    settings::GameRules,
+};
+
+pub const DEFAULT_BUDGET: Duration =
+    Duration::from_millis(50);
+/// Headless games end here even if the bots would
+/// keep circling forever.
+const MAX_HEADLESS_TICKS: u64 = 100_000;
+
+pub struct BotPlugin;
+
+impl Plugin for BotPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = BotSettings::from_args();
+        app.insert_resource(Bots::spawn(&settings))
+            .insert_resource(settings);
+    }
+}
+
+/// External bots, set from the command line:
+///
+/// `bevy-snake --bot ./my_bot` controls snake 0
+/// `bevy-snake --bot 1:"python3 bot.py"` adds an
+/// opponent controlled by a second bot
+/// `bevy-snake --battlesnake 1:http://localhost:8000`
+/// adds an opponent played by a Battlesnake server
+/// `bevy-snake --builtin 1:greedy` adds an opponent
+/// played by one of the `BuiltinBot`s
+///
+/// `--bot-budget <ms>` is how long a bot gets to
+/// answer each tick, `--headless` plays a single game
+/// without a window and prints the result.
+#[derive(Debug, Clone)]
+pub struct BotSettings {
+    pub bots: Vec<(usize, BotKind)>,
+    pub budget: Duration,
+    pub headless: bool,
+    pub seed: Option<u64>,
+}
+
+impl Default for BotSettings {
+    fn default() -> Self {
+        BotSettings {
+            bots: vec![],
+            budget: DEFAULT_BUDGET,
+            headless: false,
+            seed: None,
+        }
+    }
+}
+
+impl BotSettings {
+    pub fn from_args() -> Self {
+        let mut settings = BotSettings::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => settings.headless = true,
+                "--bot" => {
+                    if let Some((snake, command)) = args
+                        .next()
+                        .and_then(|bot| parse_bot(&bot))
+                    {
+                        settings.bots.push((
+                            snake,
+                            BotKind::Process(command),
+                        ));
+                    }
+                }
+                "--battlesnake" => {
+                    if let Some((snake, url)) = args
+                        .next()
+                        .and_then(|bot| parse_bot(&bot))
+                    {
+                        settings.bots.push((
+                            snake,
+                            BotKind::Battlesnake(url),
+                        ));
+                    }
+                }
+                "--builtin" => {
+                    if let Some((snake, name)) = args
+                        .next()
+                        .and_then(|bot| parse_bot(&bot))
+                    {
+                        match BuiltinBot::from_name(&name) {
+                            Some(bot) => {
+                                settings.bots.push((
+                                    snake,
+                                    BotKind::Builtin(bot),
+                                ))
+                            }
+                            None => error!(
+                                %name,
+                                "unknown builtin bot"
+                            ),
+                        }
+                    }
+                }
+                "--bot-budget" => {
+                    if let Some(Ok(ms)) =
+                        args.next().map(|ms| ms.parse())
+                    {
+                        settings.budget =
+                            Duration::from_millis(ms);
+                    }
+                }
+                "--seed" => {
+                    if let Some(Ok(seed)) =
+                        args.next().map(|seed| seed.parse())
+                    {
+                        settings.seed = Some(seed);
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+/// `1:./bot` is the bot for snake 1, a command without
+/// an index controls snake 0.
+fn parse_bot(bot: &str) -> Option<(usize, String)> {
+    let (snake, command) = match bot.split_once(':') {
+        Some((snake, command)) => match snake.parse() {
+            Ok(snake) => (snake, command.to_string()),
+            Err(_) => (0, bot.to_string()),
+        },
+        None => (0, bot.to_string()),
+    };
+    if snake >= GameRules::MAX_PLAYERS {
+        error!(
+            snake,
+            max = GameRules::MAX_PLAYERS,
+            "no such snake for a bot"
+        );
+        return None;
+    }
+    Some((snake, command))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BotKind {
+    Process(String),
+    Battlesnake(String),
+    Builtin(BuiltinBot),
+}
+
+impl BotKind {
+    pub fn name(&self) -> String {
+        match self {
+            BotKind::Process(command) => command.clone(),
+            BotKind::Battlesnake(url) => url.clone(),
+            BotKind::Builtin(bot) => bot.name().to_string(),
+        }
+    }
+}
+
+/// Simple bots that run in-process, for opponents and
+/// as baselines when tuning other bots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinBot {
+    /// never turns
+    Straight,
+    /// picks any move that doesn't die straight away
+    Random,
+    /// heads for the nearest food along safe moves
+    Greedy,
+}
+
+impl BuiltinBot {
+    pub const ALL: [BuiltinBot; 3] = [
+        BuiltinBot::Straight,
+        BuiltinBot::Random,
+        BuiltinBot::Greedy,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinBot::Straight => "straight",
+            BuiltinBot::Random => "random",
+            BuiltinBot::Greedy => "greedy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BuiltinBot> {
+        BuiltinBot::ALL
+            .into_iter()
+            .find(|bot| bot.name() == name)
+    }
+
+    pub fn decide(
+        &self,
+        state: &GameState,
+        snake: usize,
+    ) -> Option<Direction> {
+        let current = &state.snakes[snake];
+        let safe = Direction::ALL
+            .into_iter()
+            .filter_map(|direction| {
+                let next = direction.step(
+                    current.head(),
+                    state.rules.board_size,
+                )?;
+                (!state.is_blocked(next))
+                    .then_some((direction, next))
+            })
+            .collect::<Vec<(Direction, Position)>>();
+
+        match self {
+            BuiltinBot::Straight => None,
+            BuiltinBot::Random => {
+                // seeded from the state so games stay
+                // reproducible
+                let mut rng = ChaCha8Rng::seed_from_u64(
+                    state.checksum() ^ snake as u64,
+                );
+                safe.choose(&mut rng)
+                    .map(|(direction, _)| *direction)
+            }
+            BuiltinBot::Greedy => safe
+                .into_iter()
+                .min_by_key(|(_, next)| {
+                    state
+                        .food
+                        .iter()
+                        .map(|food| {
+                            u32::from(
+                                next.x.abs_diff(food.x),
+                            ) + u32::from(
+                                next.y.abs_diff(food.y),
+                            )
+                        })
+                        .min()
+                })
+                .map(|(direction, _)| direction),
+        }
+    }
+}
+
+/// What a bot is sent every tick, one JSON object per
+/// line. `you` is the index of its snake in `snakes`.
+/// Replies name the tick they answer, e.g.
+/// `{"tick":12,"move":"up"}`.
+#[derive(Serialize)]
+struct BotRequest<'a> {
+    tick: u64,
+    you: usize,
+    board_size: u16,
+    snakes: &'a [Snake],
+    food: &'a [Position],
+    walls: &'a [Position],
+}
+
+#[derive(Deserialize)]
+struct BotReply {
+    tick: u64,
+    #[serde(rename = "move")]
+    direction: String,
+}
+
+fn parse_direction(direction: &str) -> Option<Direction> {
+    match direction.to_lowercase().as_str() {
+        "up" => Some(Direction::Up),
+        "down" => Some(Direction::Down),
+        "left" => Some(Direction::Left),
+        "right" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
+/// A bot running as a child process, talking line-delimited
+/// JSON over stdin and stdout.
+pub struct ExternalBot {
+    child: Child,
+    // channels aren't `Sync`, which resources have to be
+    requests: Mutex<SyncSender<String>>,
+    replies: Mutex<Receiver<String>>,
+    budget: Duration,
+    asked: u64,
+}
+
+impl ExternalBot {
+    pub fn spawn(
+        command: &str,
+        budget: Duration,
+    ) -> io::Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "empty bot command",
+            )
+        })?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // and writing too, for a bot that stops reading
+        // its stdin
+        let (requests, pending) = mpsc::sync_channel(1);
+        thread::spawn(move || {
+            for request in pending {
+                let written: io::Result<()> =
+                    writeln!(stdin, "{}", request)
+                        .and_then(|_| stdin.flush());
+                if written.is_err() {
+                    break;
+                }
+            }
+        });
+
+        // reading happens on its own thread so a bot
+        // that never answers can't block the game
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if sender.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(ExternalBot {
+            child,
+            requests: Mutex::new(requests),
+            replies: Mutex::new(receiver),
+            budget,
+            asked: 0,
+        })
+    }
+
+    pub fn ask(&mut self, state: &GameState, snake: usize) {
+        self.asked = state.tick;
+        let request = serde_json::to_string(&BotRequest {
+            tick: state.tick,
+            you: snake,
+            board_size: state.rules.board_size,
+            snakes: &state.snakes,
+            food: &state.food,
+            walls: &state.walls,
+        })
+        .unwrap();
+        // a bot still stuck on an earlier request misses
+        // this one
+        self.requests
+            .get_mut()
+            .unwrap()
+            .try_send(request)
+            .ok();
+    }
+
+    /// The answer to the last `ask`, made at `asked`.
+    /// `None`, which keeps the current direction, if it
+    /// doesn't come within the budget or isn't a
+    /// direction. Late answers to earlier ticks are
+    /// skipped.
+    pub fn answer(
+        &mut self,
+        asked: Instant,
+    ) -> Option<Direction> {
+        let deadline = asked + self.budget;
+        let replies = self.replies.get_mut().unwrap();
+        loop {
+            let wait = deadline
+                .saturating_duration_since(Instant::now());
+            let reply = replies.recv_timeout(wait).ok()?;
+            match serde_json::from_str::<BotReply>(&reply) {
+                Ok(reply) if reply.tick == self.asked => {
+                    return parse_direction(
+                        &reply.direction,
+                    )
+                }
+                Ok(reply) => {
+                    debug!(
+                        tick = reply.tick,
+                        asked = self.asked,
+                        "stale bot reply"
+                    )
+                }
+                Err(error) => {
+                    debug!(
+                        %error,
+                        %reply,
+                        "malformed bot reply"
+                    );
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+pub enum Bot {
+    Process(ExternalBot),
+    Battlesnake(BattlesnakeBot),
+    Builtin(BuiltinBot),
+}
+
+#[derive(Default)]
+pub struct Bots(pub Vec<Option<Bot>>);
+
+impl Bots {
+    /// Bots that fail to start are logged and leave
+    /// their snake without a controller.
+    pub fn spawn(settings: &BotSettings) -> Self {
+        let mut bots = Bots::default();
+        for (snake, kind) in settings.bots.iter() {
+            if bots.0.len() <= *snake {
+                bots.0.resize_with(snake + 1, || None);
+            }
+            bots.0[*snake] = match kind {
+                BotKind::Process(command) => {
+                    match ExternalBot::spawn(
+                        command,
+                        settings.budget,
+                    ) {
+                        Ok(bot) => Some(Bot::Process(bot)),
+                        Err(error) => {
+                            error!(
+                                %command,
+                                %error,
+                                "failed to start bot"
+                            );
+                            None
+                        }
+                    }
+                }
+                BotKind::Builtin(bot) => {
+                    Some(Bot::Builtin(*bot))
+                }
+                BotKind::Battlesnake(url) => Some(
+                    Bot::Battlesnake(BattlesnakeBot::new(
+                        url,
+                        settings.budget,
+                    )),
+                ),
+            };
+        }
+        bots
+    }
+
+    pub fn controls(&self, snake: usize) -> bool {
+        matches!(self.0.get(snake), Some(Some(_)))
+    }
+
+    /// Snakes needed for every bot to have one. Local
+    /// games are played with at least this many.
+    pub fn players(&self) -> usize {
+        self.0.len().max(1)
+    }
+
+    pub fn start(&mut self, state: &GameState) {
+        for (snake, bot) in self.0.iter_mut().enumerate() {
+            if let Some(Bot::Battlesnake(bot)) = bot {
+                if snake < state.snakes.len() {
+                    bot.start(state, snake);
+                }
+            }
+        }
+    }
+
+    pub fn end(&mut self, state: &GameState) {
+        for (snake, bot) in self.0.iter_mut().enumerate() {
+            if let Some(Bot::Battlesnake(bot)) = bot {
+                if snake < state.snakes.len() {
+                    bot.end(state, snake);
+                }
+            }
+        }
+    }
+
+    /// One input per snake in `state`, `None` for
+    /// snakes without a bot or that are out. All bots
+    /// think at the same time, so a tick waits for one
+    /// budget however many bots there are.
+    pub fn decide(
+        &mut self,
+        state: &GameState,
+    ) -> Vec<Option<Direction>> {
+        let alive = |snake: usize| {
+            state
+                .snakes
+                .get(snake)
+                .map_or(false, |snake| snake.is_alive())
+        };
+        let asked = Instant::now();
+        for (snake, bot) in self.0.iter_mut().enumerate() {
+            if let (Some(Bot::Process(bot)), true) =
+                (bot, alive(snake))
+            {
+                bot.ask(state, snake);
+            }
+        }
+
+        let mut decisions = vec![None; state.snakes.len()];
+        thread::scope(|scope| {
+            let mut rest = vec![];
+            for ((snake, bot), decision) in self
+                .0
+                .iter_mut()
+                .enumerate()
+                .zip(decisions.iter_mut())
+                .filter(|((snake, _), _)| alive(*snake))
+            {
+                match bot {
+                    Some(Bot::Battlesnake(bot)) => {
+                        scope.spawn(move || {
+                            *decision =
+                                bot.decide(state, snake);
+                        });
+                    }
+                    Some(bot) => {
+                        rest.push((snake, bot, decision))
+                    }
+                    None => {}
+                }
+            }
+            for (snake, bot, decision) in rest {
+                *decision = match bot {
+                    Bot::Process(bot) => bot.answer(asked),
+                    Bot::Builtin(bot) => {
+                        bot.decide(state, snake)
+                    }
+                    Bot::Battlesnake(_) => continue,
+                };
+            }
+        });
+        decisions
+    }
+}
+
+pub fn play(
+    bots: &mut Bots,
+    state: &mut GameState,
+    max_ticks: u64,
+) {
+    bots.start(state);
+    while !state.is_over() && state.tick < max_ticks {
+        let inputs = bots.decide(state);
+        state.step(&inputs);
+    }
+    bots.end(state);
+}
+
+pub fn run_headless(settings: &BotSettings) {
+    let mut bots = Bots::spawn(settings);
+    let seed = settings.seed.unwrap_or_else(rand::random);
+    let mut state = GameState::new(
+        GameRules::default(),
+        bots.players(),
+        seed,
+    );
+    play(&mut bots, &mut state, MAX_HEADLESS_TICKS);
+
+    println!(
+        "{}",
+        serde_json::json!({
+            "seed": seed,
+            "ticks": state.tick,
+            "snakes": state.snakes,
+        })
+    );
+}
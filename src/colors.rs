@@ -1,24 +1,88 @@
 use bevy::prelude::Color;
 
 pub struct Materials {
+    pub background: Color,
     pub board: Color,
     pub tile_placeholder: Color,
     pub tile_placeholder_dark: Color,
     pub tile: Color,
     pub food: Color,
+    pub wall: Color,
     pub none: Color,
     pub screen: Color,
 }
 pub const MATERIALS: Materials = Materials {
+    background: Color::rgb(0.52, 0.73, 0.17),
     board: Color::rgb(0.7, 0.7, 0.8),
     tile_placeholder: Color::rgb(0.75, 0.75, 0.9),
     tile_placeholder_dark: Color::rgb(0.70, 0.70, 0.85),
     tile: Color::rgb(0.9, 0.9, 1.0),
     food: Color::rgb(0.9, 0.1, 0.1),
+    wall: Color::rgb(0.42, 0.36, 0.31),
     none: Color::NONE,
     screen: Color::rgba(0.0, 0.0, 0.0, 0.2),
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: Color,
+    pub board: Color,
+    pub wall: Color,
+    pub food: Color,
+    /// The two alternating cells of a plain grid board
+    pub grid: [Color; 2],
+    /// Drawn behind every snake segment, so snakes
+    /// stand out by shape and not just by color
+    pub outline: Option<Color>,
+    /// A diamond drawn behind every apple
+    pub food_cue: Option<Color>,
+    /// Multiplied into the skin's own tint
+    pub snake_tint: Color,
+    pub food_tint: Color,
+}
+
+pub fn tinted(color: Color, tint: Color) -> Color {
+    Color::rgba(
+        color.r() * tint.r(),
+        color.g() * tint.g(),
+        color.b() * tint.b(),
+        color.a() * tint.a(),
+    )
+}
+
+/// Blue tinted snakes and orange apples on a grey grid,
+/// colors that stay apart with the common kinds of
+/// colorblindness.
+pub const COLOR_SAFE: Palette = Palette {
+    background: Color::rgb(0.12, 0.16, 0.22),
+    board: Color::rgb(0.35, 0.38, 0.42),
+    wall: Color::rgb(0.0, 0.0, 0.0),
+    food: Color::rgb(0.9, 0.62, 0.0),
+    grid: [
+        Color::rgb(0.8, 0.8, 0.8),
+        Color::rgb(0.7, 0.7, 0.7),
+    ],
+    outline: Some(Color::rgb(0.0, 0.45, 0.7)),
+    food_cue: Some(Color::rgb(0.94, 0.89, 0.26)),
+    snake_tint: Color::rgb(0.35, 0.6, 1.0),
+    food_tint: Color::rgb(1.0, 0.7, 0.2),
+};
+
+pub const HIGH_CONTRAST: Palette = Palette {
+    background: Color::rgb(0.0, 0.0, 0.0),
+    board: Color::rgb(0.1, 0.1, 0.1),
+    wall: Color::rgb(1.0, 1.0, 1.0),
+    food: Color::rgb(1.0, 0.85, 0.0),
+    grid: [
+        Color::rgb(0.0, 0.0, 0.0),
+        Color::rgb(0.15, 0.15, 0.15),
+    ],
+    outline: Some(Color::rgb(1.0, 1.0, 1.0)),
+    food_cue: Some(Color::rgb(1.0, 0.85, 0.0)),
+    snake_tint: Color::rgb(1.0, 1.0, 1.0),
+    food_tint: Color::rgb(1.0, 1.0, 0.0),
+};
+
 pub struct ButtonMaterials {
     pub none: Color,
     pub normal: Color,
@@ -1,3 +1,7 @@
+use bevy::prelude::Component;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
 // GameStates and FixedTimesteps can not be used
 // together yes, instead use iyes crate https://canary.discord.com/channels/691052431525675048/956767127291965500/956770647911059477
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -11,4 +15,44 @@ pub enum RunState {
 pub struct Game {
     pub score: u32,
     pub score_best: u32,
+    /// `score_best` when this game started
+    pub best_at_start: u32,
+}
+
+pub struct NewBestEvent(pub u32);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    Daily {
+        ranked: bool,
+    },
+    /// lockstep multiplayer, see `net`
+    Lan,
+}
+
+/// The single source of randomness for anything that
+/// affects the layout of a game (grass, walls, food).
+/// Reseeded from `GameRules::seed` whenever a game
+/// starts so seeded games are reproducible.
+pub struct GameRng(pub ChaCha8Rng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(ChaCha8Rng::from_entropy())
+    }
 }
+
+/// Stops the snakes without leaving the game. Only
+/// offline games can be paused.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Paused(pub bool);
+
+#[derive(Component)]
+pub struct MainCamera;
+
+/// Index of the snake this machine controls. Always 0
+/// outside of LAN games.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LocalPlayer(pub usize);
@@ -1,52 +1,62 @@
 use bevy::prelude::*;
 
-use crate::snake::SnakeBody;
+use crate::{
+    actions::Actions,
+    common::LocalPlayer,
+    rules::{Direction, GameState},
+    settings::{ControlScheme, GameSettings},
+};
 
-pub struct LastKeyPress(pub KeyCode);
+/// The direction the local snake turns to on the next
+/// tick.
+pub struct LastTurn(pub Direction);
 
-impl Default for LastKeyPress {
+impl Default for LastTurn {
     fn default() -> Self {
-        Self(KeyCode::Right)
+        Self(Direction::Right)
     }
 }
 
+pub fn can_turn(
+    state: &GameState,
+    snake: usize,
+    direction: Direction,
+) -> bool {
+    let body = &state.snakes[snake].body;
+    let head = body.segments[0];
+    match body.segments.get(1) {
+        Some(neck) => {
+            direction.step(head, u16::MAX) != Some(*neck)
+        }
+        None => true,
+    }
+}
+
+/// Relative turns count presses rather than held
+/// directions, and turn from the direction already
+/// queued for the next tick.
 pub fn user_input(
-    input: Res<Input<KeyCode>>,
-    snake: Res<SnakeBody>,
-    mut last_pressed: ResMut<LastKeyPress>,
+    actions: Res<Actions>,
+    settings: Res<GameSettings>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    mut last_turn: ResMut<LastTurn>,
 ) {
-    let head = snake.segments[0];
-    let neck = snake.segments.get(1);
-
-    if input.pressed(KeyCode::Up) {
-        match (head, neck) {
-            (h, Some(n)) if (h.y + 1) == n.y => {}
-            _ => {
-                last_pressed.0 = KeyCode::Up;
-            }
-        }
-    } else if input.pressed(KeyCode::Down) {
-        match (head, neck) {
-            (h, Some(n))
-                if h.y != 0 && (h.y - 1) == n.y => {}
-            _ => {
-                last_pressed.0 = KeyCode::Down;
-            }
-        }
-    } else if input.pressed(KeyCode::Left) {
-        match (head, neck) {
-            (h, Some(n))
-                if h.x != 0 && (h.x - 1) == n.x => {}
-            _ => {
-                last_pressed.0 = KeyCode::Left;
-            }
-        }
-    } else if input.pressed(KeyCode::Right) {
-        match (head, neck) {
-            (h, Some(n)) if (h.x + 1) == n.x => {}
-            _ => {
-                last_pressed.0 = KeyCode::Right;
-            }
+    let scheme = settings.control_scheme;
+    let pressed = match scheme {
+        ControlScheme::Absolute => actions.direction(),
+        ControlScheme::Relative => {
+            actions.just_pressed_direction()
         }
+    };
+    let direction = match pressed.and_then(|pressed| {
+        scheme.steer(last_turn.0, pressed)
+    }) {
+        Some(direction) => direction,
+        None => return,
+    };
+
+    if can_turn(&state, local.0, direction) {
+        last_turn.0 = direction;
     }
 }
@@ -0,0 +1,381 @@
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{Game, GameMode, RunState},
+    persistence,
+    scoring::Timer,
+    settings::{GameRules, GameSpeed, Modifier},
+};
+
+const HISTORY_FILE: &str = "daily_history.ron";
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DailyHistory::load())
+            .add_system_set(
+                SystemSet::on_enter(RunState::Playing)
+                    .with_system(start_daily_attempt),
+            )
+            .add_system_set(
+                SystemSet::on_exit(RunState::Playing)
+                    .with_system(finish_daily_attempt),
+            );
+    }
+}
+
+/// A calendar day in UTC, so the whole team shares the
+/// same challenge regardless of timezone.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub struct DailyDate {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl DailyDate {
+    pub fn today() -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        DailyDate::from_days((seconds / 86_400) as i64)
+    }
+
+    /// Converts days since 1970-01-01 into a calendar
+    /// date.
+    /// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524
+            - doe / 146_096)
+            / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        DailyDate {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    pub fn days(&self) -> i64 {
+        let month = i64::from(self.month);
+        let year =
+            i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year.rem_euclid(400);
+        let mp = (month + 9) % 12;
+        let doy =
+            (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn previous(&self) -> Self {
+        DailyDate::from_days(self.days() - 1)
+    }
+
+    /// A stable seed for the date. This has to be the
+    /// same on every machine and every build, so it
+    /// can't use std's `Hash`.
+    fn seed(&self) -> u64 {
+        // FNV-1a
+        self.to_string().bytes().fold(
+            0xcbf2_9ce4_8422_2325,
+            |hash, byte| {
+                (hash ^ u64::from(byte))
+                    .wrapping_mul(0x0100_0000_01b3)
+            },
+        )
+    }
+}
+
+impl fmt::Display for DailyDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}",
+            self.year, self.month, self.day
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyChallenge {
+    pub date: DailyDate,
+    pub rules: GameRules,
+}
+
+impl DailyChallenge {
+    pub fn today() -> Self {
+        DailyChallenge::for_date(DailyDate::today())
+    }
+
+    pub fn for_date(date: DailyDate) -> Self {
+        let mut rng =
+            ChaCha8Rng::seed_from_u64(date.seed());
+
+        let board_size = rng.gen_range(12..=20);
+        let speed = *[
+            GameSpeed::SLOW,
+            GameSpeed::REGULAR,
+            GameSpeed::FAST,
+        ]
+        .choose(&mut rng)
+        .unwrap();
+        let modifiers = Modifier::ALL
+            .into_iter()
+            .filter(|_| rng.gen_bool(0.4))
+            .collect();
+
+        DailyChallenge {
+            date,
+            rules: GameRules {
+                seed: Some(rng.gen()),
+                board_size,
+                speed,
+                modifiers,
+            },
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct DailyResult {
+    pub date: DailyDate,
+    pub score: u32,
+    pub time: Duration,
+}
+
+/// Ranked daily results, one per day at most. Practice
+/// runs are not recorded.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub struct DailyHistory {
+    results: Vec<DailyResult>,
+}
+
+impl DailyHistory {
+    pub fn load() -> Self {
+        persistence::load(HISTORY_FILE)
+    }
+
+    pub fn save(&self) {
+        persistence::save(HISTORY_FILE, self);
+    }
+
+    pub fn result_for(
+        &self,
+        date: DailyDate,
+    ) -> Option<&DailyResult> {
+        self.results
+            .iter()
+            .find(|result| result.date == date)
+    }
+
+    pub fn recent(&self) -> Vec<DailyResult> {
+        let mut results = self.results.clone();
+        results.sort_by(|a, b| b.date.cmp(&a.date));
+        results
+    }
+
+    /// Consecutive days played up to today. A streak
+    /// that ended yesterday is still alive until today
+    /// is over.
+    pub fn current_streak(&self, today: DailyDate) -> u32 {
+        let start = if self.result_for(today).is_some() {
+            today
+        } else {
+            today.previous()
+        };
+        let mut streak = 0;
+        let mut date = start;
+        while self.result_for(date).is_some() {
+            streak += 1;
+            date = date.previous();
+        }
+        streak
+    }
+
+    /// Uses up the ranked attempt for `today`. `false`
+    /// if it was already used, the run is practice then.
+    pub fn start_attempt(
+        &mut self,
+        today: DailyDate,
+    ) -> bool {
+        if self.result_for(today).is_some() {
+            return false;
+        }
+        self.results.push(DailyResult {
+            date: today,
+            score: 0,
+            time: Duration::ZERO,
+        });
+        self.save();
+        true
+    }
+
+    /// Records the result of the attempt started on
+    /// `today`. A run that crosses midnight still counts
+    /// for the day it was started on.
+    pub fn finish_attempt(
+        &mut self,
+        today: DailyDate,
+        score: u32,
+        time: Duration,
+    ) {
+        if let Some(result) =
+            self.results.iter_mut().rev().find(|result| {
+                result.date == today
+                    || result.date == today.previous()
+            })
+        {
+            result.score = score;
+            result.time = time;
+        }
+        self.save();
+    }
+
+    pub fn best_streak(&self) -> u32 {
+        let mut days = self
+            .results
+            .iter()
+            .map(|result| result.date.days())
+            .collect::<Vec<i64>>();
+        days.sort_unstable();
+        days.dedup();
+
+        let mut best = 0;
+        let mut streak = 0;
+        let mut previous = None;
+        for day in days {
+            streak = match previous {
+                Some(previous) if previous + 1 == day => {
+                    streak + 1
+                }
+                _ => 1,
+            };
+            best = best.max(streak);
+            previous = Some(day);
+        }
+        best
+    }
+}
+
+/// Uses up today's ranked attempt as soon as the game
+/// starts, so quitting a bad run doesn't give a retry.
+fn start_daily_attempt(
+    mut mode: ResMut<GameMode>,
+    mut history: ResMut<DailyHistory>,
+) {
+    if *mode != (GameMode::Daily { ranked: true }) {
+        return;
+    }
+
+    if !history.start_attempt(DailyDate::today()) {
+        *mode = GameMode::Daily { ranked: false };
+    }
+}
+
+fn finish_daily_attempt(
+    mode: Res<GameMode>,
+    game: Res<Game>,
+    timer: Res<Timer>,
+    mut history: ResMut<DailyHistory>,
+) {
+    if *mode != (GameMode::Daily { ranked: true }) {
+        return;
+    }
+
+    let time = timer.elapsed().unwrap_or_default();
+    history.finish_attempt(
+        DailyDate::today(),
+        game.score,
+        time,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> DailyDate {
+        DailyDate { year, month, day }
+    }
+
+    #[test]
+    fn known_dates() {
+        assert_eq!(
+            DailyDate::from_days(0),
+            date(1970, 1, 1)
+        );
+        assert_eq!(
+            DailyDate::from_days(-1),
+            date(1969, 12, 31)
+        );
+        assert_eq!(
+            DailyDate::from_days(11_016),
+            date(2000, 2, 29)
+        );
+        assert_eq!(date(2024, 3, 1).days(), 19_783);
+    }
+
+    #[test]
+    fn days_round_trip() {
+        for days in -800_000..800_000 {
+            let date = DailyDate::from_days(days);
+            assert_eq!(date.days(), days, "{}", date);
+        }
+    }
+
+    #[test]
+    fn previous_crosses_months_and_years() {
+        assert_eq!(
+            date(2024, 3, 1).previous(),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            date(2023, 3, 1).previous(),
+            date(2023, 2, 28)
+        );
+        assert_eq!(
+            date(2024, 1, 1).previous(),
+            date(2023, 12, 31)
+        );
+    }
+}
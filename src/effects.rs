@@ -0,0 +1,378 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    board::{Board, Position},
+    common::{LocalPlayer, MainCamera},
+    food::Food,
+    rules::{GameState, TickEvent},
+    settings::GameSettings,
+    snake::SnakeSegment,
+    theme::ActiveTheme,
+    ui::FontSpec,
+};
+
+const PARTICLES_PER_APPLE: usize = 12;
+const PARTICLE_SPEED: f32 = 120.0;
+const PARTICLE_LIFETIME: f32 = 0.5;
+const POPUP_LIFETIME: f32 = 0.8;
+const POPUP_RISE: f32 = 40.0;
+const POP_DURATION: f32 = 0.25;
+const POP_OVERSHOOT: f32 = 1.7;
+/// Delay between one segment collapsing and the next,
+/// head first
+const COLLAPSE_STAGGER: f32 = 0.03;
+const COLLAPSE_DURATION: f32 = 0.2;
+const SHAKE_DURATION: f32 = 0.3;
+/// Largest camera offset in pixels at the start of a
+/// shake
+const SHAKE_STRENGTH: f32 = 8.0;
+
+/// Particles, popups, screen shake and the death
+/// collapse. Everything is started from `TickEvent`s
+/// and new `Food`, and is skipped with
+/// `GameSettings::reduce_motion`.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Shake>()
+            .add_system(spawn_tick_effects)
+            .add_system(pop_new_food)
+            .add_system(update_particles)
+            .add_system(update_popups)
+            .add_system(update_pops)
+            .add_system(collapse_segments)
+            .add_system(shake_camera);
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct Popup {
+    start: Vec2,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct Pop {
+    timer: Timer,
+}
+
+/// Shrinks a dead snake's segment away, then despawns
+/// it.
+#[derive(Component)]
+struct Collapse {
+    delay: Timer,
+    timer: Timer,
+}
+
+#[derive(Default)]
+struct Shake {
+    timer: Option<Timer>,
+}
+
+fn spawn_tick_effects(
+    mut commands: Commands,
+    mut events: EventReader<TickEvent>,
+    settings: Res<GameSettings>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    font_spec: Res<FontSpec>,
+    theme: Res<ActiveTheme>,
+    boards: Query<&Board>,
+    segments: Query<(Entity, &Position, &SnakeSegment)>,
+    mut shake: ResMut<Shake>,
+) {
+    let board = match boards.iter().next() {
+        Some(board) => board,
+        None => return,
+    };
+    for event in events.iter() {
+        match event {
+            TickEvent::Ate { snake, position }
+                if !settings.reduce_motion =>
+            {
+                let center = board.cell_center(*position);
+                spawn_particles(
+                    &mut commands,
+                    center,
+                    theme.palette.food,
+                );
+                if *snake == local.0 {
+                    spawn_popup(
+                        &mut commands,
+                        &font_spec,
+                        center,
+                    );
+                }
+            }
+            TickEvent::GameOver { snake, .. } => {
+                let segments = segments.iter().filter(
+                    |(.., segment)| segment.snake == *snake,
+                );
+                if settings.reduce_motion {
+                    // other snakes keep playing, clear
+                    // the board of this one
+                    if !state.is_over() {
+                        for (entity, ..) in segments {
+                            commands
+                                .entity(entity)
+                                .despawn_recursive();
+                        }
+                    }
+                    continue;
+                }
+
+                if *snake == local.0 {
+                    shake.timer =
+                        Some(Timer::from_seconds(
+                            SHAKE_DURATION,
+                            false,
+                        ));
+                }
+                let body =
+                    &state.snakes[*snake].body.segments;
+                for (entity, position, _) in segments {
+                    // the trailing tail goes last
+                    let index = body
+                        .iter()
+                        .position(|part| part == position)
+                        .unwrap_or(body.len());
+                    commands.entity(entity).insert(
+                        Collapse {
+                            delay: Timer::from_seconds(
+                                index as f32
+                                    * COLLAPSE_STAGGER,
+                                false,
+                            ),
+                            timer: Timer::from_seconds(
+                                COLLAPSE_DURATION,
+                                false,
+                            ),
+                        },
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_particles(
+    commands: &mut Commands,
+    center: Vec2,
+    color: Color,
+) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..PARTICLES_PER_APPLE {
+        let angle =
+            rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = rng.gen_range(
+            PARTICLE_SPEED / 2.0..PARTICLE_SPEED,
+        );
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(4.0, 4.0)),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(
+                    center.extend(3.0),
+                ),
+                ..Default::default()
+            })
+            .insert(Particle {
+                velocity: Vec2::new(
+                    angle.cos(),
+                    angle.sin(),
+                ) * speed,
+                timer: Timer::from_seconds(
+                    PARTICLE_LIFETIME,
+                    false,
+                ),
+            });
+    }
+}
+
+fn spawn_popup(
+    commands: &mut Commands,
+    font_spec: &FontSpec,
+    center: Vec2,
+) {
+    let alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+    commands
+        .spawn_bundle(Text2dBundle {
+            text: Text::with_section(
+                "+1",
+                TextStyle {
+                    font: font_spec.family.clone(),
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                },
+                alignment,
+            ),
+            transform: Transform::from_translation(
+                center.extend(4.0),
+            ),
+            ..Default::default()
+        })
+        .insert(Popup {
+            start: center,
+            timer: Timer::from_seconds(
+                POPUP_LIFETIME,
+                false,
+            ),
+        });
+}
+
+fn pop_new_food(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    mut food: Query<(Entity, &mut Transform), Added<Food>>,
+) {
+    if settings.reduce_motion {
+        return;
+    }
+    for (entity, mut transform) in food.iter_mut() {
+        transform.scale = Vec3::ZERO;
+        commands.entity(entity).insert(Pop {
+            timer: Timer::from_seconds(POP_DURATION, false),
+        });
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(
+        Entity,
+        &mut Particle,
+        &mut Transform,
+        &mut Sprite,
+    )>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in
+        particles.iter_mut()
+    {
+        particle.timer.tick(time.delta());
+        if particle.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let movement =
+            particle.velocity * time.delta_seconds();
+        transform.translation += movement.extend(0.0);
+        sprite.color.set_a(1.0 - particle.timer.percent());
+    }
+}
+
+fn update_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut popups: Query<(
+        Entity,
+        &mut Popup,
+        &mut Transform,
+        &mut Text,
+    )>,
+) {
+    for (entity, mut popup, mut transform, mut text) in
+        popups.iter_mut()
+    {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let progress = popup.timer.percent();
+        transform.translation.y =
+            popup.start.y + POPUP_RISE * progress;
+        text.sections[0].style.color.set_a(1.0 - progress);
+    }
+}
+
+fn update_pops(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pops: Query<(Entity, &mut Pop, &mut Transform)>,
+) {
+    for (entity, mut pop, mut transform) in pops.iter_mut()
+    {
+        pop.timer.tick(time.delta());
+        // eases out past full size before settling
+        let t = pop.timer.percent() - 1.0;
+        let scale = 1.0
+            + (POP_OVERSHOOT + 1.0) * t.powi(3)
+            + POP_OVERSHOOT * t.powi(2);
+        transform.scale = Vec3::splat(scale.max(0.0));
+        if pop.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).remove::<Pop>();
+        }
+    }
+}
+
+fn collapse_segments(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut segments: Query<(
+        Entity,
+        &mut Collapse,
+        &mut Transform,
+    )>,
+) {
+    for (entity, mut collapse, mut transform) in
+        segments.iter_mut()
+    {
+        collapse.delay.tick(time.delta());
+        if !collapse.delay.finished() {
+            continue;
+        }
+        collapse.timer.tick(time.delta());
+        if collapse.timer.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        transform.scale =
+            Vec3::splat(1.0 - collapse.timer.percent());
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    mut shake: ResMut<Shake>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    let offset = match shake.timer.as_mut() {
+        Some(timer) => {
+            timer.tick(time.delta());
+            if timer.finished() {
+                shake.timer = None;
+                Vec2::ZERO
+            } else {
+                let strength = SHAKE_STRENGTH
+                    * (1.0 - timer.percent());
+                let mut rng = rand::thread_rng();
+                Vec2::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                ) * strength
+            }
+        }
+        None => return,
+    };
+    for mut transform in cameras.iter_mut() {
+        transform.translation.x = offset.x;
+        transform.translation.y = offset.y;
+    }
+}
@@ -0,0 +1,492 @@
+use std::thread;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Position,
+    rules::{
+        Direction, GameOverReason, GameState, TickEvent,
+    },
+    settings::GameRules,
+};
+
+/// Planes in `Observation::grid`: head, body, food and
+/// walls.
+pub const GRID_CHANNELS: usize = 4;
+pub const FEATURES: usize = 13;
+/// Fewer boards than this per thread are stepped on the
+/// calling thread, spawning threads would cost more
+/// than stepping them.
+const BOARDS_PER_THREAD: usize = 64;
+
+/// Rewards handed out by `SnakeEnv::step`. Everything
+/// except `food` and `death` defaults to zero so
+/// shaping is opt-in.
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct RewardConfig {
+    pub food: f32,
+    pub death: f32,
+    pub win: f32,
+    /// Added every step, usually a small negative
+    /// number to discourage stalling
+    pub step: f32,
+    /// Scaled by how many cells closer to the nearest
+    /// food the head moved
+    pub approach: f32,
+}
+
+impl Default for RewardConfig {
+    fn default() -> Self {
+        RewardConfig {
+            food: 1.0,
+            death: -1.0,
+            win: 10.0,
+            step: 0.0,
+            approach: 0.0,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct EnvConfig {
+    pub rules: GameRules,
+    pub reward: RewardConfig,
+    /// Episodes are truncated after this many steps
+    pub max_steps: Option<u64>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            rules: GameRules::default(),
+            reward: RewardConfig::default(),
+            max_steps: Some(1000),
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct Observation {
+    /// `GRID_CHANNELS` planes of `board_size` rows of
+    /// `board_size` cells, row `y` at
+    /// `(channel * size + y) * size`, one-hot.
+    pub grid: Vec<f32>,
+    /// Danger in each direction, the current direction
+    /// and where the nearest food is (all in
+    /// `Direction::ALL` order), then the snake's length
+    /// relative to the board.
+    pub features: Vec<f32>,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct StepInfo {
+    pub score: u32,
+    pub steps: u64,
+    /// Why the episode ended, `None` if it is still
+    /// running or was truncated
+    pub reason: Option<GameOverReason>,
+    /// The episode hit `max_steps`
+    pub truncated: bool,
+    pub events: Vec<TickEvent>,
+    /// Set by `VecEnv` when it reset a finished board,
+    /// the observation returned alongside is then the
+    /// first of the next episode.
+    pub final_observation: Option<Observation>,
+}
+
+/// A single-player game behind a
+/// `reset(seed) / step(action)` interface, for
+/// training agents without running Bevy.
+#[derive(Debug, Clone)]
+pub struct SnakeEnv {
+    pub config: EnvConfig,
+    state: GameState,
+    steps: u64,
+}
+
+impl SnakeEnv {
+    pub fn new(config: EnvConfig) -> Self {
+        let seed = config.rules.seed.unwrap_or_default();
+        let state =
+            GameState::new(config.rules.clone(), 1, seed);
+        SnakeEnv {
+            config,
+            state,
+            steps: 0,
+        }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.state = GameState::new(
+            self.config.rules.clone(),
+            1,
+            seed,
+        );
+        self.steps = 0;
+        self.observe()
+    }
+
+    /// Turning back into the neck is ignored, as it is
+    /// for players. Stepping a finished episode is a
+    /// no-op that reports `done` again.
+    pub fn step(
+        &mut self,
+        action: Direction,
+    ) -> (Observation, f32, bool, StepInfo) {
+        let reward = &self.config.reward;
+        let mut total = 0.0;
+
+        if !self.state.is_over() {
+            self.steps += 1;
+            total += reward.step;
+        }
+        let distance = self.food_distance();
+        let events = self.state.step(&[Some(action)]);
+
+        for event in &events {
+            match event {
+                TickEvent::Ate { .. } => {
+                    total += reward.food
+                }
+                TickEvent::GameOver {
+                    reason: GameOverReason::Win,
+                    ..
+                } => total += reward.win,
+                TickEvent::GameOver { .. } => {
+                    total += reward.death
+                }
+                _ => {}
+            }
+        }
+        let ate = events.iter().any(|event| {
+            matches!(event, TickEvent::Ate { .. })
+        });
+        if let (false, Some(before), Some(after)) =
+            (ate, distance, self.food_distance())
+        {
+            total += reward.approach
+                * (before as f32 - after as f32);
+        }
+
+        let snake = &self.state.snakes[0];
+        let truncated = snake.is_alive()
+            && self
+                .config
+                .max_steps
+                .is_some_and(|max| self.steps >= max);
+        let info = StepInfo {
+            score: snake.score,
+            steps: self.steps,
+            reason: snake.game_over,
+            truncated,
+            events,
+            final_observation: None,
+        };
+        let done = self.state.is_over() || truncated;
+        (self.observe(), total, done, info)
+    }
+
+    pub fn observe(&self) -> Observation {
+        let size = usize::from(self.state.rules.board_size);
+        let snake = &self.state.snakes[0];
+        let mut grid =
+            vec![0.0; GRID_CHANNELS * size * size];
+        let mut set =
+            |channel: usize, position: &Position| {
+                grid[(channel * size
+                    + usize::from(position.y))
+                    * size
+                    + usize::from(position.x)] = 1.0;
+            };
+        if snake.is_alive() {
+            set(0, &snake.head());
+            for segment in
+                snake.body.segments.iter().skip(1)
+            {
+                set(1, segment);
+            }
+        }
+        for food in &self.state.food {
+            set(2, food);
+        }
+        for wall in &self.state.walls {
+            set(3, wall);
+        }
+
+        let mut features = Vec::with_capacity(FEATURES);
+        let head = snake.head();
+        features.extend(
+            Direction::ALL
+                .iter()
+                .map(|direction| self.danger(*direction)),
+        );
+        features.extend(Direction::ALL.iter().map(
+            |direction| {
+                one_hot(*direction == snake.direction)
+            },
+        ));
+        let food = self.nearest_food();
+        features.extend([
+            one_hot(food.is_some_and(|f| f.y > head.y)),
+            one_hot(food.is_some_and(|f| f.y < head.y)),
+            one_hot(food.is_some_and(|f| f.x < head.x)),
+            one_hot(food.is_some_and(|f| f.x > head.x)),
+        ]);
+        features.push(
+            snake.body.segments.len() as f32
+                / (size * size) as f32,
+        );
+
+        Observation { grid, features }
+    }
+
+    /// Whether moving `direction` would end the game
+    /// next tick, ignoring other snakes' moves.
+    fn danger(&self, direction: Direction) -> f32 {
+        let snake = &self.state.snakes[0];
+        let next = direction.step(
+            snake.head(),
+            self.state.rules.board_size,
+        );
+        one_hot(match next {
+            None => true,
+            Some(next) => self.state.is_blocked(next),
+        })
+    }
+
+    fn nearest_food(&self) -> Option<Position> {
+        let head = self.state.snakes[0].head();
+        self.state
+            .food
+            .iter()
+            .copied()
+            .min_by_key(|food| manhattan(head, *food))
+    }
+
+    fn food_distance(&self) -> Option<u32> {
+        let head = self.state.snakes[0].head();
+        self.nearest_food()
+            .map(|food| manhattan(head, food))
+    }
+}
+
+/// Many independent boards stepped in parallel. Boards
+/// that finish are reset straight away with seeds
+/// drawn from the seed given to `reset`, so a run is
+/// reproducible.
+pub struct VecEnv {
+    pub envs: Vec<SnakeEnv>,
+    rng: ChaCha8Rng,
+}
+
+impl VecEnv {
+    pub fn new(config: EnvConfig, count: usize) -> Self {
+        VecEnv {
+            envs: vec![SnakeEnv::new(config); count],
+            rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.envs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envs.is_empty()
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Vec<Observation> {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+        let rng = &mut self.rng;
+        self.envs
+            .iter_mut()
+            .map(|env| env.reset(rng.gen()))
+            .collect()
+    }
+
+    pub fn step(
+        &mut self,
+        actions: &[Direction],
+    ) -> Vec<(Observation, f32, bool, StepInfo)> {
+        assert_eq!(
+            actions.len(),
+            self.envs.len(),
+            "one action is needed per board"
+        );
+        let threads = thread::available_parallelism()
+            .map_or(1, |threads| threads.get());
+        let chunk = (self.envs.len() / threads)
+            .max(BOARDS_PER_THREAD);
+
+        let mut results = if chunk >= self.envs.len() {
+            self.envs
+                .iter_mut()
+                .zip(actions)
+                .map(|(env, action)| env.step(*action))
+                .collect()
+        } else {
+            thread::scope(|scope| {
+                self.envs
+                    .chunks_mut(chunk)
+                    .zip(actions.chunks(chunk))
+                    .map(|(envs, actions)| {
+                        scope.spawn(move || {
+                            envs.iter_mut()
+                                .zip(actions)
+                                .map(|(env, action)| {
+                                    env.step(*action)
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .flat_map(|handle| {
+                        handle.join().unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        for (env, (observation, _, done, info)) in
+            self.envs.iter_mut().zip(&mut results)
+        {
+            if *done {
+                let next = env.reset(self.rng.gen());
+                info.final_observation = Some(
+                    std::mem::replace(observation, next),
+                );
+            }
+        }
+        results
+    }
+}
+
+fn one_hot(value: bool) -> f32 {
+    if value {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn manhattan(a: Position, b: Position) -> u32 {
+    u32::from(a.x.abs_diff(b.x))
+        + u32::from(a.y.abs_diff(b.y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: u16, y: u16) -> Position {
+        Position { x, y }
+    }
+
+    fn env(max_steps: Option<u64>) -> SnakeEnv {
+        let mut env = SnakeEnv::new(EnvConfig {
+            max_steps,
+            ..EnvConfig::default()
+        });
+        env.state.put_food(&[pos(19, 19)]);
+        env
+    }
+
+    #[test]
+    fn eating_is_rewarded() {
+        let mut env = env(None);
+        env.state.put_food(&[pos(5, 4)]);
+
+        let (_, reward, done, info) =
+            env.step(Direction::Right);
+        assert_eq!(reward, env.config.reward.food);
+        assert!(!done);
+        assert_eq!(info.score, 1);
+    }
+
+    #[test]
+    fn dying_is_rewarded() {
+        let mut env = env(None);
+        for _ in 0..4 {
+            let (_, reward, done, _) =
+                env.step(Direction::Down);
+            assert_eq!(reward, 0.0);
+            assert!(!done);
+        }
+
+        let (_, reward, done, info) =
+            env.step(Direction::Down);
+        assert_eq!(reward, env.config.reward.death);
+        assert!(done);
+        assert_eq!(
+            info.reason,
+            Some(GameOverReason::HitWall)
+        );
+        assert!(!info.truncated);
+    }
+
+    #[test]
+    fn winning_is_rewarded_with_the_last_food() {
+        let mut env = env(None);
+        env.state.put_food(&[pos(5, 4)]);
+        env.state.fill_free_cells();
+
+        let (_, reward, done, info) =
+            env.step(Direction::Right);
+        let rewards = &env.config.reward;
+        assert_eq!(reward, rewards.food + rewards.win);
+        assert!(done);
+        assert_eq!(info.reason, Some(GameOverReason::Win));
+    }
+
+    #[test]
+    fn episodes_are_truncated_at_the_step_limit() {
+        let mut env = env(Some(3));
+        for _ in 0..2 {
+            assert!(!env.step(Direction::Right).2);
+        }
+
+        let (_, _, done, info) = env.step(Direction::Right);
+        assert!(done);
+        assert!(info.truncated);
+        assert_eq!(info.reason, None);
+        assert_eq!(info.steps, 3);
+    }
+
+    #[test]
+    fn finished_boards_reset_right_away() {
+        let config = EnvConfig {
+            max_steps: Some(1),
+            ..EnvConfig::default()
+        };
+        let mut envs = VecEnv::new(config, 2);
+        envs.reset(3);
+
+        let results = envs.step(&[Direction::Up; 2]);
+        for ((observation, _, done, info), env) in
+            results.iter().zip(&envs.envs)
+        {
+            assert!(done);
+            assert!(info.truncated);
+            assert_eq!(env.state().tick, 0);
+            assert_eq!(*observation, env.observe());
+            let last = info.final_observation.as_ref();
+            assert!(last
+                .is_some_and(|last| last != observation));
+        }
+    }
+}
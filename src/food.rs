@@ -1,42 +1,60 @@
+use std::collections::HashMap;
+
 use bevy::prelude::*;
-use itertools::Itertools;
-use rand::prelude::SliceRandom;
 
 use crate::{
-    board::{Board, Position, SpawnApple},
-    snake::SnakeBody,
+    board::{Position, SpawnApple},
+    rules::TickEvent,
 };
 
-pub struct NewFoodEvent;
+pub struct NewFoodEvent(pub Position);
 
 #[derive(Component)]
 pub struct Food;
 
+/// The apple sprite on each cell with food, so eaten
+/// food is found without searching every `Food`.
+#[derive(Default)]
+pub struct FoodSprites(pub HashMap<Position, Entity>);
+
+/// Clears eaten food off the board and announces new
+/// food, following the `TickEvent`s of the last tick.
+pub fn sync_food(
+    mut commands: Commands,
+    mut tick_events: EventReader<TickEvent>,
+    mut sprites: ResMut<FoodSprites>,
+    mut food_events: EventWriter<NewFoodEvent>,
+) {
+    for event in tick_events.iter() {
+        match event {
+            TickEvent::Ate { position, .. } => {
+                if let Some(entity) =
+                    sprites.0.remove(position)
+                {
+                    commands
+                        .entity(entity)
+                        .despawn_recursive();
+                }
+            }
+            TickEvent::FoodSpawned { position } => {
+                food_events.send(NewFoodEvent(*position));
+            }
+            _ => {}
+        }
+    }
+}
+
 pub fn food_event_listener(
     mut commands: Commands,
-    query_board: Query<&Board>,
     mut events: EventReader<NewFoodEvent>,
-    snake: Res<SnakeBody>,
+    mut sprites: ResMut<FoodSprites>,
 ) {
-    let board = query_board.single();
-    let mut rng = rand::thread_rng();
-
-    let possible_food_locations = (0..board.size)
-        .cartesian_product(0..board.size)
-        .map(|point| Position {
-            x: point.0,
-            y: point.1,
-        })
-        .filter(|pos| !snake.segments.contains(pos))
-        .collect::<Vec<Position>>();
-
-    let mut num_food = 0;
-    for _ in events.iter() {
-        num_food += 1;
-    }
-    for pos in possible_food_locations
-        .choose_multiple(&mut rng, num_food)
-    {
-        commands.add(SpawnApple { position: *pos });
+    for NewFoodEvent(position) in events.iter() {
+        let entity = commands.spawn().id();
+        commands.add(SpawnApple {
+            entity,
+            position: *position,
+        });
+        sprites.0.insert(*position, entity);
     }
 }
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use bevy::{
+    input::{keyboard::KeyboardInput, ElementState},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{BindingProfiles, Bindings},
+    common::{LocalPlayer, Paused, RunState},
+    control::{can_turn, LastTurn},
+    persistence,
+    rules::{Direction, GameState},
+    settings::{ControlScheme, GameRules, GameSettings},
+};
+
+const GAMEPAD_FILE: &str = "gamepad.ron";
+/// Gamepads beyond this share snakes with earlier ones
+pub const MAX_PADS: usize = GameRules::MAX_PLAYERS;
+
+/// Steering with the bound buttons or left stick, and
+/// moving the focus through the menus. Pausing goes
+/// through `Action::Pause` like the keyboard.
+/// Pads are assigned to snakes as they connect.
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GamepadConfig::load())
+            .init_resource::<PadAssignments>()
+            .init_resource::<PadTurns>()
+            .add_system(gamepad_connections)
+            .add_system(save_gamepad_config)
+            .add_system_set(
+                SystemSet::on_enter(RunState::Playing)
+                    .with_system(reset_pads),
+            )
+            .add_system_set(
+                SystemSet::on_update(RunState::Playing)
+                    .with_system(gamepad_steering),
+            )
+            .add_system_set(
+                SystemSet::on_update(RunState::Menu)
+                    .with_system(gamepad_menu_focus),
+            );
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct GamepadConfig {
+    /// How far the stick has to be tilted, from 0 to 1,
+    /// before it steers
+    pub deadzone: f32,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig { deadzone: 0.5 }
+    }
+}
+
+impl GamepadConfig {
+    pub fn load() -> Self {
+        persistence::load(GAMEPAD_FILE)
+    }
+
+    pub fn save(&self) {
+        persistence::save(GAMEPAD_FILE, self);
+    }
+
+    pub fn adjust_deadzone(&mut self, by: f32) {
+        self.deadzone =
+            (self.deadzone + by).clamp(0.05, 0.95);
+    }
+}
+
+fn save_gamepad_config(config: Res<GamepadConfig>) {
+    if config.is_changed() && !config.is_added() {
+        config.save();
+    }
+}
+
+/// Which snake each connected gamepad steers. Pads
+/// on snakes other than the local one add local
+/// players to offline games.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PadAssignments {
+    pub pads: Vec<(Gamepad, usize)>,
+}
+
+impl PadAssignments {
+    pub fn snake_for(&self, pad: Gamepad) -> Option<usize> {
+        self.pads
+            .iter()
+            .find(|(assigned, _)| *assigned == pad)
+            .map(|(_, snake)| *snake)
+    }
+
+    pub fn cycle(&mut self, pad: Gamepad) {
+        if let Some((_, snake)) = self
+            .pads
+            .iter_mut()
+            .find(|(assigned, _)| *assigned == pad)
+        {
+            *snake = (*snake + 1) % MAX_PADS;
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.pads
+            .iter()
+            .map(|(_, snake)| snake + 1)
+            .max()
+            .unwrap_or(1)
+    }
+
+    fn free_snake(&self) -> usize {
+        (0..MAX_PADS)
+            .find(|snake| {
+                !self
+                    .pads
+                    .iter()
+                    .any(|(_, assigned)| assigned == snake)
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// Turns asked for by pads steering snakes other than
+/// the local one, used up by the next tick.
+#[derive(Default)]
+pub struct PadTurns(pub HashMap<usize, Direction>);
+
+fn gamepad_connections(
+    mut events: EventReader<GamepadEvent>,
+    mut assignments: ResMut<PadAssignments>,
+) {
+    for GamepadEvent(pad, event) in events.iter() {
+        match event {
+            GamepadEventType::Connected => {
+                let snake = assignments.free_snake();
+                info!(?pad, snake, "gamepad connected");
+                assignments.pads.push((*pad, snake));
+            }
+            GamepadEventType::Disconnected => {
+                info!(?pad, "gamepad disconnected");
+                assignments.pads.retain(|(assigned, _)| {
+                    assigned != pad
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn reset_pads(
+    mut turns: ResMut<PadTurns>,
+    mut paused: ResMut<Paused>,
+) {
+    turns.0.clear();
+    paused.0 = false;
+}
+
+/// The direction a pad is pointing in, from its bound
+/// buttons or the left stick. Some pads report their
+/// D-pad as an axis instead of buttons.
+fn pad_direction(
+    pad: Gamepad,
+    bindings: &Bindings,
+    buttons: &Input<GamepadButton>,
+    axes: &Axis<GamepadAxis>,
+    deadzone: f32,
+) -> Option<Direction> {
+    if let Some(direction) =
+        bindings.pad_direction(pad, buttons)
+    {
+        return Some(direction);
+    }
+
+    let axis = |axis| {
+        axes.get(GamepadAxis(pad, axis)).unwrap_or(0.0)
+    };
+    let stick = Vec2::new(
+        axis(GamepadAxisType::LeftStickX),
+        axis(GamepadAxisType::LeftStickY),
+    );
+    let dpad = Vec2::new(
+        axis(GamepadAxisType::DPadX),
+        axis(GamepadAxisType::DPadY),
+    );
+    let tilt = if stick.length() >= deadzone {
+        stick
+    } else if dpad.length() >= 0.5 {
+        dpad
+    } else {
+        return None;
+    };
+
+    Some(if tilt.x.abs() > tilt.y.abs() {
+        if tilt.x > 0.0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else if tilt.y > 0.0 {
+        Direction::Up
+    } else {
+        Direction::Down
+    })
+}
+
+fn gamepad_steering(
+    assignments: Res<PadAssignments>,
+    config: Res<GamepadConfig>,
+    profiles: Res<BindingProfiles>,
+    settings: Res<GameSettings>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    mut last_turn: ResMut<LastTurn>,
+    mut turns: ResMut<PadTurns>,
+) {
+    for (pad, snake) in assignments.pads.iter() {
+        if *snake >= state.snakes.len() {
+            continue;
+        }
+        // relative turns for the local snake come in
+        // through `Actions` like the keyboard's, the
+        // stick only steers absolutely
+        if *snake == local.0
+            && settings.control_scheme
+                == ControlScheme::Relative
+        {
+            continue;
+        }
+        let direction = match pad_direction(
+            *pad,
+            profiles.current(),
+            &buttons,
+            &axes,
+            config.deadzone,
+        ) {
+            Some(direction) => direction,
+            None => continue,
+        };
+        if !can_turn(&state, *snake, direction) {
+            continue;
+        }
+        if *snake == local.0 {
+            last_turn.0 = direction;
+        } else {
+            turns.0.insert(*snake, direction);
+        }
+    }
+}
+
+/// Moves the menus' focus the way Tab and Shift+Tab do, and
+/// presses the focused item with South, by feeding the UI
+/// the same keys.
+fn gamepad_menu_focus(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    config: Res<GamepadConfig>,
+    mut tilted: Local<HashMap<Gamepad, bool>>,
+    mut keys: EventWriter<KeyboardInput>,
+) {
+    let mut press = |key_code, state| {
+        keys.send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state,
+        });
+    };
+
+    for pad in gamepads.iter() {
+        let pad = *pad;
+        let button = |button| GamepadButton(pad, button);
+        let axis = |axis| {
+            axes.get(GamepadAxis(pad, axis)).unwrap_or(0.0)
+        };
+
+        let mut step = None;
+        for (dpad, forward) in [
+            (GamepadButtonType::DPadUp, false),
+            (GamepadButtonType::DPadLeft, false),
+            (GamepadButtonType::DPadDown, true),
+            (GamepadButtonType::DPadRight, true),
+        ] {
+            if buttons.just_pressed(button(dpad)) {
+                step = Some(forward);
+            }
+        }
+        // the stick steps once each time it's tilted
+        let stick = axis(GamepadAxisType::LeftStickY);
+        let was_tilted = tilted.entry(pad).or_default();
+        if stick.abs() < config.deadzone {
+            *was_tilted = false;
+        } else if !*was_tilted {
+            *was_tilted = true;
+            step = Some(stick < 0.0);
+        }
+
+        if let Some(forward) = step {
+            if !forward {
+                press(
+                    KeyCode::LShift,
+                    ElementState::Pressed,
+                );
+            }
+            press(KeyCode::Tab, ElementState::Pressed);
+            press(KeyCode::Tab, ElementState::Released);
+            if !forward {
+                press(
+                    KeyCode::LShift,
+                    ElementState::Released,
+                );
+            }
+        }
+
+        let south = button(GamepadButtonType::South);
+        if buttons.just_pressed(south) {
+            press(KeyCode::Return, ElementState::Pressed);
+        } else if buttons.just_released(south) {
+            press(KeyCode::Return, ElementState::Released);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::{
+    asset::Asset,
+    prelude::*,
+    render::mesh::{Indices, PrimitiveTopology},
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+use itertools::Itertools;
+
+use crate::{
+    board::{Board, Position, TILE_SIZE},
+    theme::ActiveTheme,
+};
+
+const CHUNK_CELLS: usize = 32;
+
+/// Draws the grass under each `Board` as a handful of
+/// chunk meshes instead of a sprite per cell.
+pub struct GrassPlugin;
+
+impl Plugin for GrassPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(bake_grass);
+    }
+}
+
+/// The grass tile of every cell on a board, column by
+/// column. Kept so the chunks can be rebuilt with the
+/// same pattern.
+#[derive(Component)]
+pub struct GrassTiles(pub Vec<usize>);
+
+#[derive(Component)]
+struct GrassChunk;
+
+/// Rebuilds the chunks of a board when it's spawned or
+/// resized, and of every board when the theme or its
+/// grass texture changes, so a new look shows up
+/// without starting a new game.
+fn bake_grass(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    boards: Query<(Entity, &Board, &GrassTiles)>,
+    changed: Query<
+        Entity,
+        (
+            With<Board>,
+            Or<(Changed<Board>, Changed<GrassTiles>)>,
+        ),
+    >,
+    chunks: Query<(Entity, &Parent), With<GrassChunk>>,
+) {
+    let atlas = match atlases.get(&theme.grass) {
+        Some(atlas) => atlas,
+        None => return,
+    };
+    let texture_changed = image_events
+        .iter()
+        .filter(|event| touches(event, &atlas.texture))
+        .count()
+        > 0;
+    let everything = theme.is_changed() || texture_changed;
+
+    let mut material = None;
+    for (entity, board, tiles) in boards.iter() {
+        if !everything && !changed.contains(entity) {
+            continue;
+        }
+        for (chunk, parent) in chunks.iter() {
+            if parent.0 == entity {
+                commands.entity(chunk).despawn_recursive();
+            }
+        }
+        let material = material
+            .get_or_insert_with(|| {
+                materials.add(ColorMaterial::from(
+                    atlas.texture.clone(),
+                ))
+            })
+            .clone();
+        // in `usize`, so the largest boards don't
+        // overflow
+        let per_side =
+            (usize::from(board.size) + CHUNK_CELLS - 1)
+                / CHUNK_CELLS;
+        commands.entity(entity).with_children(|builder| {
+            for (x, y) in
+                (0..per_side).cartesian_product(0..per_side)
+            {
+                builder
+                    .spawn_bundle(MaterialMesh2dBundle {
+                        mesh: Mesh2dHandle(meshes.add(
+                            grass_chunk(
+                                board,
+                                atlas,
+                                &tiles.0,
+                                theme.plain_grid,
+                                x,
+                                y,
+                            ),
+                        )),
+                        material: material.clone(),
+                        transform: Transform::from_xyz(
+                            0.0, 0.0, 1.0,
+                        ),
+                        ..Default::default()
+                    })
+                    .insert(GrassChunk);
+            }
+        });
+    }
+}
+
+fn touches<T: Asset>(
+    event: &AssetEvent<T>,
+    handle: &Handle<T>,
+) -> bool {
+    match event {
+        AssetEvent::Created { handle: changed }
+        | AssetEvent::Modified { handle: changed } => {
+            changed == handle
+        }
+        AssetEvent::Removed { .. } => false,
+    }
+}
+
+/// One quad per cell of the chunk at `chunk_x`,
+/// `chunk_y`, textured with its tile from the grass
+/// atlas. `tiles` holds the tile of every cell on the
+/// board, column by column. A plain grid alternates
+/// the first two tiles instead.
+fn grass_chunk(
+    board: &Board,
+    atlas: &TextureAtlas,
+    tiles: &[usize],
+    plain_grid: bool,
+    chunk_x: usize,
+    chunk_y: usize,
+) -> Mesh {
+    let half = TILE_SIZE / 2.0;
+    let size = usize::from(board.size);
+    let columns = chunk_x * CHUNK_CELLS
+        ..size.min((chunk_x + 1) * CHUNK_CELLS);
+    let rows = chunk_y * CHUNK_CELLS
+        ..size.min((chunk_y + 1) * CHUNK_CELLS);
+
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+    for (x, y) in columns.cartesian_product(rows) {
+        // below `board.size`, so they fit
+        let center = board.cell_center(Position {
+            x: x as u16,
+            y: y as u16,
+        });
+        let tile = if plain_grid {
+            (x + y) % 2
+        } else {
+            tiles[x * size + y]
+        };
+        // boards laid out for a theme with more tiles
+        // wrap around
+        let rect =
+            atlas.textures[tile % atlas.textures.len()];
+        let min = rect.min / atlas.size;
+        let max = rect.max / atlas.size;
+        let start = positions.len() as u32;
+        positions.extend([
+            [center.x - half, center.y - half, 0.0],
+            [center.x + half, center.y - half, 0.0],
+            [center.x + half, center.y + half, 0.0],
+            [center.x - half, center.y + half, 0.0],
+        ]);
+        // textures count down from the top
+        uvs.extend([
+            [min.x, max.y],
+            [max.x, max.y],
+            [max.x, min.y],
+            [min.x, min.y],
+        ]);
+        indices.extend([
+            start,
+            start + 1,
+            start + 2,
+            start,
+            start + 2,
+            start + 3,
+        ]);
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let mut mesh =
+        Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        positions,
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
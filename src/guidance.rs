@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_kira_audio::Audio;
+
+use crate::{
+    assets::AudioAssets,
+    board::Position,
+    common::{LocalPlayer, RunState},
+    occupancy::Cell,
+    rules::GameState,
+    sound::{AudioSettings, Channels, Sfx},
+};
+
+/// How far the beacon's pitch goes up or down, in
+/// octaves, for food a whole board above or below
+const PITCH_RANGE: f32 = 1.0;
+/// How quiet the beacon gets for food on the far side
+/// of the board
+const FAR_VOLUME: f32 = 0.3;
+
+/// Audio guidance, for playing without watching the board:
+/// a beacon pings from the nearest food, tones warn before
+/// running into a wall or a snake and menu items are
+/// announced as they're pointed at.
+pub struct GuidancePlugin;
+
+impl Plugin for GuidancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Announcement>()
+            .add_startup_system(start_beacon)
+            .add_system(steer_beacon)
+            .add_system(warn_ahead)
+            .add_system(announce);
+
+        #[cfg(feature = "speech")]
+        app.init_non_send_resource::<Speaker>()
+            .add_system(speak);
+    }
+}
+
+/// A menu item to announce, sent as it is pointed at
+/// or focused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announcement(pub String);
+
+/// The beacon loops from the start, silent until it is
+/// needed, like the music.
+fn start_beacon(
+    audio: Res<Audio>,
+    sounds: Res<AudioAssets>,
+    channels: Res<Channels>,
+) {
+    audio.play_looped_in_channel(
+        sounds.beacon.clone(),
+        &channels.beacon,
+    );
+    audio.set_volume_in_channel(0.0, &channels.beacon);
+}
+
+/// Points the beacon at the food nearest the local
+/// snake's head, and silences it outside of games.
+fn steer_beacon(
+    state: Res<GameState>,
+    run_state: Res<State<RunState>>,
+    local: Res<LocalPlayer>,
+    settings: Res<AudioSettings>,
+    audio: Res<Audio>,
+    channels: Res<Channels>,
+) {
+    if !state.is_changed()
+        && !run_state.is_changed()
+        && !settings.is_changed()
+    {
+        return;
+    }
+    let head = state
+        .snakes
+        .get(local.0)
+        .filter(|snake| snake.is_alive())
+        .map(|snake| snake.head());
+    let target = match head {
+        Some(head)
+            if settings.guidance
+                && *run_state.current()
+                    == RunState::Playing =>
+        {
+            nearest_food(&state, head)
+                .map(|food| (head, food))
+        }
+        _ => None,
+    };
+    let (head, food) = match target {
+        Some(target) => target,
+        None => {
+            audio.set_volume_in_channel(
+                0.0,
+                &channels.beacon,
+            );
+            return;
+        }
+    };
+
+    let size = f32::from(state.rules.board_size);
+    let dx = f32::from(food.x) - f32::from(head.x);
+    let dy = f32::from(food.y) - f32::from(head.y);
+    // anything more than half a board to the side is
+    // as far left or right as it goes
+    let panning = (0.5 + dx / size).clamp(0.0, 1.0);
+    let rate = 2f32.powf(dy / size * PITCH_RANGE);
+    let distance = (dx.abs() + dy.abs()) / (2.0 * size);
+    let volume = 1.0 - distance * (1.0 - FAR_VOLUME);
+
+    audio.set_panning_in_channel(panning, &channels.beacon);
+    audio.set_playback_rate_in_channel(
+        rate,
+        &channels.beacon,
+    );
+    audio.set_volume_in_channel(
+        volume * settings.sfx_volume(),
+        &channels.beacon,
+    );
+}
+
+fn nearest_food(
+    state: &GameState,
+    head: Position,
+) -> Option<Position> {
+    state.food.iter().copied().min_by_key(|food| {
+        (i32::from(food.x) - i32::from(head.x)).abs()
+            + (i32::from(food.y) - i32::from(head.y)).abs()
+    })
+}
+
+/// Warns a tick before the local snake would run into
+/// a wall or a snake if it kept going straight.
+fn warn_ahead(
+    state: Res<GameState>,
+    run_state: Res<State<RunState>>,
+    local: Res<LocalPlayer>,
+    settings: Res<AudioSettings>,
+    mut sfx: EventWriter<Sfx>,
+) {
+    if !state.is_changed()
+        || !settings.guidance
+        || *run_state.current() != RunState::Playing
+    {
+        return;
+    }
+    let snake = match state.snakes.get(local.0) {
+        Some(snake) if snake.is_alive() => snake,
+        _ => return,
+    };
+    let ahead = snake
+        .direction
+        .step(snake.head(), state.rules.board_size);
+    match ahead.map(|cell| state.occupant(cell)) {
+        None | Some(Cell::Wall) => sfx.send(Sfx::WallAhead),
+        Some(Cell::Snake(_)) => sfx.send(Sfx::BodyAhead),
+        Some(Cell::Empty | Cell::Food) => {}
+    }
+}
+
+fn announce(
+    mut announcements: EventReader<Announcement>,
+    settings: Res<AudioSettings>,
+    mut sfx: EventWriter<Sfx>,
+) {
+    if announcements.iter().count() > 0 && settings.guidance
+    {
+        sfx.send(Sfx::Focus);
+    }
+}
+
+#[cfg(feature = "speech")]
+struct Speaker(Option<tts::Tts>);
+
+#[cfg(feature = "speech")]
+impl Default for Speaker {
+    fn default() -> Self {
+        Speaker(
+            tts::Tts::default()
+                .map_err(|error| {
+                    tracing::warn!(
+                        %error,
+                        "text to speech is unavailable"
+                    )
+                })
+                .ok(),
+        )
+    }
+}
+
+/// Reads out the last item pointed at, cutting off
+/// whatever was being read before.
+#[cfg(feature = "speech")]
+fn speak(
+    mut announcements: EventReader<Announcement>,
+    settings: Res<AudioSettings>,
+    mut speaker: NonSendMut<Speaker>,
+) {
+    let last = match announcements.iter().last() {
+        Some(last) => last,
+        None => return,
+    };
+    if let (true, Some(tts)) =
+        (settings.guidance, speaker.0.as_mut())
+    {
+        if let Err(error) = tts.speak(&last.0, true) {
+            tracing::warn!(%error, "failed to announce");
+        }
+    }
+}
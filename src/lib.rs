@@ -1,166 +1,237 @@
-use assets::AudioAssets;
 use bevy::prelude::*;
-use bevy_kira_audio::Audio;
-use board::{Board, Position, SpawnSnakeSegment};
-use common::{Game, RunState};
-use control::LastKeyPress;
-use food::{Food, NewFoodEvent};
-use snake::SnakeBody;
+use board::{SpawnWall, Wall};
+use bot::Bots;
+use common::{
+    Game, GameMode, GameRng, LocalPlayer, NewBestEvent,
+    Paused, RunState,
+};
+use control::LastTurn;
+use daily::DailyChallenge;
+use food::{Food, FoodSprites, NewFoodEvent};
+use gamepad::{PadAssignments, PadTurns};
+use iyes_loopless::prelude::FixedTimestepInfo;
+use net::Lockstep;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rules::{GameState, TickEvent};
+use settings::{GameRules, GameSettings};
+use snake::{SegmentEntities, SnakeSegment};
 
+pub mod accessibility;
+pub mod actions;
 pub mod assets;
+pub mod battlesnake;
 pub mod board;
+pub mod bot;
 pub mod colors;
 pub mod common;
 pub mod control;
+pub mod daily;
+pub mod effects;
+pub mod env;
 pub mod food;
+pub mod gamepad;
+pub mod grass;
+pub mod guidance;
+pub mod locale;
+pub mod net;
+pub mod occupancy;
+pub mod persistence;
+pub mod rules;
 pub mod scoring;
 pub mod settings;
+pub mod skins;
 pub mod snake;
+pub mod sound;
+pub mod spectate;
+pub mod theme;
 pub mod ui;
 
-#[derive(PartialEq, Eq, Debug)]
-enum GameOverReason {
-    HitWall,
-    HitSnake,
-    Win,
-}
-
+/// Advances the `GameState` by one tick. In LAN games the
+/// tick waits until every player's input has arrived. Each
+/// `TickEvent` is also sent as a Bevy event for the sprites
+/// and spectators.
 pub fn snake_movement(
-    mut commands: Commands,
-    query_board: Query<&Board>,
-    mut snake: ResMut<SnakeBody>,
-    positions: Query<(Entity, &Position)>,
-    last_pressed: Res<LastKeyPress>,
-    query_food: Query<(Entity, &Position), With<Food>>,
-    mut food_events: EventWriter<NewFoodEvent>,
+    mut state: ResMut<GameState>,
+    local: Res<LocalPlayer>,
+    last_turn: Res<LastTurn>,
+    mut pad_turns: ResMut<PadTurns>,
+    paused: Res<Paused>,
+    mut bots: ResMut<Bots>,
+    mut lockstep: Option<ResMut<Lockstep>>,
+    mut tick_events: EventWriter<TickEvent>,
     mut run_state: ResMut<State<RunState>>,
     mut game: ResMut<Game>,
-    audio: Res<Audio>,
-    asset_server: Res<AssetServer>,
-    sounds: Res<AudioAssets>,
+    mut new_best: EventWriter<NewBestEvent>,
 ) {
-    let board = query_board.single();
-
-    let mut new_segment = snake.segments[0].clone();
-
-    // did the snake hit the wall?
-    let hit_wall = match last_pressed.0 {
-            KeyCode::Up if new_segment.y == board.size-1 => Some(GameOverReason::HitWall),
-            KeyCode::Up => {
-                new_segment.y += 1;
-                None
-            }
-            KeyCode::Down if new_segment.y == 0 => Some(GameOverReason::HitWall),
-            KeyCode::Down => {
-                new_segment.y -= 1;
-                None
-            }
-            KeyCode::Left if new_segment.x == 0 => Some(GameOverReason::HitWall),
-            KeyCode::Left => {
-                new_segment.x -= 1;
-                None
-            }
-            KeyCode::Right if new_segment.x == board.size-1 => Some(GameOverReason::HitWall),
-            KeyCode::Right => {
-                new_segment.x += 1;
-                None
-            }
-            _ => panic!(
-                "a key should always have been pressed, even if it's just the default input"
-            ),
-        };
-    // did the snake hit itself?
-    let hit_self = snake
-        .segments
-        .iter()
-        .find(|position| position == &&new_segment)
-        .map(|_| GameOverReason::HitSnake);
+    // several ticks can run in one frame, the state
+    // change only happens at the end of it
+    if state.is_over() || paused.0 {
+        return;
+    }
 
-    let has_won = if snake.segments.len()
-        == board.size as usize * board.size as usize
-    {
-        Some(GameOverReason::Win)
+    let stalled =
+        lockstep.as_ref().map_or(false, |lockstep| {
+            lockstep.scheduled_local(state.tick + 1)
+        });
+    // bots aren't asked again while a peer is late, the
+    // answer couldn't be used
+    let mut decisions = if stalled {
+        vec![None; state.snakes.len()]
+    } else {
+        bots.decide(&state)
+    };
+    let direction = if bots.controls(local.0) {
+        // a bot that doesn't answer in time keeps going
+        // straight
+        decisions[local.0]
+            .unwrap_or(state.snakes[local.0].direction)
     } else {
-        None
+        last_turn.0
     };
 
-    match hit_wall.or(hit_self).or(has_won) {
-        Some(GameOverReason::HitWall)
-        | Some(GameOverReason::HitSnake)
-        | Some(GameOverReason::Win) => {
-            // send game over event
-            audio.play(sounds.gameover.clone());
-            run_state.set(RunState::Menu).unwrap();
+    let inputs = match lockstep.as_mut() {
+        Some(lockstep) => {
+            let tick = state.tick + 1;
+            lockstep.schedule_local(tick, direction);
+            match lockstep.inputs_for(tick) {
+                Some(inputs) => inputs,
+                // still waiting on a peer
+                None => return,
+            }
         }
         None => {
-            snake.segments.push_front(new_segment);
-
-            commands.add(SpawnSnakeSegment {
-                position: snake.segments[0],
-            });
-
-            // remove old snake segment, unless snake just
-            // ate food
-            let is_food = query_food
-                .iter()
-                .find(|(_, pos)| &&new_segment == pos);
-            match is_food {
-                Some((entity, _)) => {
-                    game.score += 1;
-
-                    audio.play(sounds.apple.clone());
-                    commands
-                        .entity(entity)
-                        .despawn_recursive();
-                    food_events.send(NewFoodEvent);
-                }
-                None => {
-                    let segment =
-                        snake.segments.pop_back().unwrap();
-
-                    let position_to_remove = positions
-                        .iter()
-                        .find(|(_, position)| {
-                            position == &&segment
-                        });
-                    if let Some(position) =
-                        position_to_remove
+            for (snake, turn) in pad_turns.0.drain() {
+                match decisions.get_mut(snake) {
+                    Some(decision)
+                        if !bots.controls(snake) =>
                     {
-                        commands
-                            .entity(position.0)
-                            .despawn_recursive();
+                        *decision = Some(turn)
                     }
+                    _ => {}
                 }
             }
+            decisions[local.0] = Some(direction);
+            decisions
         }
     };
 
+    for event in state.step(&inputs) {
+        tick_events.send(event);
+    }
+
+    if let Some(lockstep) = lockstep.as_mut() {
+        lockstep
+            .record_checksum(state.tick, state.checksum());
+    }
+
+    let score = state.snakes[local.0].score;
+    // only the first time past the old best, and not
+    // for the very first game
+    if game.best_at_start > 0
+        && game.score <= game.best_at_start
+        && score > game.best_at_start
+    {
+        new_best.send(NewBestEvent(score));
+    }
+    game.score = score;
     if game.score_best < game.score {
         game.score_best = game.score;
     };
+
+    if state.is_over() {
+        bots.end(&state);
+        run_state.set(RunState::Menu).unwrap();
+    }
+}
+
+/// Builds the `GameRules` for the selected `GameMode`
+/// and seeds `GameRng` with them. Runs before the board
+/// is spawned so the layout comes from the new seed.
+pub fn apply_game_mode(
+    mut commands: Commands,
+    mode: Res<GameMode>,
+    settings: Res<GameSettings>,
+    lockstep: Option<Res<Lockstep>>,
+    mut rules: ResMut<GameRules>,
+    mut rng: ResMut<GameRng>,
+) {
+    *rules = match (*mode, lockstep) {
+        (GameMode::Lan, Some(lockstep)) => {
+            lockstep.rules.clone()
+        }
+        (GameMode::Daily { .. }, _) => {
+            DailyChallenge::today().rules
+        }
+        _ => settings.classic_rules(),
+    };
+    if *mode != GameMode::Lan {
+        commands.remove_resource::<Lockstep>();
+    }
+    rng.0 = match rules.seed {
+        Some(seed) => ChaCha8Rng::seed_from_u64(seed),
+        None => ChaCha8Rng::from_entropy(),
+    };
+}
+
+pub fn apply_game_speed(
+    rules: Res<GameRules>,
+    mut timestep: ResMut<FixedTimestepInfo>,
+) {
+    timestep.step = rules.speed.tick_duration();
 }
 
 pub fn reset_game(
     mut commands: Commands,
-    mut snake: ResMut<SnakeBody>,
-    positions: Query<(
-        Entity,
-        &Position,
-        &TextureAtlasSprite,
-    )>,
-    mut last_pressed: ResMut<LastKeyPress>,
+    mut state: ResMut<GameState>,
+    segments: Query<Entity, With<SnakeSegment>>,
+    mut last_turn: ResMut<LastTurn>,
+    mut ring: ResMut<SegmentEntities>,
+    mut food_sprites: ResMut<FoodSprites>,
     food_query: Query<Entity, With<Food>>,
+    wall_query: Query<Entity, With<Wall>>,
     mut food_events: EventWriter<NewFoodEvent>,
+    mode: Res<GameMode>,
+    lockstep: Option<Res<Lockstep>>,
+    mut bots: ResMut<Bots>,
+    pads: Res<PadAssignments>,
+    rules: Res<GameRules>,
+    mut rng: ResMut<GameRng>,
+    (mut game, mut paused): (ResMut<Game>, ResMut<Paused>),
 ) {
-    for entity in food_query.iter() {
+    for entity in food_query
+        .iter()
+        .chain(wall_query.iter())
+        .chain(segments.iter())
+    {
         commands.entity(entity).despawn_recursive();
     }
+    food_sprites.0.clear();
 
-    for position in positions.iter() {
-        commands.entity(position.0).despawn_recursive();
-    }
+    let players = match (*mode, lockstep) {
+        (GameMode::Lan, Some(lockstep)) => lockstep.players,
+        // extra gamepads join classic games, the daily
+        // challenge stays single player
+        (GameMode::Classic, _) => {
+            bots.players().max(pads.players())
+        }
+        _ => bots.players(),
+    };
+    let seed = rules.seed.unwrap_or_else(|| rng.0.gen());
+    *state = GameState::new(rules.clone(), players, seed);
+    bots.start(&state);
 
-    food_events.send(NewFoodEvent);
-    *snake = SnakeBody::default();
-    *last_pressed = LastKeyPress(KeyCode::Right);
+    *ring = SegmentEntities::spawn(&mut commands, &state);
+    for position in state.walls.iter() {
+        commands.add(SpawnWall {
+            position: *position,
+        });
+    }
+    for position in state.food.iter() {
+        food_events.send(NewFoodEvent(*position));
+    }
+    *last_turn = LastTurn::default();
+    game.score = 0;
+    game.best_at_start = game.score_best;
+    paused.0 = false;
 }
@@ -0,0 +1,639 @@
+use std::{
+    collections::HashMap, fmt::Display, time::Duration,
+};
+
+use bevy::{
+    asset::{
+        AssetLoader, BoxedFuture, LoadContext, LoadedAsset,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::settings::GameSettings;
+
+const LOCALE_FOLDER: &str = "locales";
+/// The language every other one falls back to. It is
+/// built in, so there is always text to show while the
+/// files load.
+pub const BASE_LANGUAGE: &str = "en";
+const BASE_LOCALE: &str =
+    include_str!("../assets/locales/en.locale.ron");
+
+/// Translated UI text from `.locale.ron` files, resolved
+/// into `Localization` together with the languages it falls
+/// back to.
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Locale>()
+            .init_asset_loader::<LocaleLoader>()
+            .init_resource::<Locales>()
+            .init_resource::<Languages>()
+            .init_resource::<Localization>()
+            .add_system(list_languages)
+            .add_system(apply_language.label("localize"))
+            .add_system(localize_text.after("localize"));
+    }
+}
+
+/// One language as it's written in its file. Messages
+/// can use `{name}` placeholders, which are filled in
+/// by `Localization::format`.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    TypeUuid,
+)]
+#[uuid = "2f5a4f0e-73c4-4bd4-8a8e-5d0e2b0c6a31"]
+pub struct Locale {
+    /// Stored in the settings, like `en` or `de-AT`
+    pub code: String,
+    /// The language's own name for itself, for the
+    /// picker
+    pub name: String,
+    /// Where missing messages are looked up next.
+    /// `de-AT` falls back to `de` without saying so,
+    /// and everything ends up at `BASE_LANGUAGE`.
+    #[serde(default)]
+    pub fallback: Option<String>,
+    #[serde(default)]
+    pub plural_rule: PluralRule,
+    #[serde(default)]
+    pub numbers: NumberFormat,
+    #[serde(default)]
+    pub messages: HashMap<String, String>,
+    #[serde(default)]
+    pub plurals: HashMap<String, Plural>,
+}
+
+impl Locale {
+    fn next_language(&self) -> Option<String> {
+        self.fallback.clone().or_else(|| {
+            self.code
+                .split_once('-')
+                .map(|(language, _)| language.to_string())
+        })
+    }
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
+pub enum PluralRule {
+    /// `one` for 1, `other` for the rest, like English
+    #[default]
+    OneOther,
+    /// `one` for 0 and 1, like French
+    ZeroOneOther,
+    /// `one`, `few` and `many` by the last digits, like
+    /// Polish or Russian
+    Slavic,
+    /// Always `other`, like Japanese
+    Invariant,
+}
+
+impl PluralRule {
+    fn form<'a>(
+        &self,
+        plural: &'a Plural,
+        count: u64,
+    ) -> &'a str {
+        let form = match self {
+            PluralRule::OneOther if count == 1 => {
+                plural.one.as_ref()
+            }
+            PluralRule::ZeroOneOther if count <= 1 => {
+                plural.one.as_ref()
+            }
+            PluralRule::Slavic => {
+                let (ones, tens) =
+                    (count % 10, count % 100);
+                if ones == 1 && tens != 11 {
+                    plural.one.as_ref()
+                } else if (2..=4).contains(&ones)
+                    && !(12..=14).contains(&tens)
+                {
+                    plural.few.as_ref()
+                } else {
+                    plural.many.as_ref()
+                }
+            }
+            _ => None,
+        };
+        form.unwrap_or(&plural.other)
+    }
+}
+
+/// The forms of a counted message. `{count}` is
+/// replaced with the count. Forms the language's rule
+/// doesn't use can be left out, and any that are
+/// missing use `other`.
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct Plural {
+    #[serde(default)]
+    pub one: Option<String>,
+    #[serde(default)]
+    pub few: Option<String>,
+    #[serde(default)]
+    pub many: Option<String>,
+    pub other: String,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct NumberFormat {
+    pub decimal: char,
+    pub grouping: Option<char>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimal: '.',
+            grouping: Some(','),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LocaleLoader;
+
+impl AssetLoader for LocaleLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let locale: Locale =
+                ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(
+                LoadedAsset::new(locale),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["locale.ron"]
+    }
+}
+
+pub struct Locales(pub Vec<HandleUntyped>);
+
+impl FromWorld for Locales {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server =
+            world.get_resource::<AssetServer>().unwrap();
+        match asset_server.load_folder(LOCALE_FOLDER) {
+            Ok(handles) => Locales(handles),
+            Err(error) => {
+                warn!(%error, "failed to load locales");
+                Locales(vec![])
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Languages(pub Vec<(String, String)>);
+
+impl Languages {
+    pub fn after(&self, code: &str) -> Option<&str> {
+        let next = self
+            .0
+            .iter()
+            .position(|(other, _)| other == code)
+            .map_or(0, |index| index + 1);
+        self.0
+            .get(next % self.0.len().max(1))
+            .map(|(code, _)| code.as_str())
+    }
+}
+
+/// The language in use, followed by the ones it falls back
+/// to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Localization {
+    chain: Vec<Locale>,
+}
+
+impl Default for Localization {
+    fn default() -> Self {
+        Localization {
+            chain: vec![base_locale()],
+        }
+    }
+}
+
+fn base_locale() -> Locale {
+    ron::de::from_str(BASE_LOCALE)
+        .expect("the built-in locale is valid")
+}
+
+impl Localization {
+    /// The language that was picked, or the first one
+    /// that stood in for it.
+    pub fn language(&self) -> &Locale {
+        &self.chain[0]
+    }
+
+    /// The message for `key`. Missing messages show
+    /// the key itself, so they are easy to spot.
+    pub fn get(&self, key: &str) -> String {
+        self.chain
+            .iter()
+            .find_map(|locale| locale.messages.get(key))
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+
+    pub fn format(
+        &self,
+        key: &str,
+        args: &[(&str, &dyn Display)],
+    ) -> String {
+        fill(self.get(key), args)
+    }
+
+    pub fn plural(&self, key: &str, count: u64) -> String {
+        let found = self.chain.iter().find_map(|locale| {
+            locale
+                .plurals
+                .get(key)
+                .map(|plural| (locale, plural))
+        });
+        match found {
+            Some((locale, plural)) => fill(
+                locale
+                    .plural_rule
+                    .form(plural, count)
+                    .to_string(),
+                &[("count", &self.number(count))],
+            ),
+            None => key.to_string(),
+        }
+    }
+
+    pub fn number(&self, number: u64) -> String {
+        let digits = number.to_string();
+        let separator =
+            match self.language().numbers.grouping {
+                Some(separator) => separator,
+                None => return digits,
+            };
+        let mut grouped = String::new();
+        for (index, digit) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index) % 3 == 0
+            {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    pub fn decimal(
+        &self,
+        value: f32,
+        places: usize,
+    ) -> String {
+        format!("{:.*}", places, value).replace(
+            '.',
+            &self.language().numbers.decimal.to_string(),
+        )
+    }
+
+    /// `fraction` from 0 to 1 as a whole percentage.
+    pub fn percent(&self, fraction: f32) -> String {
+        self.format(
+            "format.percent",
+            &[(
+                "value",
+                &self.decimal(fraction * 100.0, 0),
+            )],
+        )
+    }
+
+    pub fn duration(&self, duration: Duration) -> String {
+        // rounded before splitting, so 59.96s is a minute
+        // and not 60.0s
+        let tenths =
+            (duration.as_secs_f64() * 10.0).round() as u64;
+        let minutes = tenths / 600;
+        let seconds = (tenths % 600) as f32 / 10.0;
+        if minutes == 0 {
+            return self.format(
+                "format.seconds",
+                &[("seconds", &self.decimal(seconds, 1))],
+            );
+        }
+        // zero padded to two digits before the decimal
+        let padding = if seconds < 10.0 { "0" } else { "" };
+        self.format(
+            "format.minutes",
+            &[
+                ("minutes", &minutes),
+                (
+                    "seconds",
+                    &format!(
+                        "{}{}",
+                        padding,
+                        self.decimal(seconds, 1)
+                    ),
+                ),
+            ],
+        )
+    }
+}
+
+fn fill(
+    mut message: String,
+    args: &[(&str, &dyn Display)],
+) -> String {
+    for (name, value) in args {
+        message = message.replace(
+            &format!("{{{}}}", name),
+            &value.to_string(),
+        );
+    }
+    message
+}
+
+fn list_languages(
+    mut events: EventReader<AssetEvent<Locale>>,
+    locales: Res<Assets<Locale>>,
+    mut languages: ResMut<Languages>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let mut list = locales
+        .iter()
+        .map(|(_, locale)| {
+            (locale.code.clone(), locale.name.clone())
+        })
+        .collect::<Vec<(String, String)>>();
+    list.sort();
+    list.dedup();
+    if languages.0 != list {
+        languages.0 = list;
+    }
+}
+
+/// Switches to the language picked in the settings, or
+/// to a locale's new contents when its file is saved.
+fn apply_language(
+    settings: Res<GameSettings>,
+    locales: Res<Assets<Locale>>,
+    mut events: EventReader<AssetEvent<Locale>>,
+    mut localization: ResMut<Localization>,
+) {
+    let loaded = events.iter().count() > 0;
+    if !loaded && !settings.is_changed() {
+        return;
+    }
+    let find = |code: &str| {
+        locales
+            .iter()
+            .map(|(_, locale)| locale)
+            .find(|locale| locale.code == code)
+            .cloned()
+    };
+
+    let chain = resolve_chain(&settings.language, find);
+    if localization.chain != chain {
+        localization.chain = chain;
+    }
+}
+
+/// `language` and the languages it falls back to, as
+/// far as `find` has them, ending at `BASE_LANGUAGE`.
+fn resolve_chain(
+    language: &str,
+    find: impl Fn(&str) -> Option<Locale>,
+) -> Vec<Locale> {
+    let mut chain: Vec<Locale> = vec![];
+    let mut next = Some(language.to_string());
+    while let Some(code) = next.take() {
+        // a fallback pointing back at an earlier one
+        if chain.iter().any(|locale| locale.code == code) {
+            break;
+        }
+        match find(&code) {
+            Some(locale) => {
+                next = locale.next_language();
+                chain.push(locale);
+            }
+            None => break,
+        }
+    }
+    if !chain
+        .iter()
+        .any(|locale| locale.code == BASE_LANGUAGE)
+    {
+        chain.push(
+            find(BASE_LANGUAGE).unwrap_or_else(base_locale),
+        );
+    }
+    chain
+}
+
+/// Bevy UI text showing the message `0`, kept in the
+/// current language.
+#[derive(Component)]
+pub struct Localized(pub &'static str);
+
+fn localize_text(
+    localization: Res<Localization>,
+    mut texts: Query<(
+        &Localized,
+        ChangeTrackers<Localized>,
+        &mut Text,
+    )>,
+) {
+    for (localized, tracker, mut text) in texts.iter_mut() {
+        if localization.is_changed() || tracker.is_added() {
+            text.sections[0].value =
+                localization.get(localized.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(
+        code: &str,
+        fallback: Option<&str>,
+    ) -> Locale {
+        Locale {
+            code: code.to_string(),
+            name: code.to_string(),
+            fallback: fallback.map(str::to_string),
+            plural_rule: PluralRule::default(),
+            numbers: NumberFormat::default(),
+            messages: HashMap::new(),
+            plurals: HashMap::new(),
+        }
+    }
+
+    fn plural() -> Plural {
+        Plural {
+            one: Some("one".to_string()),
+            few: Some("few".to_string()),
+            many: Some("many".to_string()),
+            other: "other".to_string(),
+        }
+    }
+
+    #[test]
+    fn plural_forms() {
+        let plural = plural();
+        let forms = |rule: PluralRule, counts: &[u64]| {
+            counts
+                .iter()
+                .map(|count| rule.form(&plural, *count))
+                .collect::<Vec<&str>>()
+        };
+        assert_eq!(
+            forms(PluralRule::OneOther, &[0, 1, 2]),
+            ["other", "one", "other"]
+        );
+        assert_eq!(
+            forms(PluralRule::ZeroOneOther, &[0, 1, 2]),
+            ["one", "one", "other"]
+        );
+        assert_eq!(
+            forms(
+                PluralRule::Slavic,
+                &[1, 2, 5, 11, 12, 21, 22, 25, 112]
+            ),
+            [
+                "one", "few", "many", "many", "many",
+                "one", "few", "many", "many"
+            ]
+        );
+        assert_eq!(
+            forms(PluralRule::Invariant, &[0, 1, 2]),
+            ["other", "other", "other"]
+        );
+    }
+
+    #[test]
+    fn missing_forms_use_other() {
+        let plural = Plural {
+            one: None,
+            few: None,
+            many: None,
+            other: "other".to_string(),
+        };
+        assert_eq!(
+            PluralRule::Slavic.form(&plural, 2),
+            "other"
+        );
+    }
+
+    #[test]
+    fn number_grouping() {
+        let mut localization = Localization::default();
+        assert_eq!(localization.number(0), "0");
+        assert_eq!(localization.number(999), "999");
+        assert_eq!(localization.number(1000), "1,000");
+        assert_eq!(
+            localization.number(1234567),
+            "1,234,567"
+        );
+
+        localization.chain[0].numbers.grouping = None;
+        assert_eq!(localization.number(1234567), "1234567");
+    }
+
+    #[test]
+    fn durations_round_before_splitting() {
+        let localization = Localization::default();
+        let duration = |seconds: f64| {
+            localization
+                .duration(Duration::from_secs_f64(seconds))
+        };
+        assert_eq!(duration(5.04), "5.0s");
+        assert_eq!(duration(59.94), "59.9s");
+        assert_eq!(duration(59.96), "1:00.0");
+        assert_eq!(duration(65.5), "1:05.5");
+        assert_eq!(duration(119.96), "2:00.0");
+    }
+
+    #[test]
+    fn fallback_chain() {
+        let mut de = locale("de", None);
+        de.messages.insert(
+            "hello".to_string(),
+            "Hallo".to_string(),
+        );
+        de.numbers.grouping = Some('.');
+        let mut swiss = locale("de-CH", None);
+        swiss.numbers.grouping = Some('\'');
+        let mut en = locale(BASE_LANGUAGE, None);
+        en.plurals.insert("apples".to_string(), plural());
+        let locales = [de, swiss, en];
+        let find = |code: &str| {
+            locales
+                .iter()
+                .find(|locale| locale.code == code)
+                .cloned()
+        };
+
+        let localization = Localization {
+            chain: resolve_chain("de-CH", find),
+        };
+        let codes = localization
+            .chain
+            .iter()
+            .map(|locale| locale.code.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(codes, ["de-CH", "de", "en"]);
+        assert_eq!(localization.get("hello"), "Hallo");
+        assert_eq!(localization.get("missing"), "missing");
+        // counts come from the language that has the
+        // message, digits from the one picked
+        assert_eq!(localization.plural("apples", 1), "one");
+        assert_eq!(localization.number(1000), "1'000");
+    }
+
+    #[test]
+    fn fallback_loops_end_at_base() {
+        let locales = [
+            locale("a", Some("b")),
+            locale("b", Some("a")),
+        ];
+        let chain = resolve_chain("a", |code| {
+            locales
+                .iter()
+                .find(|locale| locale.code == code)
+                .cloned()
+        });
+        let codes = chain
+            .iter()
+            .map(|locale| locale.code.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(codes, ["a", "b", BASE_LANGUAGE]);
+    }
+}
@@ -1,19 +1,42 @@
-use bevy::prelude::*;
+use bevy::{asset::AssetServerSettings, prelude::*};
 use bevy_kira_audio::AudioPlugin;
 use bevy_snake::{
+    accessibility::AccessibilityPlugin,
+    actions::ActionsPlugin,
+    apply_game_mode, apply_game_speed,
     assets::AssetsPlugin,
-    board::spawn_board,
-    common::{Game, RunState},
-    control::{user_input, LastKeyPress},
-    food::{food_event_listener, NewFoodEvent},
+    board::{fit_camera, spawn_board},
+    bot::{run_headless, BotPlugin, BotSettings},
+    common::{
+        Game, GameMode, GameRng, LocalPlayer, MainCamera,
+        NewBestEvent, Paused, RunState,
+    },
+    control::{user_input, LastTurn},
+    daily::DailyPlugin,
+    effects::EffectsPlugin,
+    food::{
+        food_event_listener, sync_food, FoodSprites,
+        NewFoodEvent,
+    },
+    gamepad::GamepadPlugin,
+    grass::GrassPlugin,
+    guidance::GuidancePlugin,
+    locale::LocalePlugin,
+    net::NetPlugin,
     reset_game,
+    rules::{GameState, TickEvent},
     scoring::SpeedrunPlugin,
-    settings::GameSettings,
+    settings::{save_settings, GameRules, GameSettings},
+    skins::SkinsPlugin,
     snake::{
-        render_snake_segments, SnakeBody,
-        SnakeTextureSelection,
+        interpolate_segments, render_snake_segments,
+        sync_segments, sync_tick_clock, SegmentEntities,
+        SnakeTextureSelection, TickClock,
     },
     snake_movement,
+    sound::SoundPlugin,
+    spectate::SpectatePlugin,
+    theme::ThemePlugin,
     ui::GameUiPlugin,
 };
 use iyes_loopless::prelude::*;
@@ -21,38 +44,85 @@ use kayak_ui::bevy::BevyKayakUIPlugin;
 use std::time::Duration;
 
 fn main() {
+    let bots = BotSettings::from_args();
+    if bots.headless {
+        run_headless(&bots);
+        return;
+    }
+
     App::new()
         .insert_resource(WindowDescriptor {
             title: "Snake!".to_string(),
             ..Default::default()
         })
+        // themes and their images reload when saved
+        .insert_resource(AssetServerSettings {
+            watch_for_changes: true,
+            ..Default::default()
+        })
         .add_plugins(DefaultPlugins)
         .add_plugin(AudioPlugin)
         .add_plugin(AssetsPlugin)
         .add_plugin(BevyKayakUIPlugin)
+        .add_plugin(ThemePlugin)
+        .add_plugin(LocalePlugin)
+        .add_plugin(SkinsPlugin)
+        .add_plugin(AccessibilityPlugin)
         .add_plugin(SpeedrunPlugin)
         .add_plugin(GameUiPlugin)
+        .add_plugin(DailyPlugin)
+        .add_plugin(NetPlugin)
+        .add_plugin(SpectatePlugin)
+        .add_plugin(BotPlugin)
+        .add_plugin(GamepadPlugin)
+        .add_plugin(ActionsPlugin)
+        .add_plugin(EffectsPlugin)
+        .add_plugin(GrassPlugin)
+        .add_plugin(SoundPlugin)
+        .add_plugin(GuidancePlugin)
         .add_event::<NewFoodEvent>()
-        .insert_resource(ClearColor(Color::rgb(
-            0.52, 0.73, 0.17,
-        )))
+        .add_event::<TickEvent>()
+        .add_event::<NewBestEvent>()
         .init_resource::<Game>()
-        .init_resource::<SnakeBody>()
+        .init_resource::<GameState>()
+        .init_resource::<LocalPlayer>()
         .init_resource::<SnakeTextureSelection>()
-        .init_resource::<GameSettings>()
-        .init_resource::<LastKeyPress>()
+        .init_resource::<TickClock>()
+        .init_resource::<SegmentEntities>()
+        .init_resource::<FoodSprites>()
+        .insert_resource(GameSettings::load())
+        .init_resource::<GameMode>()
+        .init_resource::<GameRules>()
+        .init_resource::<GameRng>()
+        .init_resource::<LastTurn>()
+        .init_resource::<Paused>()
         .add_startup_system(setup)
         .add_startup_system(spawn_board)
+        .add_system(save_settings)
+        .add_system(fit_camera)
         .add_state(RunState::Menu)
         .add_system_set(
             SystemSet::on_update(RunState::Playing)
                 .with_system(user_input)
                 .with_system(food_event_listener)
-                .with_system(render_snake_segments),
+                .with_system(
+                    render_snake_segments
+                        .label("render_snake"),
+                )
+                .with_system(
+                    interpolate_segments
+                        .after("render_snake"),
+                ),
         )
         .add_system_set(
             SystemSet::on_enter(RunState::Playing)
-                .with_system(reset_game),
+                .with_system(apply_game_mode.label("rules"))
+                .with_system(
+                    spawn_board
+                        .label("board")
+                        .after("rules"),
+                )
+                .with_system(reset_game.after("board")),
         )
         .add_stage_before(
             CoreStage::Update,
@@ -61,11 +131,32 @@ fn main() {
                 100,
             ))
             .with_stage(
-                SystemStage::parallel().with_system(
-                    snake_movement.run_in_bevy_state(
-                        RunState::Playing,
+                SystemStage::parallel()
+                    .with_system(apply_game_speed)
+                    .with_system(
+                        snake_movement.run_in_bevy_state(
+                            RunState::Playing,
+                        ),
+                    ),
+            )
+            // after the tick's events are out
+            .with_stage(
+                SystemStage::parallel()
+                    .with_system(
+                        sync_tick_clock.run_in_bevy_state(
+                            RunState::Playing,
+                        ),
+                    )
+                    .with_system(
+                        sync_segments.run_in_bevy_state(
+                            RunState::Playing,
+                        ),
+                    )
+                    .with_system(
+                        sync_food.run_in_bevy_state(
+                            RunState::Playing,
+                        ),
                     ),
-                ),
             ),
         )
         .run();
@@ -73,5 +164,6 @@ fn main() {
 
 fn setup(mut commands: Commands) {
     commands
-        .spawn_bundle(OrthographicCameraBundle::new_2d());
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
 }
@@ -0,0 +1,816 @@
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{GameMode, GameRng, LocalPlayer, RunState},
+    rules::Direction,
+    settings::GameRules,
+};
+
+pub const DEFAULT_PORT: u16 = 7878;
+pub const MAX_PLAYERS: usize = GameRules::MAX_PLAYERS;
+/// Every input packet repeats this many of the most
+/// recent ticks, so a lost packet doesn't stall the
+/// game.
+const INPUT_WINDOW: u64 = 8;
+const JOIN_RESEND: Duration = Duration::from_millis(500);
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct NetPlugin;
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LobbySettings::from_args())
+            .init_resource::<LobbyStatus>()
+            .add_event::<LobbyEvent>()
+            .add_startup_system(lobby_from_args)
+            .add_system(handle_lobby_events)
+            .add_system(network.after(handle_lobby_events));
+    }
+}
+
+/// Addresses used by the lobby screen. Can be set from
+/// the command line so several processes can be started
+/// against each other on localhost:
+///
+/// `bevy-snake --host 0.0.0.0:7878`
+/// `bevy-snake --join 127.0.0.1:7878`
+#[derive(Debug, Clone)]
+pub struct LobbySettings {
+    pub host_addr: String,
+    pub join_addr: String,
+    pub input_delay: u64,
+    /// set when `--host` or `--join` was passed
+    autostart: Option<LobbyEvent>,
+}
+
+impl LobbySettings {
+    fn from_args() -> Self {
+        let mut settings = LobbySettings {
+            host_addr: format!("0.0.0.0:{}", DEFAULT_PORT),
+            join_addr: format!(
+                "127.0.0.1:{}",
+                DEFAULT_PORT
+            ),
+            input_delay: 3,
+            autostart: None,
+        };
+        let mut args = std::env::args().skip(1).peekable();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next_if(|value| !value.starts_with("--"));
+            match (arg.as_str(), value) {
+                ("--host", value) => {
+                    if let Some(addr) = value {
+                        settings.host_addr = addr;
+                    }
+                    settings.autostart =
+                        Some(LobbyEvent::Host(
+                            settings.host_addr.clone(),
+                        ));
+                }
+                ("--join", Some(addr)) => {
+                    settings.join_addr = addr.clone();
+                    settings.autostart =
+                        Some(LobbyEvent::Join(addr));
+                }
+                ("--input-delay", Some(delay)) => {
+                    if let Ok(delay) = delay.parse() {
+                        settings.input_delay = delay;
+                    }
+                }
+                _ => {}
+            }
+        }
+        settings
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LobbyEvent {
+    Host(String),
+    Join(String),
+    /// host only, starts a game with everyone who joined
+    Start,
+    Leave,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum LobbyStatus {
+    #[default]
+    Offline,
+    Hosting { addr: String, players: usize },
+    Joining { addr: String },
+    Joined { players: usize },
+    Error(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum NetMessage {
+    Join,
+    Lobby {
+        players: usize,
+    },
+    Start {
+        game: u32,
+        player: usize,
+        players: usize,
+        seed: u64,
+        rules: GameRules,
+        input_delay: u64,
+    },
+    Inputs {
+        game: u32,
+        player: usize,
+        /// tick of `directions[0]`
+        first_tick: u64,
+        directions: Vec<Option<Direction>>,
+        checksums: Vec<(u64, u64)>,
+    },
+}
+
+enum Role {
+    Host { clients: Vec<SocketAddr> },
+    Client { host: SocketAddr },
+}
+
+/// An open socket, either hosting or joined to a host.
+/// Clients only talk to the host, which relays inputs
+/// to everyone else.
+pub struct NetSession {
+    socket: UdpSocket,
+    role: Role,
+    game: u32,
+    last_sent: Option<Instant>,
+    /// the last `Start` sent to each client, resent if
+    /// the client asks to join again
+    starts: Vec<NetMessage>,
+}
+
+impl NetSession {
+    fn host(addr: &str) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(NetSession {
+            socket,
+            role: Role::Host { clients: vec![] },
+            game: 0,
+            last_sent: None,
+            starts: vec![],
+        })
+    }
+
+    fn join(addr: &str) -> std::io::Result<Self> {
+        let host = addr.parse().map_err(|_| {
+            std::io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not an address", addr),
+            )
+        })?;
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_nonblocking(true)?;
+        Ok(NetSession {
+            socket,
+            role: Role::Client { host },
+            game: 0,
+            last_sent: None,
+            starts: vec![],
+        })
+    }
+
+    fn send(&self, to: SocketAddr, message: &NetMessage) {
+        let bytes = serde_json::to_vec(message).unwrap();
+        if let Err(error) = self.socket.send_to(&bytes, to)
+        {
+            warn!(%to, %error, "failed to send packet");
+        }
+    }
+
+    fn send_to_peers(
+        &self,
+        message: &NetMessage,
+        except: Option<SocketAddr>,
+    ) {
+        match &self.role {
+            Role::Host { clients } => {
+                for client in clients {
+                    if Some(*client) != except {
+                        self.send(*client, message);
+                    }
+                }
+            }
+            Role::Client { host } => {
+                self.send(*host, message)
+            }
+        }
+    }
+
+    fn receive(&self) -> Vec<(SocketAddr, NetMessage)> {
+        let mut messages = vec![];
+        let mut buffer = [0; 4096];
+        loop {
+            match self.socket.recv_from(&mut buffer) {
+                Ok((len, from)) => {
+                    match serde_json::from_slice(
+                        &buffer[..len],
+                    ) {
+                        Ok(message) => {
+                            messages.push((from, message))
+                        }
+                        Err(error) => {
+                            warn!(%from, %error, "bad packet")
+                        }
+                    }
+                }
+                Err(error)
+                    if error.kind()
+                        == ErrorKind::WouldBlock =>
+                {
+                    break
+                }
+                Err(error) => {
+                    // windows reports ICMP port
+                    // unreachable as an error here
+                    warn!(%error, "failed to receive");
+                    break;
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// Input buffer for a running lockstep game. A tick is
+/// only simulated once every player's input for it has
+/// arrived. Local inputs are scheduled `input_delay`
+/// ticks ahead to hide latency.
+pub struct Lockstep {
+    pub player: usize,
+    pub players: usize,
+    pub seed: u64,
+    pub rules: GameRules,
+    game: u32,
+    input_delay: u64,
+    /// per tick, per player. `None` until it arrived.
+    inputs: BTreeMap<u64, Vec<Option<Option<Direction>>>>,
+    checksums: BTreeMap<u64, u64>,
+    remote_checksums: BTreeMap<u64, u64>,
+    last_heard: Vec<Instant>,
+    /// the first tick where a peer disagreed with us
+    pub desync: Option<u64>,
+}
+
+impl Lockstep {
+    fn new(
+        game: u32,
+        player: usize,
+        players: usize,
+        seed: u64,
+        rules: GameRules,
+        input_delay: u64,
+    ) -> Self {
+        Lockstep {
+            player,
+            players,
+            seed,
+            rules,
+            game,
+            input_delay,
+            inputs: BTreeMap::new(),
+            checksums: BTreeMap::new(),
+            remote_checksums: BTreeMap::new(),
+            last_heard: vec![Instant::now(); players],
+            desync: None,
+        }
+    }
+
+    fn slot(
+        &mut self,
+        tick: u64,
+    ) -> &mut Vec<Option<Option<Direction>>> {
+        let players = self.players;
+        self.inputs
+            .entry(tick)
+            .or_insert_with(|| vec![None; players])
+    }
+
+    /// Schedules the local input for the tick that is
+    /// `input_delay` after `tick`. Inputs that were
+    /// already scheduled are never changed since peers
+    /// may have seen them.
+    pub fn schedule_local(
+        &mut self,
+        tick: u64,
+        direction: Direction,
+    ) {
+        let player = self.player;
+        let slot =
+            &mut self.slot(tick + self.input_delay)[player];
+        if slot.is_none() {
+            *slot = Some(Some(direction));
+        }
+    }
+
+    /// Whether the local input for the tick that is
+    /// `input_delay` after `tick` is already scheduled.
+    /// While a peer is late it is, and nothing new needs
+    /// deciding.
+    pub fn scheduled_local(&self, tick: u64) -> bool {
+        self.inputs
+            .get(&(tick + self.input_delay))
+            .map_or(false, |slot| {
+                slot[self.player].is_some()
+            })
+    }
+
+    pub fn inputs_for(
+        &mut self,
+        tick: u64,
+    ) -> Option<Vec<Option<Direction>>> {
+        // nobody can have input for the first ticks
+        if tick <= self.input_delay {
+            return Some(vec![None; self.players]);
+        }
+        let inputs = self
+            .inputs
+            .get(&tick)?
+            .iter()
+            .copied()
+            .collect::<Option<Vec<Option<Direction>>>>()?;
+
+        // inputs this old can't be resent anymore
+        let oldest = tick.saturating_sub(INPUT_WINDOW * 4);
+        self.inputs = self.inputs.split_off(&oldest);
+        self.checksums = self.checksums.split_off(&oldest);
+        self.remote_checksums =
+            self.remote_checksums.split_off(&oldest);
+        Some(inputs)
+    }
+
+    pub fn record_checksum(
+        &mut self,
+        tick: u64,
+        checksum: u64,
+    ) {
+        self.checksums.insert(tick, checksum);
+        self.compare_checksum(tick);
+    }
+
+    fn compare_checksum(&mut self, tick: u64) {
+        if let (Some(local), Some(remote)) = (
+            self.checksums.get(&tick),
+            self.remote_checksums.get(&tick),
+        ) {
+            // checksums arrive newest first, keep the
+            // earliest tick that disagreed
+            if local != remote
+                && self
+                    .desync
+                    .map_or(true, |desync| tick < desync)
+            {
+                error!(tick, local, remote, "desync");
+                self.desync = Some(tick);
+            }
+        }
+    }
+
+    fn receive(
+        &mut self,
+        player: usize,
+        first_tick: u64,
+        directions: Vec<Option<Direction>>,
+        checksums: Vec<(u64, u64)>,
+    ) {
+        if player >= self.players || player == self.player {
+            return;
+        }
+        self.last_heard[player] = Instant::now();
+        for (tick, direction) in
+            (first_tick..).zip(directions)
+        {
+            let slot = &mut self.slot(tick)[player];
+            if slot.is_none() {
+                *slot = Some(direction);
+            }
+        }
+        for (tick, checksum) in checksums {
+            self.remote_checksums.insert(tick, checksum);
+            self.compare_checksum(tick);
+        }
+    }
+
+    /// The local inputs and checksums peers might still
+    /// be waiting on.
+    fn outgoing(&self) -> NetMessage {
+        let local = self
+            .inputs
+            .iter()
+            .filter_map(|(tick, inputs)| {
+                inputs[self.player]
+                    .map(|direction| (*tick, direction))
+            })
+            .collect::<Vec<(u64, Option<Direction>)>>();
+        let recent = &local[local
+            .len()
+            .saturating_sub(INPUT_WINDOW as usize)..];
+        let checksums = self
+            .checksums
+            .iter()
+            .rev()
+            .take(INPUT_WINDOW as usize)
+            .map(|(tick, checksum)| (*tick, *checksum))
+            .collect();
+        NetMessage::Inputs {
+            game: self.game,
+            player: self.player,
+            first_tick: recent
+                .first()
+                .map(|(tick, _)| *tick)
+                .unwrap_or_default(),
+            directions: recent
+                .iter()
+                .map(|(_, direction)| *direction)
+                .collect(),
+            checksums,
+        }
+    }
+
+    fn timed_out(&self) -> bool {
+        self.last_heard.iter().enumerate().any(
+            |(player, heard)| {
+                player != self.player
+                    && heard.elapsed() > PEER_TIMEOUT
+            },
+        )
+    }
+}
+
+fn lobby_from_args(
+    settings: Res<LobbySettings>,
+    mut events: EventWriter<LobbyEvent>,
+) {
+    if let Some(event) = &settings.autostart {
+        events.send(event.clone());
+    }
+}
+
+fn handle_lobby_events(
+    mut commands: Commands,
+    mut events: EventReader<LobbyEvent>,
+    mut session: Option<ResMut<NetSession>>,
+    mut status: ResMut<LobbyStatus>,
+    settings: Res<LobbySettings>,
+    mut rng: ResMut<GameRng>,
+    mut mode: ResMut<GameMode>,
+    mut local: ResMut<LocalPlayer>,
+    mut run_state: ResMut<State<RunState>>,
+) {
+    for event in events.iter() {
+        match event {
+            LobbyEvent::Host(addr) => {
+                match NetSession::host(addr) {
+                    Ok(new_session) => {
+                        *status = LobbyStatus::Hosting {
+                            addr: addr.clone(),
+                            players: 1,
+                        };
+                        commands
+                            .insert_resource(new_session);
+                    }
+                    Err(error) => {
+                        *status = LobbyStatus::Error(
+                            error.to_string(),
+                        );
+                    }
+                }
+            }
+            LobbyEvent::Join(addr) => {
+                match NetSession::join(addr) {
+                    Ok(new_session) => {
+                        *status = LobbyStatus::Joining {
+                            addr: addr.clone(),
+                        };
+                        commands
+                            .insert_resource(new_session);
+                    }
+                    Err(error) => {
+                        *status = LobbyStatus::Error(
+                            error.to_string(),
+                        );
+                    }
+                }
+            }
+            LobbyEvent::Start => {
+                let session = match session.as_mut() {
+                    Some(session) => session,
+                    None => continue,
+                };
+                let clients = match &session.role {
+                    Role::Host { clients } => {
+                        clients.clone()
+                    }
+                    Role::Client { .. } => continue,
+                };
+                session.game += 1;
+                let game = session.game;
+                let players = clients.len() + 1;
+                let seed = rng.0.gen();
+                let rules = GameRules {
+                    seed: Some(seed),
+                    ..GameRules::default()
+                };
+                session.starts = clients
+                    .iter()
+                    .enumerate()
+                    .map(|(index, _)| NetMessage::Start {
+                        game,
+                        player: index + 1,
+                        players,
+                        seed,
+                        rules: rules.clone(),
+                        input_delay: settings.input_delay,
+                    })
+                    .collect();
+                for (client, start) in
+                    clients.iter().zip(&session.starts)
+                {
+                    session.send(*client, start);
+                }
+                commands.insert_resource(Lockstep::new(
+                    game,
+                    0,
+                    players,
+                    seed,
+                    rules,
+                    settings.input_delay,
+                ));
+                *mode = GameMode::Lan;
+                local.0 = 0;
+                let _ = run_state.set(RunState::Playing);
+            }
+            LobbyEvent::Leave => {
+                commands.remove_resource::<NetSession>();
+                commands.remove_resource::<Lockstep>();
+                *status = LobbyStatus::Offline;
+                *mode = GameMode::Classic;
+                local.0 = 0;
+            }
+        }
+    }
+}
+
+/// Sends and receives everything for the lobby and
+/// running lockstep games. Runs every frame, not on the
+/// game tick, so packets are handled while a tick is
+/// stalled waiting for input.
+fn network(
+    mut commands: Commands,
+    session: Option<ResMut<NetSession>>,
+    mut lockstep: Option<ResMut<Lockstep>>,
+    mut status: ResMut<LobbyStatus>,
+    mut mode: ResMut<GameMode>,
+    mut local: ResMut<LocalPlayer>,
+    mut run_state: ResMut<State<RunState>>,
+) {
+    let mut session = match session {
+        Some(session) => session,
+        None => return,
+    };
+
+    for (from, message) in session.receive() {
+        match message {
+            NetMessage::Join => {
+                let clients = match &mut session.role {
+                    Role::Host { clients } => clients,
+                    Role::Client { .. } => continue,
+                };
+                if let Some(index) = clients
+                    .iter()
+                    .position(|client| client == &from)
+                {
+                    // the client missed our Start
+                    if let Some(start) =
+                        session.starts.get(index)
+                    {
+                        session.send(from, start);
+                    }
+                    continue;
+                }
+                if clients.len() + 1 >= MAX_PLAYERS
+                    || *run_state.current()
+                        == RunState::Playing
+                {
+                    continue;
+                }
+                clients.push(from);
+                let players = clients.len() + 1;
+                if let LobbyStatus::Hosting {
+                    players: shown,
+                    ..
+                } = &mut *status
+                {
+                    *shown = players;
+                }
+                session.send_to_peers(
+                    &NetMessage::Lobby { players },
+                    None,
+                );
+            }
+            NetMessage::Lobby { players } => {
+                *status = LobbyStatus::Joined { players };
+            }
+            NetMessage::Start {
+                game,
+                player,
+                players,
+                seed,
+                rules,
+                input_delay,
+            } => {
+                if game <= session.game {
+                    continue;
+                }
+                session.game = game;
+                *status = LobbyStatus::Joined { players };
+                let new_lockstep = Lockstep::new(
+                    game,
+                    player,
+                    players,
+                    seed,
+                    rules,
+                    input_delay,
+                );
+                commands.insert_resource(new_lockstep);
+                lockstep = None;
+                *mode = GameMode::Lan;
+                local.0 = player;
+                let _ = run_state.set(RunState::Playing);
+            }
+            NetMessage::Inputs {
+                game,
+                player,
+                first_tick,
+                directions,
+                checksums,
+            } => {
+                if game != session.game {
+                    continue;
+                }
+                if let Some(lockstep) = lockstep.as_mut() {
+                    lockstep.receive(
+                        player,
+                        first_tick,
+                        directions.clone(),
+                        checksums.clone(),
+                    );
+                }
+                if matches!(session.role, Role::Host { .. })
+                {
+                    session.send_to_peers(
+                        &NetMessage::Inputs {
+                            game,
+                            player,
+                            first_tick,
+                            directions,
+                            checksums,
+                        },
+                        Some(from),
+                    );
+                }
+            }
+        }
+    }
+
+    // keep knocking until the host lets us in
+    if let Role::Client { host } = session.role {
+        if session.game == 0
+            && session.last_sent.map_or(true, |sent| {
+                sent.elapsed() > JOIN_RESEND
+            })
+        {
+            session.send(host, &NetMessage::Join);
+            session.last_sent = Some(Instant::now());
+        }
+    }
+
+    let lockstep = match lockstep {
+        Some(lockstep) => lockstep,
+        None => return,
+    };
+    if *run_state.current() != RunState::Playing {
+        return;
+    }
+    session.send_to_peers(&lockstep.outgoing(), None);
+
+    if let Some(tick) = lockstep.desync {
+        *status = LobbyStatus::Error(format!(
+            "desync at tick {}",
+            tick
+        ));
+        let _ = run_state.set(RunState::Menu);
+    } else if lockstep.timed_out() {
+        *status = LobbyStatus::Error(
+            "a player stopped responding".to_string(),
+        );
+        let _ = run_state.set(RunState::Menu);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peers() -> (Lockstep, Lockstep) {
+        let peer = |player| {
+            Lockstep::new(
+                1,
+                player,
+                2,
+                9,
+                GameRules::default(),
+                2,
+            )
+        };
+        (peer(0), peer(1))
+    }
+
+    fn deliver(from: &Lockstep, to: &mut Lockstep) {
+        match from.outgoing() {
+            NetMessage::Inputs {
+                player,
+                first_tick,
+                directions,
+                checksums,
+                ..
+            } => to.receive(
+                player, first_tick, directions, checksums,
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn waits_for_the_peers_input() {
+        let (mut a, mut b) = peers();
+        a.schedule_local(1, Direction::Up);
+        assert_eq!(a.inputs_for(3), None);
+
+        b.schedule_local(1, Direction::Down);
+        deliver(&b, &mut a);
+        assert_eq!(
+            a.inputs_for(3),
+            Some(vec![
+                Some(Direction::Up),
+                Some(Direction::Down)
+            ])
+        );
+    }
+
+    #[test]
+    fn matching_checksums_are_in_sync() {
+        let (mut a, mut b) = peers();
+        for tick in 1..=5 {
+            a.record_checksum(tick, tick * 10);
+            b.record_checksum(tick, tick * 10);
+        }
+        deliver(&a, &mut b);
+        deliver(&b, &mut a);
+        assert_eq!(a.desync, None);
+        assert_eq!(b.desync, None);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_a_desync() {
+        let (mut a, mut b) = peers();
+        for tick in 1..=5 {
+            a.record_checksum(tick, tick * 10);
+            // b goes its own way from tick 3 on
+            let checksum =
+                if tick < 3 { tick * 10 } else { tick };
+            b.record_checksum(tick, checksum);
+        }
+        deliver(&a, &mut b);
+        deliver(&b, &mut a);
+        // only the first tick that disagreed is kept
+        assert_eq!(a.desync, Some(3));
+        assert_eq!(b.desync, Some(3));
+    }
+
+    #[test]
+    fn remote_checksum_arriving_first_is_compared() {
+        let (mut a, mut b) = peers();
+        b.record_checksum(4, 1);
+        deliver(&b, &mut a);
+        assert_eq!(a.desync, None);
+        a.record_checksum(4, 2);
+        assert_eq!(a.desync, Some(4));
+    }
+}
@@ -0,0 +1,222 @@
+use rand::Rng;
+
+use crate::board::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Empty,
+    Wall,
+    Food,
+    /// A segment of the snake with this index in
+    /// `GameState::snakes`
+    Snake(usize),
+}
+
+/// Marks a cell that isn't in `Occupancy::free`
+const NOT_FREE: usize = usize::MAX;
+
+/// What is in every cell of the board, so collisions and
+/// food placement don't have to search the snakes. Empty
+/// cells are also kept in a list, so a random one can be
+/// picked without collecting them first.
+#[derive(Debug, Clone)]
+pub struct Occupancy {
+    size: u16,
+    cells: Vec<Cell>,
+    /// every empty cell, in no particular order
+    free: Vec<Position>,
+    /// where each empty cell is in `free`
+    slots: Vec<usize>,
+}
+
+impl Occupancy {
+    pub fn new(size: u16) -> Self {
+        let cells = usize::from(size) * usize::from(size);
+        let mut occupancy = Occupancy {
+            size,
+            cells: vec![Cell::Empty; cells],
+            free: Vec::with_capacity(cells),
+            slots: vec![NOT_FREE; cells],
+        };
+        for x in 0..size {
+            for y in 0..size {
+                let position = Position { x, y };
+                let index = occupancy.index(position);
+                occupancy.slots[index] =
+                    occupancy.free.len();
+                occupancy.free.push(position);
+            }
+        }
+        occupancy
+    }
+
+    fn index(&self, position: Position) -> usize {
+        usize::from(position.y) * usize::from(self.size)
+            + usize::from(position.x)
+    }
+
+    /// What is at `position`. Anything off the board
+    /// counts as wall.
+    pub fn get(&self, position: Position) -> Cell {
+        if position.x >= self.size
+            || position.y >= self.size
+        {
+            return Cell::Wall;
+        }
+        self.cells[self.index(position)]
+    }
+
+    pub fn is_free(&self, position: Position) -> bool {
+        self.get(position) == Cell::Empty
+    }
+
+    pub fn set(&mut self, position: Position, cell: Cell) {
+        if position.x >= self.size
+            || position.y >= self.size
+        {
+            return;
+        }
+        let index = self.index(position);
+        let was_free = self.cells[index] == Cell::Empty;
+        self.cells[index] = cell;
+        match (was_free, cell == Cell::Empty) {
+            (false, true) => {
+                self.slots[index] = self.free.len();
+                self.free.push(position);
+            }
+            (true, false) => {
+                let slot = self.slots[index];
+                self.slots[index] = NOT_FREE;
+                self.free.swap_remove(slot);
+                // the last free cell took its place
+                if let Some(moved) = self.free.get(slot) {
+                    let moved = self.index(*moved);
+                    self.slots[moved] = slot;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Empties `position`, but only if it holds `cell`.
+    pub fn clear(
+        &mut self,
+        position: Position,
+        cell: Cell,
+    ) {
+        if self.get(position) == cell {
+            self.set(position, Cell::Empty);
+        }
+    }
+
+    pub fn free_cells(&self) -> &[Position] {
+        &self.free
+    }
+
+    pub fn sample_free<R: Rng>(
+        &self,
+        rng: &mut R,
+    ) -> Option<Position> {
+        if self.free.is_empty() {
+            return None;
+        }
+        Some(self.free[rng.gen_range(0..self.free.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    /// `free` holds exactly the empty cells, and `slots`
+    /// points at each of them.
+    fn assert_consistent(occupancy: &Occupancy) {
+        let mut empty = 0;
+        for x in 0..occupancy.size {
+            for y in 0..occupancy.size {
+                let position = Position { x, y };
+                let index = occupancy.index(position);
+                let slot = occupancy.slots[index];
+                if occupancy.is_free(position) {
+                    empty += 1;
+                    assert_eq!(
+                        occupancy.free[slot],
+                        position
+                    );
+                } else {
+                    assert_eq!(slot, NOT_FREE);
+                }
+            }
+        }
+        assert_eq!(occupancy.free.len(), empty);
+    }
+
+    #[test]
+    fn starts_empty() {
+        let occupancy = Occupancy::new(4);
+        assert_eq!(occupancy.free_cells().len(), 16);
+        assert_consistent(&occupancy);
+    }
+
+    #[test]
+    fn free_list_follows_set_and_clear() {
+        let mut occupancy = Occupancy::new(5);
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for step in 0..500 {
+            let position = Position {
+                x: rng.gen_range(0..5),
+                y: rng.gen_range(0..5),
+            };
+            match step % 4 {
+                0 => occupancy.set(position, Cell::Wall),
+                1 => {
+                    occupancy.set(position, Cell::Snake(1))
+                }
+                2 => occupancy.clear(position, Cell::Wall),
+                _ => occupancy.set(position, Cell::Empty),
+            }
+            assert_consistent(&occupancy);
+        }
+    }
+
+    #[test]
+    fn clear_only_removes_the_given_cell() {
+        let mut occupancy = Occupancy::new(3);
+        let position = Position { x: 1, y: 1 };
+        occupancy.set(position, Cell::Snake(0));
+        occupancy.clear(position, Cell::Snake(1));
+        assert_eq!(occupancy.get(position), Cell::Snake(0));
+        occupancy.clear(position, Cell::Snake(0));
+        assert_eq!(occupancy.get(position), Cell::Empty);
+        assert_consistent(&occupancy);
+    }
+
+    #[test]
+    fn off_the_board_is_wall() {
+        let mut occupancy = Occupancy::new(3);
+        let outside = Position { x: 3, y: 0 };
+        assert_eq!(occupancy.get(outside), Cell::Wall);
+        occupancy.set(outside, Cell::Food);
+        assert_eq!(occupancy.free_cells().len(), 9);
+    }
+
+    #[test]
+    fn samples_only_free_cells() {
+        let mut occupancy = Occupancy::new(2);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        for (x, y) in [(0, 0), (1, 0), (0, 1)] {
+            occupancy.set(Position { x, y }, Cell::Wall);
+        }
+        for _ in 0..10 {
+            assert_eq!(
+                occupancy.sample_free(&mut rng),
+                Some(Position { x: 1, y: 1 })
+            );
+        }
+        occupancy.set(Position { x: 1, y: 1 }, Cell::Food);
+        assert_eq!(occupancy.sample_free(&mut rng), None);
+    }
+}
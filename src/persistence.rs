@@ -0,0 +1,147 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use directories::ProjectDirs;
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::warn;
+
+/// How long to wait for another process to finish with
+/// a file before taking its lock over, in case it died
+/// holding it.
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+
+/// Where local saves (daily history, settings, ...)
+/// live. Falls back to the working directory if the
+/// platform has no data directory.
+pub fn data_dir() -> PathBuf {
+    ProjectDirs::from("", "rust-adventure", "bevy-snake")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Load a ron file from the data directory. Missing or
+/// unreadable files give the default value so a
+/// corrupted save never stops the game from starting.
+pub fn load<T: DeserializeOwned + Default>(
+    name: &str,
+) -> T {
+    let path = data_dir().join(name);
+    match fs::read_to_string(&path) {
+        Ok(contents) => ron::from_str(&contents)
+            .unwrap_or_else(|error| {
+                warn!(
+                    ?path,
+                    %error,
+                    "failed to parse save file"
+                );
+                T::default()
+            }),
+        Err(_) => T::default(),
+    }
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let dir = data_dir();
+    if let Err(error) = fs::create_dir_all(&dir) {
+        warn!(
+            ?dir,
+            %error,
+            "failed to create data dir"
+        );
+        return;
+    }
+    let path = dir.join(name);
+    let contents = match ron::ser::to_string_pretty(
+        value,
+        ron::ser::PrettyConfig::default(),
+    ) {
+        Ok(contents) => contents,
+        Err(error) => {
+            warn!(
+                ?path,
+                %error,
+                "failed to serialize save file"
+            );
+            return;
+        }
+    };
+    // written next to it and moved over, so readers
+    // never see half a file
+    let partial = path.with_extension("partial");
+    if let Err(error) = fs::write(&partial, contents)
+        .and_then(|_| fs::rename(&partial, &path))
+    {
+        warn!(
+            ?path,
+            %error,
+            "failed to write save file"
+        );
+    }
+}
+
+/// Changes a saved file and returns what was saved.
+/// Other processes updating it at the same time wait
+/// for each other, and each one re-reads the file first
+/// so none of their changes are lost.
+pub fn update<T, F>(name: &str, change: F) -> T
+where
+    T: Serialize + DeserializeOwned + Default,
+    F: FnOnce(&mut T),
+{
+    let _lock = Lock::acquire(
+        &data_dir().join(format!("{}.lock", name)),
+    );
+    let mut value = load(name);
+    change(&mut value);
+    save(name, &value);
+    value
+}
+
+struct Lock(Option<PathBuf>);
+
+impl Lock {
+    fn acquire(path: &Path) -> Lock {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+        let started = Instant::now();
+        loop {
+            let error = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+            {
+                Ok(_) => {
+                    return Lock(Some(path.to_path_buf()))
+                }
+                Err(error) => error,
+            };
+            if error.kind() != ErrorKind::AlreadyExists {
+                warn!(
+                    ?path,
+                    %error,
+                    "failed to lock save file"
+                );
+                return Lock(None);
+            }
+            if started.elapsed() >= LOCK_WAIT {
+                warn!(?path, "taking over a stale lock");
+                return Lock(Some(path.to_path_buf()));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0 {
+            fs::remove_file(path).ok();
+        }
+    }
+}
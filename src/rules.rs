@@ -0,0 +1,806 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    hash::{Hash, Hasher},
+};
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    board::Position,
+    occupancy::{Cell, Occupancy},
+    settings::{GameRules, Modifier},
+    snake::SnakeBody,
+};
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// Every direction, in the order agents and
+    /// observations index them.
+    pub const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    pub fn turn_left(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(&self) -> Direction {
+        self.turn_left().opposite()
+    }
+
+    pub fn step(
+        &self,
+        position: Position,
+        board_size: u16,
+    ) -> Option<Position> {
+        match self {
+            Direction::Up
+                if position.y + 1 < board_size =>
+            {
+                Some(Position {
+                    y: position.y + 1,
+                    ..position
+                })
+            }
+            Direction::Down if position.y > 0 => {
+                Some(Position {
+                    y: position.y - 1,
+                    ..position
+                })
+            }
+            Direction::Left if position.x > 0 => {
+                Some(Position {
+                    x: position.x - 1,
+                    ..position
+                })
+            }
+            Direction::Right
+                if position.x + 1 < board_size =>
+            {
+                Some(Position {
+                    x: position.x + 1,
+                    ..position
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum GameOverReason {
+    HitWall,
+    HitSnake,
+    Win,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Snake {
+    pub body: SnakeBody,
+    pub direction: Direction,
+    pub score: u32,
+    pub game_over: Option<GameOverReason>,
+}
+
+impl Snake {
+    pub fn head(&self) -> Position {
+        self.body.segments[0]
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.game_over.is_none()
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum TickEvent {
+    /// `tail` is the cell the snake left behind, `None`
+    /// if it grew this tick.
+    Moved {
+        snake: usize,
+        head: Position,
+        tail: Option<Position>,
+    },
+    Ate {
+        snake: usize,
+        position: Position,
+    },
+    FoodSpawned {
+        position: Position,
+    },
+    GameOver {
+        snake: usize,
+        reason: GameOverReason,
+    },
+}
+
+/// The complete state of a game, independent of Bevy.
+/// Given the same rules, seed and inputs, `step`
+/// always produces the same state, which is what
+/// lockstep multiplayer relies on.
+#[derive(Debug, Clone)]
+pub struct GameState {
+    pub rules: GameRules,
+    pub snakes: Vec<Snake>,
+    pub food: Vec<Position>,
+    pub walls: Vec<Position>,
+    pub tick: u64,
+    /// kept in step with `snakes`, `food` and `walls`
+    grid: Occupancy,
+    rng: ChaCha8Rng,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::new(GameRules::default(), 1, 0)
+    }
+}
+
+impl GameState {
+    pub fn new(
+        rules: GameRules,
+        players: usize,
+        seed: u64,
+    ) -> Self {
+        let snakes = (0..players)
+            .map(|player| {
+                let y = start_row(
+                    player,
+                    players,
+                    rules.board_size,
+                );
+                let length =
+                    if rules.has(Modifier::LongStart) {
+                        4
+                    } else {
+                        2
+                    };
+                Snake {
+                    body: SnakeBody {
+                        segments: (0..length)
+                            .map(|i| Position {
+                                x: 4 - i,
+                                y,
+                            })
+                            .collect::<VecDeque<Position>>(
+                            ),
+                    },
+                    direction: Direction::Right,
+                    score: 0,
+                    game_over: None,
+                }
+            })
+            .collect::<Vec<Snake>>();
+
+        let mut grid = Occupancy::new(rules.board_size);
+        for (index, snake) in snakes.iter().enumerate() {
+            for segment in snake.body.segments.iter() {
+                grid.set(*segment, Cell::Snake(index));
+            }
+        }
+        let mut state = GameState {
+            rules,
+            snakes,
+            food: vec![],
+            walls: vec![],
+            tick: 0,
+            grid,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+
+        if state.rules.has(Modifier::Obstacles) {
+            // keep the rows the snakes start on clear so
+            // the first few ticks are always survivable
+            let start_rows = state
+                .snakes
+                .iter()
+                .map(|snake| snake.head().y)
+                .collect::<Vec<u16>>();
+            let free = state
+                .free_cells()
+                .iter()
+                .copied()
+                .filter(|position| {
+                    !start_rows.contains(&position.y)
+                })
+                .collect::<Vec<Position>>();
+            state.walls = free
+                .choose_multiple(
+                    &mut state.rng,
+                    usize::from(state.rules.board_size / 2),
+                )
+                .copied()
+                .collect();
+            for wall in state.walls.iter() {
+                state.grid.set(*wall, Cell::Wall);
+            }
+        }
+
+        let apples =
+            if state.rules.has(Modifier::DoubleApples) {
+                2
+            } else {
+                1
+            };
+        for _ in 0..apples {
+            state.spawn_food();
+        }
+
+        state
+    }
+
+    pub fn alive(&self) -> impl Iterator<Item = &Snake> {
+        self.snakes.iter().filter(|snake| snake.is_alive())
+    }
+
+    /// Single player games end when the snake does,
+    /// multiplayer games when at most one snake is left.
+    pub fn is_over(&self) -> bool {
+        match self.snakes.len() {
+            1 => !self.snakes[0].is_alive(),
+            _ => self.alive().count() <= 1,
+        }
+    }
+
+    /// Advances the game by one tick. `inputs` holds one
+    /// entry per snake, `None` keeps the current
+    /// direction.
+    pub fn step(
+        &mut self,
+        inputs: &[Option<Direction>],
+    ) -> Vec<TickEvent> {
+        let mut events = vec![];
+        if self.is_over() {
+            return events;
+        }
+        self.tick += 1;
+
+        for (snake, input) in
+            self.snakes.iter_mut().zip(inputs)
+        {
+            match input {
+                // a snake can't turn back into its neck
+                Some(direction)
+                    if *direction
+                        != snake.direction.opposite() =>
+                {
+                    snake.direction = *direction;
+                }
+                _ => {}
+            }
+        }
+
+        let mut heads = vec![None; self.snakes.len()];
+        let mut deaths = vec![];
+        for (index, snake) in self.snakes.iter().enumerate()
+        {
+            if !snake.is_alive() {
+                continue;
+            }
+            match snake
+                .direction
+                .step(snake.head(), self.rules.board_size)
+            {
+                None => deaths
+                    .push((index, GameOverReason::HitWall)),
+                Some(head) => match self.grid.get(head) {
+                    Cell::Wall => deaths.push((
+                        index,
+                        GameOverReason::HitWall,
+                    )),
+                    Cell::Snake(_) => deaths.push((
+                        index,
+                        GameOverReason::HitSnake,
+                    )),
+                    Cell::Empty | Cell::Food => {
+                        heads[index] = Some(head)
+                    }
+                },
+            }
+        }
+        // head to head collisions take out both snakes
+        for (a, b) in (0..heads.len()).tuple_combinations()
+        {
+            if heads[a].is_some() && heads[a] == heads[b] {
+                deaths.push((a, GameOverReason::HitSnake));
+                deaths.push((b, GameOverReason::HitSnake));
+            }
+        }
+        for (index, reason) in deaths {
+            heads[index] = None;
+            if self.snakes[index].is_alive() {
+                self.snakes[index].game_over = Some(reason);
+                // dead snakes are no longer in the way
+                for segment in
+                    self.snakes[index].body.segments.iter()
+                {
+                    self.grid.clear(
+                        *segment,
+                        Cell::Snake(index),
+                    );
+                }
+                events.push(TickEvent::GameOver {
+                    snake: index,
+                    reason,
+                });
+            }
+        }
+
+        let mut eaten = vec![];
+        for (index, head) in heads.into_iter().enumerate() {
+            let head = match head {
+                Some(head) => head,
+                None => continue,
+            };
+            let ate = self.grid.get(head) == Cell::Food;
+            let snake = &mut self.snakes[index];
+            snake.body.segments.push_front(head);
+            self.grid.set(head, Cell::Snake(index));
+
+            let tail = if ate {
+                self.food.retain(|food| *food != head);
+                snake.score += 1;
+                eaten.push(index);
+                events.push(TickEvent::Ate {
+                    snake: index,
+                    position: head,
+                });
+                None
+            } else {
+                let tail = snake.body.segments.pop_back();
+                if let Some(tail) = tail {
+                    self.grid
+                        .clear(tail, Cell::Snake(index));
+                }
+                tail
+            };
+            events.push(TickEvent::Moved {
+                snake: index,
+                head,
+                tail,
+            });
+        }
+
+        for index in eaten {
+            match self.spawn_food() {
+                Some(position) => {
+                    events.push(TickEvent::FoodSpawned {
+                        position,
+                    })
+                }
+                // nowhere left to put food, the board is
+                // full
+                None if self.food.is_empty() => {
+                    self.snakes[index].game_over =
+                        Some(GameOverReason::Win);
+                    events.push(TickEvent::GameOver {
+                        snake: index,
+                        reason: GameOverReason::Win,
+                    });
+                }
+                None => {}
+            }
+        }
+
+        // the last snake standing wins a multiplayer game
+        if self.snakes.len() > 1
+            && self.alive().count() == 1
+        {
+            let index = self
+                .snakes
+                .iter()
+                .position(|snake| snake.is_alive())
+                .unwrap();
+            self.snakes[index].game_over =
+                Some(GameOverReason::Win);
+            events.push(TickEvent::GameOver {
+                snake: index,
+                reason: GameOverReason::Win,
+            });
+        }
+
+        events
+    }
+
+    /// Whether a head moving onto `position` would die,
+    /// not counting other heads moving there too.
+    pub fn is_blocked(&self, position: Position) -> bool {
+        matches!(
+            self.grid.get(position),
+            Cell::Wall | Cell::Snake(_)
+        )
+    }
+
+    /// How many cells a snake at `from` could still
+    /// reach, counting `from` and stopping at `limit`.
+    /// Tells when a snake is boxing itself in.
+    pub fn open_area(
+        &self,
+        from: Position,
+        limit: usize,
+    ) -> usize {
+        let mut seen = HashSet::from([from]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(position) = queue.pop_front() {
+            for direction in Direction::ALL {
+                if seen.len() >= limit {
+                    return limit;
+                }
+                let next = match direction
+                    .step(position, self.rules.board_size)
+                {
+                    Some(next) => next,
+                    None => continue,
+                };
+                if !self.is_blocked(next)
+                    && seen.insert(next)
+                {
+                    queue.push_back(next);
+                }
+            }
+        }
+        seen.len()
+    }
+
+    /// What is at `position` right now. Dead snakes
+    /// have already been cleared away.
+    pub fn occupant(&self, position: Position) -> Cell {
+        self.grid.get(position)
+    }
+
+    pub fn free_cells(&self) -> &[Position] {
+        self.grid.free_cells()
+    }
+
+    /// Swaps the food that was spawned for `at`, so
+    /// nothing eats it by chance.
+    #[cfg(test)]
+    pub(crate) fn put_food(&mut self, at: &[Position]) {
+        for food in std::mem::take(&mut self.food) {
+            self.grid.clear(food, Cell::Food);
+        }
+        for position in at {
+            self.grid.set(*position, Cell::Food);
+            self.food.push(*position);
+        }
+    }
+
+    /// Walls off every empty cell, leaving the snakes
+    /// nowhere to go but food.
+    #[cfg(test)]
+    pub(crate) fn fill_free_cells(&mut self) {
+        for position in self.grid.free_cells().to_vec() {
+            self.grid.set(position, Cell::Wall);
+        }
+    }
+
+    fn spawn_food(&mut self) -> Option<Position> {
+        let position =
+            self.grid.sample_free(&mut self.rng)?;
+        self.grid.set(position, Cell::Food);
+        self.food.push(position);
+        Some(position)
+    }
+
+    /// A checksum of everything that affects future
+    /// ticks. Peers compare these to detect desyncs.
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        self.tick.hash(&mut hasher);
+        self.snakes.hash(&mut hasher);
+        self.food.hash(&mut hasher);
+        self.walls.hash(&mut hasher);
+        self.rng.get_word_pos().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Rows the snakes start on, spread evenly over the
+/// board and at least one row apart. A single snake
+/// always starts on row 4.
+fn start_row(
+    player: usize,
+    players: usize,
+    size: u16,
+) -> u16 {
+    if players <= 1 {
+        return 4;
+    }
+    let size = usize::from(size);
+    let spread = size.saturating_sub(8).max(players - 1);
+    let first = 4.min(size.saturating_sub(spread + 1));
+    (first + player * spread / (players - 1)) as u16
+}
+
+/// std's `DefaultHasher` may change between Rust
+/// releases, checksums have to match across builds.
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte))
+                .wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: u16, y: u16) -> Position {
+        Position { x, y }
+    }
+
+    fn state(
+        players: usize,
+        rules: GameRules,
+    ) -> GameState {
+        GameState::new(rules, players, 7)
+    }
+
+    #[test]
+    fn moves_and_leaves_the_tail_behind() {
+        let mut state = state(1, GameRules::default());
+        state.put_food(&[pos(0, 0)]);
+
+        let events = state.step(&[None]);
+        assert_eq!(
+            events,
+            [TickEvent::Moved {
+                snake: 0,
+                head: pos(5, 4),
+                tail: Some(pos(3, 4)),
+            }]
+        );
+        assert_eq!(state.tick, 1);
+        assert_eq!(
+            state.occupant(pos(5, 4)),
+            Cell::Snake(0)
+        );
+        assert_eq!(state.occupant(pos(3, 4)), Cell::Empty);
+    }
+
+    #[test]
+    fn turning_back_is_ignored() {
+        let mut state = state(1, GameRules::default());
+        state.put_food(&[pos(0, 0)]);
+
+        state.step(&[Some(Direction::Left)]);
+        assert_eq!(state.snakes[0].head(), pos(5, 4));
+        assert_eq!(
+            state.snakes[0].direction,
+            Direction::Right
+        );
+    }
+
+    #[test]
+    fn eating_grows_and_spawns_food() {
+        let mut state = state(1, GameRules::default());
+        state.put_food(&[pos(5, 4)]);
+
+        let events = state.step(&[None]);
+        let snake = &state.snakes[0];
+        assert_eq!(snake.body.segments.len(), 3);
+        assert_eq!(snake.score, 1);
+        assert!(events.contains(&TickEvent::Ate {
+            snake: 0,
+            position: pos(5, 4),
+        }));
+        assert!(events.contains(&TickEvent::Moved {
+            snake: 0,
+            head: pos(5, 4),
+            tail: None,
+        }));
+        assert_eq!(state.food.len(), 1);
+        assert!(events.contains(&TickEvent::FoodSpawned {
+            position: state.food[0],
+        }));
+        assert_eq!(
+            state.occupant(state.food[0]),
+            Cell::Food
+        );
+    }
+
+    #[test]
+    fn leaving_the_board_hits_the_wall() {
+        let mut state = state(1, GameRules::default());
+        state.put_food(&[pos(19, 19)]);
+
+        for _ in 0..4 {
+            state.step(&[Some(Direction::Down)]);
+            assert!(state.snakes[0].is_alive());
+        }
+        let events = state.step(&[None]);
+        assert_eq!(
+            events,
+            [TickEvent::GameOver {
+                snake: 0,
+                reason: GameOverReason::HitWall,
+            }]
+        );
+        assert!(state.is_over());
+        // nothing moves once the game is over
+        assert!(state.step(&[None]).is_empty());
+    }
+
+    #[test]
+    fn running_into_itself_hits_the_snake() {
+        let rules = GameRules {
+            modifiers: vec![Modifier::LongStart],
+            ..GameRules::default()
+        };
+        let mut state = state(1, rules);
+        state.put_food(&[pos(19, 19)]);
+
+        state.step(&[Some(Direction::Up)]);
+        state.step(&[Some(Direction::Left)]);
+        state.step(&[Some(Direction::Down)]);
+        assert_eq!(
+            state.snakes[0].game_over,
+            Some(GameOverReason::HitSnake)
+        );
+        // the dead snake is cleared off the board
+        assert_eq!(state.occupant(pos(3, 5)), Cell::Empty);
+    }
+
+    #[test]
+    fn heads_meeting_take_out_both_snakes() {
+        let mut state = state(2, GameRules::default());
+        state.put_food(&[pos(19, 0)]);
+        assert_eq!(state.snakes[1].head(), pos(4, 16));
+
+        let inputs =
+            [Some(Direction::Up), Some(Direction::Down)];
+        for _ in 0..5 {
+            state.step(&inputs);
+        }
+        assert!(!state.is_over());
+        state.step(&inputs);
+        for snake in state.snakes.iter() {
+            assert_eq!(
+                snake.game_over,
+                Some(GameOverReason::HitSnake)
+            );
+        }
+        assert!(state.is_over());
+    }
+
+    #[test]
+    fn last_snake_standing_wins() {
+        let mut state = state(2, GameRules::default());
+        state.put_food(&[pos(0, 19)]);
+
+        for _ in 0..5 {
+            state.step(&[Some(Direction::Down), None]);
+        }
+        assert_eq!(
+            state.snakes[0].game_over,
+            Some(GameOverReason::HitWall)
+        );
+        assert_eq!(
+            state.snakes[1].game_over,
+            Some(GameOverReason::Win)
+        );
+        assert!(state.is_over());
+    }
+
+    #[test]
+    fn filling_the_board_wins() {
+        let size = *GameRules::BOARD_SIZES.start();
+        let rules = GameRules {
+            board_size: size,
+            ..GameRules::default()
+        };
+        let mut state = state(1, rules);
+        state.put_food(&[pos(4, 3)]);
+        state.fill_free_cells();
+
+        let events = state.step(&[Some(Direction::Down)]);
+        assert!(events.contains(&TickEvent::GameOver {
+            snake: 0,
+            reason: GameOverReason::Win,
+        }));
+        assert!(state.food.is_empty());
+        assert!(state.is_over());
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_game() {
+        let rules = GameRules {
+            modifiers: vec![
+                Modifier::Obstacles,
+                Modifier::DoubleApples,
+            ],
+            ..GameRules::default()
+        };
+        let mut a = GameState::new(rules.clone(), 2, 42);
+        let mut b = GameState::new(rules, 2, 42);
+        assert_eq!(a.checksum(), b.checksum());
+        for turn in 0..20 {
+            let input =
+                Direction::ALL.get(turn % 3).copied();
+            assert_eq!(
+                a.step(&[input, None]),
+                b.step(&[input, None])
+            );
+            assert_eq!(a.checksum(), b.checksum());
+        }
+    }
+
+    #[test]
+    fn snakes_start_on_rows_of_their_own() {
+        for size in GameRules::BOARD_SIZES {
+            for players in 1..=GameRules::MAX_PLAYERS {
+                let rows = (0..players)
+                    .map(|player| {
+                        start_row(player, players, size)
+                    })
+                    .collect::<Vec<u16>>();
+                assert!(
+                    rows.windows(2).all(|w| w[0] < w[1]),
+                    "{size} {players} {rows:?}"
+                );
+                assert!(rows.iter().all(|row| *row < size));
+            }
+        }
+    }
+}
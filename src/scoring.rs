@@ -1,19 +1,31 @@
 use std::time::{Duration, Instant};
 
 use bevy::prelude::{App, Plugin, Res, ResMut, SystemSet};
+use serde::{Deserialize, Serialize};
 
-use crate::common::{Game, RunState};
+use crate::{
+    common::{Game, Paused, RunState},
+    persistence,
+    settings::{ControlScheme, GameSettings},
+};
+
+const SPEEDRUNS_FILE: &str = "speedruns.ron";
 
 pub struct SpeedrunPlugin;
 
 impl Plugin for SpeedrunPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Timer>()
-            .init_resource::<Speedruns>()
+            .init_resource::<Restarting>()
+            .insert_resource(Speedruns::load())
             .add_system_set(
                 SystemSet::on_enter(RunState::Playing)
                     .with_system(start_timer),
             )
+            .add_system_set(
+                SystemSet::on_update(RunState::Playing)
+                    .with_system(pause_timer),
+            )
             .add_system_set(
                 SystemSet::on_exit(RunState::Playing)
                     .with_system(close_timer),
@@ -21,17 +33,52 @@ impl Plugin for SpeedrunPlugin {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
 pub struct Run {
     pub time: Duration,
     pub score: u32,
+    /// Runs with different schemes are separate
+    /// categories
+    #[serde(default)]
+    pub scheme: ControlScheme,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
 pub struct Speedruns {
     runs: Vec<Run>,
 }
 impl Speedruns {
+    pub fn load() -> Self {
+        persistence::load(SPEEDRUNS_FILE)
+    }
+    pub fn save(&self) {
+        persistence::save(SPEEDRUNS_FILE, self);
+    }
+    /// Records a finished run on top of whatever is
+    /// saved by now, so frontends running at the same
+    /// time keep each other's runs.
+    pub fn record(&mut self, run: Run) {
+        *self = persistence::update(
+            SPEEDRUNS_FILE,
+            |runs: &mut Speedruns| runs.runs.push(run),
+        );
+    }
+    pub fn best_score(&self) -> u32 {
+        self.runs
+            .iter()
+            .map(|run| run.score)
+            .max()
+            .unwrap_or(0)
+    }
     pub fn sorted_by_score(&self) -> Vec<Run> {
         let mut runs = self.runs.clone();
         runs.sort_by_key(|run| run.score);
@@ -40,17 +87,29 @@ impl Speedruns {
     pub fn sorted_by_run(&self) -> Vec<Run> {
         self.runs.clone()
     }
-}
-
-impl Default for Speedruns {
-    fn default() -> Self {
-        Speedruns { runs: vec![] }
+    pub fn for_scheme(
+        &self,
+        scheme: ControlScheme,
+    ) -> Vec<Run> {
+        self.runs
+            .iter()
+            .filter(|run| run.scheme == scheme)
+            .cloned()
+            .collect()
     }
 }
 
+/// Set when the game is restarted, so the run that was
+/// given up isn't recorded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Restarting(pub bool);
+
 pub struct Timer {
     pub start: Option<Instant>,
     pub runtime: Option<Duration>,
+    // time spent paused, which doesn't count
+    paused: Duration,
+    paused_since: Option<Instant>,
 }
 
 impl Default for Timer {
@@ -58,23 +117,66 @@ impl Default for Timer {
         Timer {
             start: Some(Instant::now()),
             runtime: None,
+            paused: Duration::ZERO,
+            paused_since: None,
         }
     }
 }
 
+impl Timer {
+    /// Time played since the start, without pauses
+    pub fn elapsed(&self) -> Option<Duration> {
+        let start = self.start?;
+        let pausing = self
+            .paused_since
+            .map(|since| since.elapsed())
+            .unwrap_or_default();
+        Some(
+            start
+                .elapsed()
+                .saturating_sub(self.paused + pausing),
+        )
+    }
+}
+
 fn start_timer(mut timer: ResMut<Timer>) {
     timer.start = Some(Instant::now());
+    timer.paused = Duration::ZERO;
+    timer.paused_since = None;
+}
+
+fn pause_timer(
+    mut timer: ResMut<Timer>,
+    paused: Res<Paused>,
+) {
+    match (paused.0, timer.paused_since) {
+        (true, None) => {
+            timer.paused_since = Some(Instant::now());
+        }
+        (false, Some(since)) => {
+            timer.paused += since.elapsed();
+            timer.paused_since = None;
+        }
+        _ => {}
+    }
 }
 
 fn close_timer(
     mut timer: ResMut<Timer>,
+    mut restarting: ResMut<Restarting>,
     mut runs: ResMut<Speedruns>,
     game: Res<Game>,
+    settings: Res<GameSettings>,
 ) {
-    timer.runtime = Some(timer.start.unwrap().elapsed());
-    runs.runs.push(Run {
+    timer.runtime = timer.elapsed();
+    if restarting.0 {
+        restarting.0 = false;
+        return;
+    }
+    runs.record(Run {
         time: timer.runtime.unwrap(),
         score: game.score,
+        scheme: settings.control_scheme,
     });
     dbg!(&runs.runs);
 }
@@ -1,13 +1,87 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use std::{ops::RangeInclusive, time::Duration};
+
+use bevy::prelude::Res;
+use serde::{Deserialize, Serialize};
+
+use crate::{locale, persistence, rules::Direction};
+
+const SETTINGS_FILE: &str = "settings.ron";
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+)]
 pub enum GameSpeed {
     SLOW,
     REGULAR,
     FAST,
 }
-#[derive(Debug, Clone, PartialEq, Eq)]
+
+impl GameSpeed {
+    pub fn tick_duration(&self) -> Duration {
+        match self {
+            GameSpeed::SLOW => Duration::from_millis(150),
+            GameSpeed::REGULAR => {
+                Duration::from_millis(100)
+            }
+            GameSpeed::FAST => Duration::from_millis(70),
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            GameSpeed::SLOW => "speed.slow",
+            GameSpeed::REGULAR => "speed.regular",
+            GameSpeed::FAST => "speed.fast",
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+)]
 pub struct GameSettings {
     pub speed: GameSpeed,
     pub speedrun_mode: bool,
+    #[serde(default)]
+    pub control_scheme: ControlScheme,
+    /// Snap segments from cell to cell once per tick
+    /// instead of sliding them
+    #[serde(default)]
+    pub classic_stepping: bool,
+    /// No particles, shaking or other animations
+    #[serde(default)]
+    pub reduce_motion: bool,
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    #[serde(default)]
+    pub accessibility: Accessibility,
+    /// Flat checkered cells instead of grass
+    #[serde(default)]
+    pub plain_grid: bool,
+    #[serde(default = "default_language")]
+    pub language: String,
+    #[serde(default)]
+    pub hud: HudSettings,
+    #[serde(default = "default_board_size")]
+    pub board_size: u16,
+}
+
+fn default_theme() -> String {
+    "Meadow".to_string()
+}
+
+fn default_board_size() -> u16 {
+    GameRules::default().board_size
+}
+
+fn default_language() -> String {
+    locale::BASE_LANGUAGE.to_string()
 }
 
 impl Default for GameSettings {
@@ -15,6 +89,427 @@ impl Default for GameSettings {
         GameSettings {
             speed: GameSpeed::REGULAR,
             speedrun_mode: false,
+            control_scheme: ControlScheme::Absolute,
+            classic_stepping: false,
+            reduce_motion: false,
+            theme: default_theme(),
+            accessibility: Accessibility::Standard,
+            plain_grid: false,
+            language: default_language(),
+            hud: HudSettings::default(),
+            board_size: default_board_size(),
+        }
+    }
+}
+
+impl GameSettings {
+    pub const BOARD_SIZES: [u16; 6] =
+        [10, 15, 20, 30, 50, 100];
+
+    pub fn classic_rules(&self) -> GameRules {
+        GameRules {
+            speed: self.speed,
+            board_size: self.board_size.clamp(
+                *GameRules::BOARD_SIZES.start(),
+                *GameRules::BOARD_SIZES.end(),
+            ),
+            ..GameRules::default()
+        }
+    }
+
+    /// The next size in `BOARD_SIZES` after the current
+    /// one, wrapping around.
+    pub fn next_board_size(&self) -> u16 {
+        GameSettings::BOARD_SIZES
+            .into_iter()
+            .find(|size| *size > self.board_size)
+            .unwrap_or(GameSettings::BOARD_SIZES[0])
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct HudSettings {
+    pub position: HudPosition,
+    /// Shown in `HudElement::ALL` order
+    pub elements: Vec<HudElement>,
+}
+
+impl Default for HudSettings {
+    fn default() -> Self {
+        HudSettings {
+            position: HudPosition::Top,
+            elements: vec![
+                HudElement::Title,
+                HudElement::Score,
+                HudElement::Best,
+            ],
+        }
+    }
+}
+
+impl HudSettings {
+    pub fn shows(&self, element: HudElement) -> bool {
+        self.elements.contains(&element)
+    }
+
+    pub fn toggle(&mut self, element: HudElement) {
+        let shown = !self.shows(element);
+        let elements = std::mem::take(&mut self.elements);
+        self.elements = HudElement::ALL
+            .into_iter()
+            .filter(|other| {
+                if *other == element {
+                    shown
+                } else {
+                    elements.contains(other)
+                }
+            })
+            .collect();
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum HudElement {
+    Title,
+    Score,
+    Best,
+    Timer,
+    Length,
+    Speed,
+    /// Pause and the modifiers of the current rules
+    Effects,
+    /// The actions being held
+    Input,
+}
+
+impl HudElement {
+    pub const ALL: [HudElement; 8] = [
+        HudElement::Title,
+        HudElement::Score,
+        HudElement::Best,
+        HudElement::Timer,
+        HudElement::Length,
+        HudElement::Speed,
+        HudElement::Effects,
+        HudElement::Input,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            HudElement::Title => "title",
+            HudElement::Score => "hud.score",
+            HudElement::Best => "hud.best",
+            HudElement::Timer => "hud.time",
+            HudElement::Length => "hud.length",
+            HudElement::Speed => "hud.speed",
+            HudElement::Effects => "hud.effects",
+            HudElement::Input => "hud.input",
+        }
+    }
+}
+
+/// Where the HUD sits. Along the top or bottom edge
+/// it's a row, in a corner a column.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum HudPosition {
+    #[default]
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl HudPosition {
+    pub const ALL: [HudPosition; 6] = [
+        HudPosition::Top,
+        HudPosition::Bottom,
+        HudPosition::TopLeft,
+        HudPosition::TopRight,
+        HudPosition::BottomLeft,
+        HudPosition::BottomRight,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            HudPosition::Top => "position.top",
+            HudPosition::Bottom => "position.bottom",
+            HudPosition::TopLeft => "position.top_left",
+            HudPosition::TopRight => "position.top_right",
+            HudPosition::BottomLeft => {
+                "position.bottom_left"
+            }
+            HudPosition::BottomRight => {
+                "position.bottom_right"
+            }
+        }
+    }
+
+    pub fn next(&self) -> HudPosition {
+        let index = HudPosition::ALL
+            .iter()
+            .position(|position| position == self)
+            .unwrap_or(0);
+        HudPosition::ALL
+            [(index + 1) % HudPosition::ALL.len()]
+    }
+}
+
+/// Colors and shapes that make the board easier to
+/// read, drawn over whatever theme is picked.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Accessibility {
+    /// The theme as it is
+    #[default]
+    Standard,
+    /// Colors that colorblind players can tell apart,
+    /// with outlines and patterned food
+    ColorSafe,
+    /// Light on black, with outlines and patterned food
+    HighContrast,
+}
+
+impl Accessibility {
+    pub const ALL: [Accessibility; 3] = [
+        Accessibility::Standard,
+        Accessibility::ColorSafe,
+        Accessibility::HighContrast,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Accessibility::Standard => {
+                "accessibility.standard"
+            }
+            Accessibility::ColorSafe => {
+                "accessibility.color_safe"
+            }
+            Accessibility::HighContrast => {
+                "accessibility.high_contrast"
+            }
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum ControlScheme {
+    #[default]
+    Absolute,
+    /// Left and right turn relative to where the snake
+    /// is heading, up and down do nothing
+    Relative,
+}
+
+impl ControlScheme {
+    pub fn steer(
+        &self,
+        heading: Direction,
+        pressed: Direction,
+    ) -> Option<Direction> {
+        match (self, pressed) {
+            (ControlScheme::Absolute, _) => Some(pressed),
+            (ControlScheme::Relative, Direction::Left) => {
+                Some(heading.turn_left())
+            }
+            (ControlScheme::Relative, Direction::Right) => {
+                Some(heading.turn_right())
+            }
+            (ControlScheme::Relative, _) => None,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            ControlScheme::Absolute => "scheme.absolute",
+            ControlScheme::Relative => "scheme.relative",
+        }
+    }
+
+    pub fn toggled(&self) -> ControlScheme {
+        match self {
+            ControlScheme::Absolute => {
+                ControlScheme::Relative
+            }
+            ControlScheme::Relative => {
+                ControlScheme::Absolute
+            }
+        }
+    }
+}
+
+/// Shared by every frontend, so a speed picked in the
+/// window is also used in the terminal.
+impl GameSettings {
+    pub fn load() -> Self {
+        persistence::load(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        persistence::save(SETTINGS_FILE, self);
+    }
+}
+
+pub fn save_settings(settings: Res<GameSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Modifier {
+    /// Two apples are on the board at all times
+    DoubleApples,
+    /// The snake starts four segments long
+    LongStart,
+    /// Rocks are scattered across the board
+    Obstacles,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 3] = [
+        Modifier::DoubleApples,
+        Modifier::LongStart,
+        Modifier::Obstacles,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Modifier::DoubleApples => {
+                "modifier.double_apples"
+            }
+            Modifier::LongStart => "modifier.long_start",
+            Modifier::Obstacles => "modifier.obstacles",
+        }
+    }
+}
+
+/// Classic games build these from `GameSettings`, the daily
+/// challenge derives them from the date.
+#[derive(
+    Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct GameRules {
+    /// `None` picks a fresh random seed every game
+    pub seed: Option<u64>,
+    pub board_size: u16,
+    pub speed: GameSpeed,
+    pub modifiers: Vec<Modifier>,
+}
+
+impl GameRules {
+    /// Snakes start four cells in from the left, smaller
+    /// boards leave them next to no room ahead. Bigger
+    /// ones take too long to draw and to keep an
+    /// occupancy grid for.
+    pub const BOARD_SIZES: RangeInclusive<u16> = 10..=1024;
+    /// Most snakes a game is started with, however they
+    /// are controlled.
+    pub const MAX_PLAYERS: usize = 4;
+
+    pub fn has(&self, modifier: Modifier) -> bool {
+        self.modifiers.contains(&modifier)
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules {
+            seed: None,
+            board_size: 20,
+            speed: GameSpeed::REGULAR,
+            modifiers: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_shows_and_hides() {
+        let mut hud = HudSettings::default();
+        hud.toggle(HudElement::Score);
+        assert!(!hud.shows(HudElement::Score));
+        assert_eq!(
+            hud.elements,
+            [HudElement::Title, HudElement::Best]
+        );
+        hud.toggle(HudElement::Score);
+        assert_eq!(hud, HudSettings::default());
+    }
+
+    #[test]
+    fn toggle_keeps_the_display_order() {
+        let mut hud = HudSettings {
+            position: HudPosition::Top,
+            elements: vec![],
+        };
+        for element in [
+            HudElement::Input,
+            HudElement::Title,
+            HudElement::Timer,
+        ] {
+            hud.toggle(element);
         }
+        assert_eq!(
+            hud.elements,
+            [
+                HudElement::Title,
+                HudElement::Timer,
+                HudElement::Input
+            ]
+        );
     }
 }
@@ -0,0 +1,434 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::ensure;
+use bevy::{
+    asset::{
+        AssetLoader, AssetPath, BoxedFuture, LoadContext,
+        LoadedAsset,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{persistence, snake::SnakeTextureSelection};
+
+const SKIN_FOLDER: &str = "skins";
+const MANIFEST_EXTENSION: &str = "skins.ron";
+
+/// Snake skins from `.skins.ron` manifests in
+/// `assets/skins` and the data directory's `skins` folder.
+pub struct SkinsPlugin;
+
+impl Plugin for SkinsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<SkinManifest>()
+            .init_asset_loader::<SkinLoader>()
+            .init_resource::<SkinManifests>()
+            .init_resource::<Skins>()
+            .add_system(collect_skins);
+    }
+}
+
+/// A manifest as it's written. Paths are relative to
+/// the manifest, so user skins can sit next to theirs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkinFile {
+    pub skins: Vec<SkinSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkinSpec {
+    /// Shown in the selector, skins are picked by
+    /// their `Skin::id`
+    pub name: String,
+    pub atlas: AtlasSpec,
+    pub frames: Frames,
+    #[serde(default)]
+    pub rotations: Rotations,
+    /// Multiplied into every sprite of the skin
+    #[serde(default = "default_tint")]
+    pub tint: Color,
+    /// A small picture for the selector, the whole
+    /// atlas if there is none
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+}
+
+fn default_tint() -> Color {
+    Color::WHITE
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtlasSpec {
+    pub path: String,
+    pub tile_size: f32,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// Which frame of the atlas each piece of the snake
+/// is. Heads and tails are drawn joining the segment
+/// above them, straight pieces run up and down and
+/// corners join the cells below and to the right.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Serialize, Deserialize,
+)]
+pub struct Frames {
+    pub head: usize,
+    pub body: usize,
+    pub corner: usize,
+    pub tail: usize,
+}
+
+/// Extra counter-clockwise turn in degrees for frames
+/// that aren't drawn the way `Frames` expects.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
+pub struct Rotations {
+    #[serde(default)]
+    pub head: f32,
+    #[serde(default)]
+    pub body: f32,
+    #[serde(default)]
+    pub corner: f32,
+    #[serde(default)]
+    pub tail: f32,
+}
+
+impl SkinSpec {
+    fn validate(&self) -> anyhow::Result<()> {
+        let frames = self.atlas.columns * self.atlas.rows;
+        let Frames {
+            head,
+            body,
+            corner,
+            tail,
+        } = self.frames;
+        ensure!(
+            [head, body, corner, tail]
+                .iter()
+                .all(|frame| *frame < frames),
+            "skin {:?} uses frames past the end of its \
+             {} frame atlas",
+            self.name,
+            frames
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Piece {
+    Head,
+    Body,
+    Corner,
+    Tail,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skin {
+    /// The manifest's path and the skin's place in it,
+    /// which stays the same when names are edited or
+    /// shared between manifests
+    pub id: String,
+    pub name: String,
+    pub image: Handle<Image>,
+    pub atlas: Handle<TextureAtlas>,
+    pub thumbnail: Handle<Image>,
+    /// smallest image the atlas fits in
+    pub atlas_size: Vec2,
+    pub frames: Frames,
+    pub rotations: Rotations,
+    pub tint: Color,
+}
+
+impl Skin {
+    pub fn piece(
+        &self,
+        piece: Piece,
+        rotation: Quat,
+    ) -> (usize, Quat) {
+        let (frame, degrees) = match piece {
+            Piece::Head => {
+                (self.frames.head, self.rotations.head)
+            }
+            Piece::Body => {
+                (self.frames.body, self.rotations.body)
+            }
+            Piece::Corner => {
+                (self.frames.corner, self.rotations.corner)
+            }
+            Piece::Tail => {
+                (self.frames.tail, self.rotations.tail)
+            }
+        };
+        (
+            frame,
+            rotation
+                * Quat::from_rotation_z(
+                    degrees.to_radians(),
+                ),
+        )
+    }
+
+    fn fits(&self, image: &Image) -> bool {
+        let size = image.size();
+        size.x >= self.atlas_size.x
+            && size.y >= self.atlas_size.y
+    }
+}
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "0b8bd8d5-5b5f-4a37-9fb4-bd5a8f0b9e07"]
+pub struct SkinManifest {
+    pub skins: Vec<Skin>,
+}
+
+#[derive(Default)]
+pub struct SkinLoader;
+
+impl AssetLoader for SkinLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let file: SkinFile =
+                ron::de::from_bytes(bytes)?;
+            let dir = load_context
+                .path()
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            let manifest_path = load_context
+                .path()
+                .to_string_lossy()
+                .replace('\\', "/");
+            let mut dependencies = vec![];
+            let mut skins = vec![];
+            for (index, spec) in
+                file.skins.into_iter().enumerate()
+            {
+                spec.validate()?;
+                let image_path = AssetPath::new(
+                    dir.join(&spec.atlas.path),
+                    None,
+                );
+                let thumbnail_path = match &spec.thumbnail {
+                    Some(path) => {
+                        AssetPath::new(dir.join(path), None)
+                    }
+                    None => image_path.clone(),
+                };
+                let image: Handle<Image> = load_context
+                    .get_handle(image_path.clone());
+                let tile_size =
+                    Vec2::splat(spec.atlas.tile_size);
+                let atlas = load_context.set_labeled_asset(
+                    &format!("atlas{}", index),
+                    LoadedAsset::new(
+                        TextureAtlas::from_grid(
+                            image.clone(),
+                            tile_size,
+                            spec.atlas.columns,
+                            spec.atlas.rows,
+                        ),
+                    ),
+                );
+                skins.push(Skin {
+                    id: skin_id(&manifest_path, index),
+                    name: spec.name,
+                    image,
+                    atlas,
+                    thumbnail: load_context
+                        .get_handle(thumbnail_path.clone()),
+                    atlas_size: tile_size
+                        * Vec2::new(
+                            spec.atlas.columns as f32,
+                            spec.atlas.rows as f32,
+                        ),
+                    frames: spec.frames,
+                    rotations: spec.rotations,
+                    tint: spec.tint,
+                });
+                dependencies.push(image_path);
+                dependencies.push(thumbnail_path);
+            }
+            load_context.set_default_asset(
+                LoadedAsset::new(SkinManifest { skins })
+                    .with_dependencies(dependencies),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[MANIFEST_EXTENSION]
+    }
+}
+
+/// Every manifest found, built-in ones first, so skins
+/// keep the same order from run to run.
+pub struct SkinManifests(pub Vec<Handle<SkinManifest>>);
+
+impl FromWorld for SkinManifests {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server =
+            world.get_resource::<AssetServer>().unwrap();
+
+        // the folder also holds the skins' images
+        let mut built_in = asset_server
+            .load_folder(SKIN_FOLDER)
+            .unwrap_or_else(|error| {
+                warn!(%error, "failed to load skins");
+                vec![]
+            })
+            .into_iter()
+            .filter_map(|handle| {
+                let path = asset_server
+                    .get_handle_path(&handle)?
+                    .path()
+                    .to_path_buf();
+                is_manifest(&path).then(|| path)
+            })
+            .collect::<Vec<PathBuf>>();
+        built_in.sort();
+
+        // loaded by absolute path, which the asset
+        // server reads from outside `assets`
+        let mut installed = fs::read_dir(user_skin_dir())
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| {
+                        Some(entry.ok()?.path())
+                    })
+                    .filter(|path| is_manifest(path))
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+        installed.sort();
+
+        SkinManifests(
+            built_in
+                .into_iter()
+                .chain(installed)
+                .map(|path| asset_server.load(path))
+                .collect(),
+        )
+    }
+}
+
+pub fn skin_id(manifest: &str, index: usize) -> String {
+    format!("{}#{}", manifest, index)
+}
+
+pub fn user_skin_dir() -> PathBuf {
+    persistence::data_dir().join(SKIN_FOLDER)
+}
+
+fn is_manifest(path: &Path) -> bool {
+    path.file_name().and_then(|name| name.to_str()).map_or(
+        false,
+        |name| {
+            name.to_lowercase().ends_with(&format!(
+                ".{}",
+                MANIFEST_EXTENSION
+            ))
+        },
+    )
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Skins(pub Vec<Skin>);
+
+impl Skins {
+    /// The skin for snake `snake`. Other players get
+    /// one further down the list so they never look
+    /// like the local snake. `None` until a skin has
+    /// loaded.
+    pub fn for_snake(
+        &self,
+        selection: &SnakeTextureSelection,
+        snake: usize,
+        local: usize,
+    ) -> Option<&Skin> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let chosen = self
+            .0
+            .iter()
+            .position(|skin| skin.id == selection.0)
+            .unwrap_or(0);
+        let index = if snake == local {
+            chosen
+        } else {
+            (chosen + snake * 7) % self.0.len()
+        };
+        self.0.get(index)
+    }
+}
+
+/// Rebuilds `Skins` as manifests and their images
+/// load. Skins whose image is too small for their atlas
+/// are left out with a warning.
+fn collect_skins(
+    mut manifest_events: EventReader<
+        AssetEvent<SkinManifest>,
+    >,
+    mut image_events: EventReader<AssetEvent<Image>>,
+    sources: Res<SkinManifests>,
+    manifests: Res<Assets<SkinManifest>>,
+    images: Res<Assets<Image>>,
+    mut warned: Local<HashSet<String>>,
+    mut skins: ResMut<Skins>,
+) {
+    let manifests_changed =
+        manifest_events.iter().count() > 0;
+    let images_changed = image_events.iter().count() > 0;
+    if !manifests_changed && !images_changed {
+        return;
+    }
+
+    let mut installed = vec![];
+    for skin in sources
+        .0
+        .iter()
+        .filter_map(|handle| manifests.get(handle))
+        .flat_map(|manifest| manifest.skins.iter())
+    {
+        let image = match images.get(&skin.image) {
+            Some(image) => image,
+            None => continue,
+        };
+        if !skin.fits(image) {
+            if warned.insert(skin.id.clone()) {
+                warn!(
+                    skin = %skin.name,
+                    expected = ?skin.atlas_size,
+                    found = ?image.size(),
+                    "skin image is smaller than its atlas"
+                );
+            }
+            continue;
+        }
+        installed.push(skin.clone());
+    }
+    if skins.0 != installed {
+        skins.0 = installed;
+    }
+}
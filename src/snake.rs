@@ -1,18 +1,36 @@
 use bevy::prelude::*;
-use itertools::Itertools;
-use std::collections::VecDeque;
+use itertools::Either;
+use iyes_loopless::prelude::FixedTimestepInfo;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
 
-use crate::board::Position;
+use crate::{
+    board::{Board, Position, SpawnSnakeSegment},
+    colors::tinted,
+    common::LocalPlayer,
+    rules::{Direction, GameState, TickEvent},
+    settings::GameSettings,
+    skins::{skin_id, Piece, Skin, Skins},
+    theme::ActiveTheme,
+};
 
-pub struct SnakeTextureSelection(pub usize);
+/// The id of the skin the local snake is drawn with,
+/// see `Skin::id`.
+pub struct SnakeTextureSelection(pub String);
 
 impl Default for SnakeTextureSelection {
     fn default() -> Self {
-        SnakeTextureSelection(116)
+        SnakeTextureSelection(skin_id(
+            "skins/classic.skins.ron",
+            29,
+        ))
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct SnakeBody {
     pub segments: VecDeque<Position>,
 }
@@ -28,166 +46,533 @@ impl Default for SnakeBody {
     }
 }
 
-#[derive(Debug)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+/// Marks a sprite as part of the snake with this index
+/// in `GameState::snakes`.
+#[derive(Component)]
+pub struct SnakeSegment {
+    pub snake: usize,
+}
+
+/// The sprite entities of every snake, in the same
+/// order as `SnakeBody::segments`, so a segment's
+/// sprite is found by its index instead of by
+/// searching. Kept out of `SnakeBody` so the rules
+/// stay free of Bevy.
+#[derive(Default)]
+pub struct SegmentEntities {
+    pub snakes: Vec<SnakeSprites>,
+    refresh: bool,
+}
+
+#[derive(Default)]
+pub struct SnakeSprites {
+    pub segments: VecDeque<Entity>,
+    pub trailing: Option<Entity>,
+}
+
+impl SegmentEntities {
+    pub fn spawn(
+        commands: &mut Commands,
+        state: &GameState,
+    ) -> Self {
+        let snakes = state
+            .snakes
+            .iter()
+            .enumerate()
+            .map(|(snake, body)| SnakeSprites {
+                segments: body
+                    .body
+                    .segments
+                    .iter()
+                    .map(|position| {
+                        spawn_segment(
+                            commands, snake, *position,
+                        )
+                    })
+                    .collect(),
+                trailing: None,
+            })
+            .collect();
+        SegmentEntities {
+            snakes,
+            refresh: true,
+        }
+    }
+}
+
+fn spawn_segment(
+    commands: &mut Commands,
+    snake: usize,
+    position: Position,
+) -> Entity {
+    let entity = commands.spawn().id();
+    commands.add(SpawnSnakeSegment {
+        entity,
+        snake,
+        position,
+    });
+    entity
+}
+
+/// Mirrors the snakes' moves onto their sprites. The
+/// tail's sprite is moved to the new head instead of
+/// despawning one and spawning another, and each snake
+/// keeps a single trailing sprite that follows its old
+/// tail around.
+pub fn sync_segments(
+    mut commands: Commands,
+    mut tick_events: EventReader<TickEvent>,
+    boards: Query<&Board>,
+    mut ring: ResMut<SegmentEntities>,
+    mut segments: Query<
+        (&mut Position, &mut Transform),
+        With<SnakeSegment>,
+    >,
+) {
+    let board = match boards.iter().next() {
+        Some(board) => board,
+        None => return,
+    };
+    let mut place =
+        |entity: Entity, position: Position| match segments
+            .get_mut(entity)
+        {
+            Ok((mut current, mut transform)) => {
+                *current = position;
+                transform.translation =
+                    board.cell_center(position).extend(2.0);
+                true
+            }
+            Err(_) => false,
+        };
+
+    for event in tick_events.iter() {
+        let (snake, head, tail) = match event {
+            TickEvent::Moved { snake, head, tail } => {
+                (*snake, *head, *tail)
+            }
+            _ => continue,
+        };
+        let sprites = match ring.snakes.get_mut(snake) {
+            Some(sprites) => sprites,
+            None => continue,
+        };
+        let recycled = match tail {
+            Some(_) => sprites.segments.pop_back(),
+            None => None,
+        };
+        let head_entity = match recycled {
+            Some(entity) if place(entity, head) => entity,
+            _ => spawn_segment(&mut commands, snake, head),
+        };
+        sprites.segments.push_front(head_entity);
+
+        if let Some(tail) = tail {
+            sprites.trailing = match sprites.trailing {
+                Some(entity) if place(entity, tail) => {
+                    Some(entity)
+                }
+                _ => Some(spawn_segment(
+                    &mut commands,
+                    snake,
+                    tail,
+                )),
+            };
+        }
+    }
 }
 
 pub fn render_snake_segments(
-    snake: Res<SnakeBody>,
-    snake_index: Res<SnakeTextureSelection>,
-    mut positions: Query<(
-        &Position,
-        &mut TextureAtlasSprite,
-        &mut Transform,
-    )>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    selection: Res<SnakeTextureSelection>,
+    skins: Res<Skins>,
+    theme: Res<ActiveTheme>,
+    mut ring: ResMut<SegmentEntities>,
+    mut sprites: Query<
+        (
+            &mut TextureAtlasSprite,
+            &mut Handle<TextureAtlas>,
+            &mut Transform,
+        ),
+        With<SnakeSegment>,
+    >,
 ) {
-    let snake_texture_index = snake_index.0;
-
-    if snake.segments.len() > 1 {
-        let current_position = positions
-            .iter_mut()
-            .find(|pos| pos.0 == &snake.segments[0]);
-
-        match current_position {
-            Some((pos, mut sprite, mut transform)) => {
-                let rotation = match detect_side(
-                    pos,
-                    &snake.segments[1],
-                ) {
-                    Direction::Up => {
-                        Quat::from_rotation_z(0.0)
-                    }
-                    Direction::Down => {
-                        Quat::from_rotation_z(
-                            std::f32::consts::PI,
-                        )
-                    }
-                    Direction::Left => {
-                        Quat::from_rotation_z(
-                            std::f32::consts::FRAC_PI_2,
-                        )
-                    }
-                    Direction::Right => {
-                        Quat::from_rotation_z(
-                            -std::f32::consts::FRAC_PI_2,
-                        )
-                    }
+    let full = ring.refresh
+        || selection.is_changed()
+        || skins.is_changed()
+        || theme.is_changed();
+    if !full && !state.is_changed() {
+        return;
+    }
+    let mut missing = false;
+    for (index, snake) in state.snakes.iter().enumerate() {
+        // dead snakes are removed from multiplayer boards
+        if !snake.is_alive() && state.snakes.len() > 1 {
+            continue;
+        }
+        let (entities, trailing) =
+            match ring.snakes.get(index) {
+                Some(sprites) => {
+                    (&sprites.segments, sprites.trailing)
+                }
+                None => continue,
+            };
+        let skin = match skins
+            .for_snake(&selection, index, local.0)
+        {
+            Some(skin) => skin,
+            // nothing to draw with until a skin loads
+            None => {
+                missing = true;
+                continue;
+            }
+        };
+        let tint =
+            tinted(skin.tint, theme.palette.snake_tint);
+        let body = &snake.body.segments;
+        let len = body.len();
+        // a tick only changes the head, the segment
+        // behind it and the tail
+        let changed = if full {
+            Either::Left(0..len)
+        } else {
+            Either::Right(
+                [0, 1, len.saturating_sub(1)].into_iter(),
+            )
+        };
+        for segment in changed {
+            let piece =
+                match segment_sprite(body, segment, skin) {
+                    Some(piece) => piece,
+                    None => continue,
                 };
-                sprite.index = snake_texture_index;
-                transform.rotation = rotation;
+            match entities.get(segment).and_then(|entity| {
+                sprites.get_mut(*entity).ok()
+            }) {
+                Some((
+                    mut sprite,
+                    mut atlas,
+                    mut transform,
+                )) => {
+                    if full {
+                        *atlas = skin.atlas.clone();
+                        sprite.color = tint;
+                    }
+                    sprite.index = piece.0;
+                    transform.rotation = piece.1;
+                }
+                None => missing = true,
             }
-            None => {}
         }
+        if let Some(Ok((mut sprite, mut atlas, _))) =
+            trailing
+                .filter(|_| full)
+                .map(|entity| sprites.get_mut(entity))
+        {
+            *atlas = skin.atlas.clone();
+            sprite.color = tint;
+        }
+    }
+    // sprites spawned this frame only show up once the
+    // commands have run, try again next frame
+    if ring.refresh && !missing {
+        ring.refresh = false;
     }
+}
 
-    if snake.segments.len() > 1 {
-        let current_position =
-            positions.iter_mut().find(|pos| {
-                pos.0
-                    == &snake.segments
-                        [snake.segments.len() - 1]
-            });
-
-        match current_position {
-            Some((pos, mut sprite, mut transform)) => {
-                let rotation = match detect_side(
-                    pos,
-                    &snake.segments
-                        [snake.segments.len() - 2],
-                ) {
-                    Direction::Up => {
-                        Quat::from_rotation_z(0.0)
-                    }
-                    Direction::Down => {
-                        Quat::from_rotation_z(
-                            std::f32::consts::PI,
-                        )
-                    }
-                    Direction::Left => {
-                        Quat::from_rotation_z(
-                            std::f32::consts::FRAC_PI_2,
-                        )
-                    }
-                    Direction::Right => {
-                        Quat::from_rotation_z(
-                            -std::f32::consts::FRAC_PI_2,
-                        )
-                    }
-                };
+fn segment_sprite(
+    body: &VecDeque<Position>,
+    index: usize,
+    skin: &Skin,
+) -> Option<(usize, Quat)> {
+    let len = body.len();
+    if len < 2 || index >= len {
+        return None;
+    }
+    Some(if index == 0 {
+        skin.piece(
+            Piece::Head,
+            rotation_for(detect_side(&body[0], &body[1])),
+        )
+    } else if index == len - 1 {
+        skin.piece(
+            Piece::Tail,
+            rotation_for(detect_side(
+                &body[index],
+                &body[index - 1],
+            )),
+        )
+    } else {
+        body_piece(
+            skin,
+            detect_side(&body[index], &body[index - 1]),
+            detect_side(&body[index], &body[index + 1]),
+        )
+    })
+}
+
+/// How far the game is between the last tick and the
+/// next, for sliding sprites between cells.
+#[derive(Default)]
+pub struct TickClock {
+    tick: u64,
+    elapsed: Duration,
+    step: Duration,
+    /// the clock was synced this frame, so the frame's
+    /// time is already counted
+    synced: bool,
+    /// where each snake's tail was before the last tick,
+    /// `None` if it grew instead
+    tails: HashMap<usize, Option<Position>>,
+}
+
+impl TickClock {
+    pub fn fraction(&self) -> f32 {
+        if self.step.is_zero() {
+            return 1.0;
+        }
+        (self.elapsed.as_secs_f32()
+            / self.step.as_secs_f32())
+        .min(1.0)
+    }
+}
+
+/// Runs in the fixed timestep after the tick, where
+/// `FixedTimestepInfo` knows how much time is left over.
+pub fn sync_tick_clock(
+    state: Res<GameState>,
+    info: Res<FixedTimestepInfo>,
+    mut tick_events: EventReader<TickEvent>,
+    mut clock: ResMut<TickClock>,
+) {
+    let mut tails = HashMap::new();
+    for event in tick_events.iter() {
+        if let TickEvent::Moved { snake, tail, .. } = event
+        {
+            tails.insert(*snake, *tail);
+        }
+    }
+    if state.tick == clock.tick {
+        return;
+    }
+    clock.tick = state.tick;
+    clock.step = info.step;
+    clock.synced = true;
+    clock.tails = tails;
+    // a fresh board starts at rest
+    clock.elapsed = if state.tick == 0 {
+        info.step
+    } else {
+        info.accumulator
+    };
+}
 
-                sprite.index = snake_texture_index + 3;
+/// Slides head and tail sprites between cells by the
+/// elapsed fraction of the tick. While the tail slides, the
+/// segment under it is drawn as body so the corner shows up
+/// behind it.
+pub fn interpolate_segments(
+    time: Res<Time>,
+    settings: Res<GameSettings>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    selection: Res<SnakeTextureSelection>,
+    skins: Res<Skins>,
+    ring: Res<SegmentEntities>,
+    boards: Query<&Board>,
+    mut clock: ResMut<TickClock>,
+    mut segments: Query<
+        (
+            &mut TextureAtlasSprite,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<SnakeSegment>,
+    >,
+) {
+    if clock.synced {
+        clock.synced = false;
+    } else {
+        clock.elapsed += time.delta();
+    }
+    let board = match boards.iter().next() {
+        Some(board) => board,
+        None => return,
+    };
+    let classic = settings.classic_stepping;
+    let t = if classic { 1.0 } else { clock.fraction() };
+
+    for (index, snake) in state.snakes.iter().enumerate() {
+        if !snake.is_alive() && state.snakes.len() > 1 {
+            continue;
+        }
+        let (sprites, body) = match ring.snakes.get(index) {
+            Some(sprites)
+                if snake.body.segments.len() > 1 =>
+            {
+                (sprites, &snake.body.segments)
+            }
+            _ => continue,
+        };
+        let skin = match skins
+            .for_snake(&selection, index, local.0)
+        {
+            Some(skin) => skin,
+            None => continue,
+        };
+        let len = body.len();
+        let tail = body[len - 1];
+        let moved =
+            !classic && clock.tails.contains_key(&index);
+        let old_tail = clock
+            .tails
+            .get(&index)
+            .copied()
+            .flatten()
+            .filter(|_| !classic);
+
+        // the neck has arrived, the head slides in
+        // front of it
+        if let Some(Ok((_, mut transform, _))) = sprites
+            .segments
+            .get(1)
+            .map(|entity| segments.get_mut(*entity))
+        {
+            transform.translation =
+                board.cell_center(body[1]).extend(2.0);
+        }
+        if let Some(Ok((_, mut transform, _))) = sprites
+            .segments
+            .front()
+            .map(|entity| segments.get_mut(*entity))
+        {
+            let end = board.cell_center(body[0]);
+            let start = if moved {
+                board.cell_center(body[1])
+            } else {
+                end
+            };
+            transform.translation =
+                start.lerp(end, t).extend(2.1);
+        }
+
+        if let Some(Ok((mut sprite, mut transform, _))) =
+            sprites
+                .segments
+                .back()
+                .map(|entity| segments.get_mut(*entity))
+        {
+            let (piece, rotation) = match old_tail {
+                Some(from) => body_piece(
+                    skin,
+                    detect_side(&tail, &body[len - 2]),
+                    detect_side(&tail, &from),
+                ),
+                None => segment_sprite(body, len - 1, skin)
+                    .unwrap(),
+            };
+            sprite.index = piece;
+            transform.rotation = rotation;
+        }
+
+        let ghost = match sprites.trailing {
+            Some(entity) => segments.get_mut(entity),
+            None => continue,
+        };
+        if let Ok((
+            mut sprite,
+            mut transform,
+            mut visibility,
+        )) = ghost
+        {
+            visibility.is_visible = old_tail.is_some();
+            if let Some(from) = old_tail {
+                let start = board.cell_center(from);
+                let end = board.cell_center(tail);
+                let (frame, rotation) = skin.piece(
+                    Piece::Tail,
+                    rotation_for(detect_side(&from, &tail))
+                        .slerp(
+                            rotation_for(detect_side(
+                                &tail,
+                                &body[len - 2],
+                            )),
+                            t,
+                        ),
+                );
+                sprite.index = frame;
+                transform.translation =
+                    start.lerp(end, t).extend(2.1);
                 transform.rotation = rotation;
             }
-            None => {}
         }
     }
+}
 
-    for (front, origin, back) in
-        snake.segments.iter().tuple_windows()
-    {
-        let a = detect_side(origin, front);
-        let b = detect_side(origin, back);
-
-        let image = match (a, b) {
-            // vertical
-            (Direction::Down, Direction::Up)
-            | (Direction::Up, Direction::Down) => (
-                snake_texture_index + 1,
-                Quat::from_rotation_z(0.0),
-            ),
-            // horizontal
-            (Direction::Right, Direction::Left)
-            | (Direction::Left, Direction::Right) => (
-                snake_texture_index + 1,
-                Quat::from_rotation_z(
-                    std::f32::consts::FRAC_PI_2,
-                ),
-            ),
-            // ⌞
-            (Direction::Up, Direction::Right)
-            | (Direction::Right, Direction::Up) => (
-                snake_texture_index + 2,
-                Quat::from_rotation_z(
-                    std::f32::consts::FRAC_PI_2,
-                ),
-            ),
-            // ⌜
-            (Direction::Right, Direction::Down)
-            | (Direction::Down, Direction::Right) => (
-                snake_texture_index + 2,
-                Quat::from_rotation_z(0.0),
+fn body_piece(
+    skin: &Skin,
+    a: Direction,
+    b: Direction,
+) -> (usize, Quat) {
+    let (piece, rotation) = match (a, b) {
+        // vertical
+        (Direction::Down, Direction::Up)
+        | (Direction::Up, Direction::Down) => {
+            (Piece::Body, Quat::from_rotation_z(0.0))
+        }
+        // horizontal
+        (Direction::Right, Direction::Left)
+        | (Direction::Left, Direction::Right) => (
+            Piece::Body,
+            Quat::from_rotation_z(
+                std::f32::consts::FRAC_PI_2,
             ),
-            // ⌟
-            (Direction::Left, Direction::Up)
-            | (Direction::Up, Direction::Left) => (
-                snake_texture_index + 2,
-                Quat::from_rotation_z(std::f32::consts::PI),
+        ),
+        // ⌞
+        (Direction::Up, Direction::Right)
+        | (Direction::Right, Direction::Up) => (
+            Piece::Corner,
+            Quat::from_rotation_z(
+                std::f32::consts::FRAC_PI_2,
             ),
-            // ⌝
-            (Direction::Left, Direction::Down)
-            | (Direction::Down, Direction::Left) => (
-                snake_texture_index + 2,
-                Quat::from_rotation_z(
-                    -std::f32::consts::FRAC_PI_2,
-                ),
+        ),
+        // ⌜
+        (Direction::Right, Direction::Down)
+        | (Direction::Down, Direction::Right) => {
+            (Piece::Corner, Quat::from_rotation_z(0.0))
+        }
+        // ⌟
+        (Direction::Left, Direction::Up)
+        | (Direction::Up, Direction::Left) => (
+            Piece::Corner,
+            Quat::from_rotation_z(std::f32::consts::PI),
+        ),
+        // ⌝
+        (Direction::Left, Direction::Down)
+        | (Direction::Down, Direction::Left) => (
+            Piece::Corner,
+            Quat::from_rotation_z(
+                -std::f32::consts::FRAC_PI_2,
             ),
-            _ => panic!("unhandled"),
-        };
-        let current_position = positions
-            .iter_mut()
-            .find(|pos| pos.0 == origin);
-
-        match current_position {
-            Some((_, mut sprite, mut transform)) => {
-                sprite.index = image.0;
-                transform.rotation = image.1;
-            }
-            None => {}
+        ),
+        _ => panic!("unhandled"),
+    };
+    skin.piece(piece, rotation)
+}
+
+fn rotation_for(direction: Direction) -> Quat {
+    match direction {
+        Direction::Up => Quat::from_rotation_z(0.0),
+        Direction::Down => {
+            Quat::from_rotation_z(std::f32::consts::PI)
         }
+        Direction::Left => Quat::from_rotation_z(
+            std::f32::consts::FRAC_PI_2,
+        ),
+        Direction::Right => Quat::from_rotation_z(
+            -std::f32::consts::FRAC_PI_2,
+        ),
     }
 }
 
@@ -0,0 +1,492 @@
+use bevy::prelude::*;
+use bevy_kira_audio::{Audio, AudioChannel, AudioSource};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::AudioAssets,
+    common::{LocalPlayer, NewBestEvent, RunState},
+    occupancy::Cell,
+    persistence,
+    rules::{
+        Direction, GameOverReason, GameState, TickEvent,
+    },
+    settings::GameSpeed,
+};
+
+const AUDIO_FILE: &str = "audio.ron";
+const CROSSFADE: f32 = 1.5;
+const TENSION_FADE: f32 = 0.5;
+const LENGTH_FOR_FULL_TEMPO: f32 = 60.0;
+const MAX_LENGTH_SPEEDUP: f32 = 0.2;
+/// Free cells a snake wants beyond its own length
+/// before it stops feeling boxed in
+const ROOM_TO_BREATHE: usize = 8;
+
+/// The menu and gameplay tracks loop in channels of their
+/// own and are crossfaded, see `adapt_music` for the rest.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AudioSettings::load())
+            .init_resource::<Channels>()
+            .init_resource::<MusicFade>()
+            .init_resource::<AdaptiveMusic>()
+            .add_event::<Sfx>()
+            .add_startup_system(start_music)
+            .add_system(save_audio_settings)
+            .add_system(crossfade_music.label("crossfade"))
+            .add_system(adapt_music.after("crossfade"))
+            .add_system(play_stinger)
+            .add_system(apply_sfx_volume)
+            .add_system(tick_sfx)
+            .add_system(turn_sfx)
+            .add_system(new_best_sfx)
+            .add_system(play_sfx);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Volume {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl Volume {
+    pub const ALL: [Volume; 3] =
+        [Volume::Master, Volume::Music, Volume::Sfx];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Volume::Master => "volume.master",
+            Volume::Music => "volume.music",
+            Volume::Sfx => "volume.sfx",
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub muted: bool,
+    /// Sounds that stand in for looking at the board,
+    /// see `guidance`
+    #[serde(default)]
+    pub guidance: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.0,
+            music: 0.6,
+            sfx: 0.8,
+            muted: false,
+            guidance: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn load() -> Self {
+        persistence::load(AUDIO_FILE)
+    }
+
+    pub fn save(&self) {
+        persistence::save(AUDIO_FILE, self);
+    }
+
+    pub fn get(&self, volume: Volume) -> f32 {
+        match volume {
+            Volume::Master => self.master,
+            Volume::Music => self.music,
+            Volume::Sfx => self.sfx,
+        }
+    }
+
+    pub fn set(&mut self, volume: Volume, value: f32) {
+        let slot = match volume {
+            Volume::Master => &mut self.master,
+            Volume::Music => &mut self.music,
+            Volume::Sfx => &mut self.sfx,
+        };
+        *slot = value.clamp(0.0, 1.0);
+    }
+
+    pub fn adjust(&mut self, volume: Volume, by: f32) {
+        self.set(volume, self.get(volume) + by);
+    }
+
+    /// What the music channels play at, with master
+    /// and mute applied.
+    pub fn music_volume(&self) -> f32 {
+        self.effective(self.music)
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.effective(self.sfx)
+    }
+
+    fn effective(&self, volume: f32) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master * volume
+        }
+    }
+}
+
+fn save_audio_settings(settings: Res<AudioSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
+
+pub struct Channels {
+    pub menu: AudioChannel,
+    pub game: AudioChannel,
+    /// layered over `game`, in step with it
+    pub tension: AudioChannel,
+    /// one-off musical hits, at music volume
+    pub stinger: AudioChannel,
+    pub sfx: AudioChannel,
+    /// the food beacon of audio guidance, panned
+    /// towards the food
+    pub beacon: AudioChannel,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Channels {
+            menu: AudioChannel::new(
+                "menu_music".to_string(),
+            ),
+            game: AudioChannel::new(
+                "game_music".to_string(),
+            ),
+            tension: AudioChannel::new(
+                "tension".to_string(),
+            ),
+            stinger: AudioChannel::new(
+                "stinger".to_string(),
+            ),
+            sfx: AudioChannel::new("sfx".to_string()),
+            beacon: AudioChannel::new("beacon".to_string()),
+        }
+    }
+}
+
+pub struct MusicFade {
+    pub menu: f32,
+    pub game: f32,
+}
+
+impl Default for MusicFade {
+    fn default() -> Self {
+        MusicFade {
+            menu: 1.0,
+            game: 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AdaptiveMusic {
+    pub tension: f32,
+    pub tense: bool,
+    pub rate: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sfx {
+    Apple,
+    GameOver,
+    Turn,
+    Win,
+    NewBest,
+    WallAhead,
+    BodyAhead,
+    /// A menu item was pointed at
+    Focus,
+}
+
+impl Sfx {
+    fn source(
+        &self,
+        sounds: &AudioAssets,
+    ) -> Handle<AudioSource> {
+        match self {
+            Sfx::Apple => sounds.apple.clone(),
+            Sfx::GameOver => sounds.gameover.clone(),
+            Sfx::Turn => sounds.turn.clone(),
+            Sfx::Win => sounds.win.clone(),
+            Sfx::NewBest => sounds.new_best.clone(),
+            Sfx::WallAhead => sounds.wall_ahead.clone(),
+            Sfx::BodyAhead => sounds.body_ahead.clone(),
+            Sfx::Focus => sounds.focus.clone(),
+        }
+    }
+}
+
+/// Both tracks loop for as long as the game runs, the
+/// crossfade only moves their volumes. The tension
+/// layer is as long as the gameplay track and starts
+/// with it, so the two stay together.
+fn start_music(
+    audio: Res<Audio>,
+    sounds: Res<AudioAssets>,
+    channels: Res<Channels>,
+) {
+    audio.play_looped_in_channel(
+        sounds.menu_music.clone(),
+        &channels.menu,
+    );
+    audio.play_looped_in_channel(
+        sounds.game_music.clone(),
+        &channels.game,
+    );
+    audio.play_looped_in_channel(
+        sounds.tension.clone(),
+        &channels.tension,
+    );
+    audio.set_volume_in_channel(0.0, &channels.tension);
+}
+
+fn crossfade_music(
+    time: Res<Time>,
+    run_state: Res<State<RunState>>,
+    settings: Res<AudioSettings>,
+    audio: Res<Audio>,
+    channels: Res<Channels>,
+    mut fade: ResMut<MusicFade>,
+) {
+    let step = time.delta_seconds() / CROSSFADE;
+    let step = if *run_state.current() == RunState::Playing
+    {
+        step
+    } else {
+        -step
+    };
+    let menu = (fade.menu - step).clamp(0.0, 1.0);
+    let game = (fade.game + step).clamp(0.0, 1.0);
+    if menu == fade.menu
+        && game == fade.game
+        && !settings.is_changed()
+    {
+        return;
+    }
+    fade.menu = menu;
+    fade.game = game;
+    let volume = settings.music_volume();
+    audio.set_volume_in_channel(
+        fade.menu * volume,
+        &channels.menu,
+    );
+    audio.set_volume_in_channel(
+        fade.game * volume,
+        &channels.game,
+    );
+}
+
+/// Speeds the gameplay track up with faster games and
+/// longer snakes, and fades the tension layer in while
+/// the local snake is hugging a wall or boxed in.
+fn adapt_music(
+    time: Res<Time>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    settings: Res<AudioSettings>,
+    fade: Res<MusicFade>,
+    audio: Res<Audio>,
+    channels: Res<Channels>,
+    mut music: ResMut<AdaptiveMusic>,
+) {
+    if state.is_changed() {
+        music.tense = state.snakes.get(local.0).map_or(
+            false,
+            |snake| {
+                snake.is_alive()
+                    && is_tense(&state, local.0)
+            },
+        );
+        let rate = tempo(&state, local.0);
+        if (rate - music.rate).abs() > 0.01 {
+            music.rate = rate;
+            audio.set_playback_rate_in_channel(
+                rate,
+                &channels.game,
+            );
+            audio.set_playback_rate_in_channel(
+                rate,
+                &channels.tension,
+            );
+        }
+    }
+
+    let step = time.delta_seconds() / TENSION_FADE;
+    let target = if music.tense { 1.0 } else { 0.0 };
+    let tension = if music.tension < target {
+        (music.tension + step).min(target)
+    } else {
+        (music.tension - step).max(target)
+    };
+    if tension == music.tension
+        && !fade.is_changed()
+        && !settings.is_changed()
+    {
+        return;
+    }
+    music.tension = tension;
+    audio.set_volume_in_channel(
+        tension * fade.game * settings.music_volume(),
+        &channels.tension,
+    );
+}
+
+fn tempo(state: &GameState, snake: usize) -> f32 {
+    let regular =
+        GameSpeed::REGULAR.tick_duration().as_secs_f32();
+    let tick =
+        state.rules.speed.tick_duration().as_secs_f32();
+    let speed = (regular / tick).sqrt();
+    let length = state
+        .snakes
+        .get(snake)
+        .map_or(0, |snake| snake.body.segments.len());
+    let growth = (length as f32 / LENGTH_FOR_FULL_TEMPO)
+        .min(1.0)
+        * MAX_LENGTH_SPEEDUP;
+    speed * (1.0 + growth)
+}
+
+fn is_tense(state: &GameState, snake: usize) -> bool {
+    let snake = &state.snakes[snake];
+    let head = snake.head();
+    let by_wall = Direction::ALL.iter().any(|direction| {
+        match direction.step(head, state.rules.board_size) {
+            Some(next) => {
+                state.occupant(next) == Cell::Wall
+            }
+            None => true,
+        }
+    });
+    let room = snake.body.segments.len() + ROOM_TO_BREATHE;
+    by_wall || state.open_area(head, room) < room
+}
+
+fn play_stinger(
+    mut events: EventReader<NewBestEvent>,
+    audio: Res<Audio>,
+    sounds: Res<AudioAssets>,
+    settings: Res<AudioSettings>,
+    channels: Res<Channels>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    audio.set_volume_in_channel(
+        settings.music_volume(),
+        &channels.stinger,
+    );
+    audio.play_in_channel(
+        sounds.stinger.clone(),
+        &channels.stinger,
+    );
+}
+
+fn apply_sfx_volume(
+    settings: Res<AudioSettings>,
+    audio: Res<Audio>,
+    channels: Res<Channels>,
+) {
+    if settings.is_changed() {
+        audio.set_volume_in_channel(
+            settings.sfx_volume(),
+            &channels.sfx,
+        );
+    }
+}
+
+fn play_sfx(
+    mut events: EventReader<Sfx>,
+    audio: Res<Audio>,
+    sounds: Res<AudioAssets>,
+    channels: Res<Channels>,
+) {
+    for sfx in events.iter() {
+        audio.play_in_channel(
+            sfx.source(&sounds),
+            &channels.sfx,
+        );
+    }
+}
+
+fn tick_sfx(
+    mut tick_events: EventReader<TickEvent>,
+    local: Res<LocalPlayer>,
+    mut sfx: EventWriter<Sfx>,
+) {
+    for event in tick_events.iter() {
+        match event {
+            TickEvent::Ate { snake, .. }
+                if *snake == local.0 =>
+            {
+                sfx.send(Sfx::Apple)
+            }
+            TickEvent::GameOver { snake, reason }
+                if *snake == local.0 =>
+            {
+                sfx.send(match reason {
+                    GameOverReason::Win => Sfx::Win,
+                    _ => Sfx::GameOver,
+                })
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Ticks for every turn the local snake actually
+/// makes, however it was steered.
+fn turn_sfx(
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    mut heading: Local<Option<Direction>>,
+    mut sfx: EventWriter<Sfx>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let direction = match state.snakes.get(local.0) {
+        Some(snake) => snake.direction,
+        None => return,
+    };
+    let previous = heading.replace(direction);
+    // a new game starts heading its own way
+    if state.tick > 0
+        && previous
+            .map_or(false, |previous| previous != direction)
+    {
+        sfx.send(Sfx::Turn);
+    }
+}
+
+/// The stinger marks a new best while music is
+/// playing, the cue only stands in for it when the
+/// music is muted.
+fn new_best_sfx(
+    mut events: EventReader<NewBestEvent>,
+    settings: Res<AudioSettings>,
+    mut sfx: EventWriter<Sfx>,
+) {
+    for _ in events.iter() {
+        if settings.music_volume() == 0.0 {
+            sfx.send(Sfx::NewBest);
+        }
+    }
+}
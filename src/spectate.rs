@@ -0,0 +1,348 @@
+use std::{
+    io::{ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, SyncSender, TrySendError},
+    thread,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+use tungstenite::{Message, WebSocket};
+
+use crate::{
+    board::Position,
+    common::RunState,
+    rules::{Direction, GameState, TickEvent},
+    scoring::Timer,
+};
+
+pub const DEFAULT_SPECTATE_ADDR: &str = "127.0.0.1:7879";
+/// How long a new client has to start a WebSocket
+/// handshake before it's treated as plain TCP
+const HANDSHAKE_WAIT: Duration = Duration::from_millis(300);
+/// Messages a spectator may fall behind by before it's
+/// dropped
+const BACKLOG: usize = 256;
+
+/// Streams the game to read-only spectators as JSON, one
+/// message per line over TCP or per text message over a
+/// WebSocket on the same port. Clients get a `snapshot` of
+/// the whole game, then a `delta` per tick and a new
+/// `snapshot` for every new game. Every message carries
+/// the `game` it belongs to, which counts up from 0.
+pub struct SpectatePlugin;
+
+impl Plugin for SpectatePlugin {
+    fn build(&self, app: &mut App) {
+        let addr = match spectate_addr_from_args() {
+            Some(addr) => addr,
+            None => return,
+        };
+        match SpectatorServer::bind(&addr) {
+            Ok(server) => {
+                info!(%addr, "spectator server listening");
+                app.insert_resource(server)
+                    .add_system_set(
+                        SystemSet::on_enter(
+                            RunState::Playing,
+                        )
+                        .with_system(new_game),
+                    )
+                    // once a new game has been set up
+                    .add_system_to_stage(
+                        CoreStage::PostUpdate,
+                        broadcast_game,
+                    );
+            }
+            Err(error) => {
+                error!(
+                    %addr,
+                    %error,
+                    "failed to start spectator server"
+                );
+            }
+        }
+    }
+}
+
+fn spectate_addr_from_args() -> Option<String> {
+    let mut args = std::env::args()
+        .skip_while(|arg| arg != "--spectate");
+    args.next()?;
+    Some(
+        args.next()
+            .filter(|addr| !addr.starts_with("--"))
+            .unwrap_or_else(|| {
+                DEFAULT_SPECTATE_ADDR.to_string()
+            }),
+    )
+}
+
+#[derive(Serialize)]
+struct SnakeSnapshot {
+    segments: Vec<Position>,
+    direction: Direction,
+    score: u32,
+    alive: bool,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SpectatorMessage<'a> {
+    Snapshot {
+        game: u64,
+        tick: u64,
+        board_size: u16,
+        snakes: Vec<SnakeSnapshot>,
+        food: &'a [Position],
+        walls: &'a [Position],
+        time_ms: u128,
+        playing: bool,
+    },
+    Delta {
+        game: u64,
+        tick: u64,
+        events: &'a [TickEvent],
+        scores: Vec<u32>,
+        time_ms: u128,
+        playing: bool,
+    },
+}
+
+/// Each spectator gets its own writer thread, so a slow
+/// client can never stall the game.
+pub struct SpectatorServer {
+    listener: TcpListener,
+    clients: Vec<SyncSender<String>>,
+    last_tick: Option<u64>,
+    game: u64,
+    new_game: bool,
+}
+
+impl SpectatorServer {
+    fn bind(addr: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(SpectatorServer {
+            listener,
+            clients: vec![],
+            last_tick: None,
+            game: 0,
+            new_game: false,
+        })
+    }
+
+    fn accept(&mut self) -> Vec<SyncSender<String>> {
+        let mut new_clients = vec![];
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    info!(%addr, "spectator connected");
+                    new_clients.push(spawn_writer(stream));
+                }
+                Err(error)
+                    if error.kind()
+                        == ErrorKind::WouldBlock =>
+                {
+                    break
+                }
+                Err(error) => {
+                    warn!(
+                        %error,
+                        "failed to accept spectator"
+                    );
+                    break;
+                }
+            }
+        }
+        new_clients
+    }
+
+    fn broadcast(&mut self, line: &str) {
+        self.clients.retain(|client| {
+            send(client, line.to_string())
+        });
+    }
+}
+
+/// Whether `client` is still listening. Writer threads
+/// hang up when their client disconnects, and clients
+/// that fall `BACKLOG` messages behind are dropped.
+fn send(client: &SyncSender<String>, line: String) -> bool {
+    match client.try_send(line) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            warn!("dropping a spectator that fell behind");
+            false
+        }
+        Err(TrySendError::Disconnected(_)) => false,
+    }
+}
+
+fn spawn_writer(stream: TcpStream) -> SyncSender<String> {
+    let (sender, receiver) =
+        mpsc::sync_channel::<String>(BACKLOG);
+    thread::spawn(move || {
+        let mut client = match Client::open(stream) {
+            Some(client) => client,
+            None => return,
+        };
+        for line in receiver {
+            if client.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    sender
+}
+
+enum Client {
+    Tcp(TcpStream),
+    WebSocket(WebSocket<TcpStream>),
+}
+
+impl Client {
+    /// Browsers open with an HTTP upgrade request,
+    /// plain TCP clients usually don't say anything.
+    fn open(stream: TcpStream) -> Option<Client> {
+        stream.set_nonblocking(false).ok()?;
+        stream
+            .set_read_timeout(Some(HANDSHAKE_WAIT))
+            .ok()?;
+        let mut start = [0; 4];
+        let websocket = matches!(
+            stream.peek(&mut start),
+            Ok(4) if &start == b"GET "
+        );
+        stream.set_read_timeout(None).ok()?;
+        if !websocket {
+            return Some(Client::Tcp(stream));
+        }
+        match tungstenite::accept(stream) {
+            Ok(socket) => Some(Client::WebSocket(socket)),
+            Err(error) => {
+                warn!(%error, "spectator handshake failed");
+                None
+            }
+        }
+    }
+
+    fn send(&mut self, line: String) -> anyhow::Result<()> {
+        match self {
+            Client::Tcp(stream) => {
+                stream.write_all(line.as_bytes())?;
+                stream.write_all(b"\n")?;
+            }
+            Client::WebSocket(socket) => {
+                socket
+                    .write_message(Message::Text(line))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_line(message: &SpectatorMessage) -> String {
+    serde_json::to_string(message).unwrap()
+}
+
+fn snapshot(
+    game: u64,
+    state: &GameState,
+    time_ms: u128,
+    playing: bool,
+) -> String {
+    to_line(&SpectatorMessage::Snapshot {
+        game,
+        tick: state.tick,
+        board_size: state.rules.board_size,
+        snakes: state
+            .snakes
+            .iter()
+            .map(|snake| SnakeSnapshot {
+                segments: snake
+                    .body
+                    .segments
+                    .iter()
+                    .copied()
+                    .collect(),
+                direction: snake.direction,
+                score: snake.score,
+                alive: snake.is_alive(),
+            })
+            .collect(),
+        food: &state.food,
+        walls: &state.walls,
+        time_ms,
+        playing,
+    })
+}
+
+fn new_game(mut server: ResMut<SpectatorServer>) {
+    server.game += 1;
+    server.new_game = true;
+}
+
+fn broadcast_game(
+    mut server: ResMut<SpectatorServer>,
+    state: Res<GameState>,
+    timer: Res<Timer>,
+    run_state: Res<State<RunState>>,
+    mut tick_events: EventReader<TickEvent>,
+) {
+    let playing = *run_state.current() == RunState::Playing;
+    let time_ms =
+        match (playing, timer.elapsed(), timer.runtime) {
+            (true, Some(elapsed), _) => elapsed,
+            (false, _, Some(runtime)) => runtime,
+            _ => Default::default(),
+        }
+        .as_millis();
+    let events = tick_events
+        .iter()
+        .cloned()
+        .collect::<Vec<TickEvent>>();
+
+    let new_clients = server.accept();
+
+    let game = server.game;
+    match server.last_tick {
+        Some(_) if server.new_game => {
+            let line =
+                snapshot(game, &state, time_ms, playing);
+            server.broadcast(&line);
+        }
+        Some(last)
+            if state.tick > last || !events.is_empty() =>
+        {
+            let line = to_line(&SpectatorMessage::Delta {
+                game,
+                tick: state.tick,
+                events: &events,
+                scores: state
+                    .snakes
+                    .iter()
+                    .map(|snake| snake.score)
+                    .collect(),
+                time_ms,
+                playing,
+            });
+            server.broadcast(&line);
+        }
+        _ => {}
+    }
+    server.last_tick = Some(state.tick);
+    server.new_game = false;
+
+    // after the broadcast, as their snapshot already has
+    // this frame's delta in it
+    if !new_clients.is_empty() {
+        let line = snapshot(game, &state, time_ms, playing);
+        for client in new_clients {
+            if send(&client, line.clone()) {
+                server.clients.push(client);
+            }
+        }
+    }
+}
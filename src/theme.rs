@@ -0,0 +1,453 @@
+use anyhow::ensure;
+use bevy::{
+    asset::{
+        AssetLoader, BoxedFuture, LoadContext, LoadedAsset,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{
+        Extent3d, TextureDimension, TextureFormat,
+    },
+    sprite::Rect,
+};
+use kayak_ui::{bevy::FontMapping, font::KayakFont};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    board::{Board, Wall},
+    colors::{
+        Palette, COLOR_SAFE, HIGH_CONTRAST, MATERIALS,
+    },
+    food::Food,
+    settings::{Accessibility, GameSettings},
+    ui::FontSpec,
+};
+
+const THEME_FOLDER: &str = "themes";
+
+/// Themes are read from `.theme.ron` files and applied
+/// again whenever their file changes on disk.
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Theme>()
+            .init_asset_loader::<ThemeLoader>()
+            .init_resource::<Themes>()
+            .init_resource::<ThemeNames>()
+            .init_resource::<ActiveTheme>()
+            .add_system(list_themes)
+            .add_system(apply_theme.label("theme"))
+            .add_system(restyle_board.after("theme"))
+            .add_system(apply_theme_fonts.after("theme"));
+    }
+}
+
+/// A theme as it's written in its file. Image and
+/// font fields are paths under `assets`.
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    TypeUuid,
+)]
+#[uuid = "6dc0414a-08d1-4ba1-bed8-72db75584741"]
+pub struct Theme {
+    pub name: String,
+    /// Around the board
+    pub background: Color,
+    /// Under the grass, seen while it loads
+    pub board: Color,
+    pub wall: Color,
+    /// Particles when an apple is eaten
+    pub food: Color,
+    pub grass: TileSet,
+    pub apple: String,
+    pub panel: String,
+    pub button: String,
+    pub button_pressed: String,
+    pub fonts: ThemeFonts,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct TileSet {
+    pub path: String,
+    pub tile_size: f32,
+    pub columns: usize,
+    pub rows: usize,
+    /// How often each tile is picked compared to the
+    /// others, one per tile
+    pub weights: Vec<u32>,
+}
+
+#[derive(
+    Debug, Clone, PartialEq, Serialize, Deserialize,
+)]
+pub struct ThemeFonts {
+    /// A `.kayak_font` for the menus
+    pub menu: String,
+    /// A `.ttf` for the scoreboard and popups
+    pub text: String,
+}
+
+/// The look the game had before themes, used until
+/// the theme files are loaded.
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: "Meadow".to_string(),
+            background: MATERIALS.background,
+            board: MATERIALS.board,
+            wall: MATERIALS.wall,
+            food: MATERIALS.food,
+            grass: TileSet {
+                path: "grass.png".to_string(),
+                tile_size: 16.0,
+                columns: 3,
+                rows: 1,
+                weights: vec![3, 3, 1],
+            },
+            apple: "apple.png".to_string(),
+            panel: "green_panel.png".to_string(),
+            button: "blue_button09.png".to_string(),
+            button_pressed: "blue_button10.png".to_string(),
+            fonts: ThemeFonts {
+                menu: "roboto.kayak_font".to_string(),
+                text: "fonts/FiraSans-Bold.ttf".to_string(),
+            },
+        }
+    }
+}
+
+impl Theme {
+    pub fn palette(&self) -> Palette {
+        Palette {
+            background: self.background,
+            board: self.board,
+            wall: self.wall,
+            food: self.food,
+            grid: [
+                MATERIALS.tile_placeholder,
+                MATERIALS.tile_placeholder_dark,
+            ],
+            outline: None,
+            food_cue: None,
+            snake_tint: Color::WHITE,
+            food_tint: Color::WHITE,
+        }
+    }
+
+    /// Catches themes that would panic once they're in
+    /// use, so they fail to load instead.
+    fn validate(&self) -> anyhow::Result<()> {
+        let grass = &self.grass;
+        ensure!(
+            grass.columns * grass.rows > 0,
+            "grass needs at least one tile"
+        );
+        ensure!(
+            grass.weights.len()
+                == grass.columns * grass.rows,
+            "grass needs one weight per tile"
+        );
+        ensure!(
+            grass.weights.iter().any(|weight| *weight > 0),
+            "grass needs a tile with a weight above 0"
+        );
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let theme: Theme = ron::de::from_bytes(bytes)?;
+            theme.validate()?;
+            load_context
+                .set_default_asset(LoadedAsset::new(theme));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+pub struct Themes(pub Vec<HandleUntyped>);
+
+impl FromWorld for Themes {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server =
+            world.get_resource::<AssetServer>().unwrap();
+        match asset_server.load_folder(THEME_FOLDER) {
+            Ok(handles) => Themes(handles),
+            Err(error) => {
+                warn!(%error, "failed to load themes");
+                Themes(vec![])
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ThemeNames(pub Vec<String>);
+
+/// The theme in use, with its assets loaded and the
+/// accessibility settings applied. The board is drawn
+/// with `palette` rather than the theme's own colors.
+#[derive(Clone, PartialEq)]
+pub struct ActiveTheme {
+    pub source: Theme,
+    pub accessibility: Accessibility,
+    pub palette: Palette,
+    /// `grass` is a two tile atlas in the palette's grid
+    /// colors, checkered by position. Always on for the
+    /// accessibility presets.
+    pub plain_grid: bool,
+    pub grass: Handle<TextureAtlas>,
+    pub apple: Handle<Image>,
+    pub panel: Handle<Image>,
+    pub button: Handle<Image>,
+    pub button_pressed: Handle<Image>,
+    pub menu_font: Handle<KayakFont>,
+    pub text_font: Handle<Font>,
+}
+
+impl ActiveTheme {
+    fn new(
+        theme: &Theme,
+        accessibility: Accessibility,
+        plain_grid: bool,
+        asset_server: &AssetServer,
+        atlases: &mut Assets<TextureAtlas>,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let palette = match accessibility {
+            Accessibility::Standard => theme.palette(),
+            Accessibility::ColorSafe => COLOR_SAFE,
+            Accessibility::HighContrast => HIGH_CONTRAST,
+        };
+        let plain_grid = plain_grid
+            || accessibility != Accessibility::Standard;
+        let grass = &theme.grass;
+        let grass = if plain_grid {
+            grid_atlas(palette.grid, images)
+        } else {
+            TextureAtlas::from_grid(
+                asset_server.load(grass.path.as_str()),
+                Vec2::splat(grass.tile_size),
+                grass.columns,
+                grass.rows,
+            )
+        };
+        ActiveTheme {
+            source: theme.clone(),
+            accessibility,
+            palette,
+            plain_grid,
+            grass: atlases.add(grass),
+            apple: asset_server.load(theme.apple.as_str()),
+            panel: asset_server.load(theme.panel.as_str()),
+            button: asset_server
+                .load(theme.button.as_str()),
+            button_pressed: asset_server
+                .load(theme.button_pressed.as_str()),
+            menu_font: asset_server
+                .load(theme.fonts.menu.as_str()),
+            text_font: asset_server
+                .load(theme.fonts.text.as_str()),
+        }
+    }
+}
+
+impl FromWorld for ActiveTheme {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world
+            .get_resource::<AssetServer>()
+            .unwrap()
+            .clone();
+        world.resource_scope(
+            |world, mut images: Mut<Assets<Image>>| {
+                let mut atlases = world
+                    .get_resource_mut::<Assets<TextureAtlas>>()
+                    .unwrap();
+                ActiveTheme::new(
+                    &Theme::default(),
+                    Accessibility::Standard,
+                    false,
+                    &asset_server,
+                    &mut atlases,
+                    &mut images,
+                )
+            },
+        )
+    }
+}
+
+/// One pixel per shade, each tile sampling the middle
+/// of its pixel so the cells come out flat.
+fn grid_atlas(
+    shades: [Color; 2],
+    images: &mut Assets<Image>,
+) -> TextureAtlas {
+    let data = shades
+        .iter()
+        .flat_map(|shade| shade.as_rgba_f32())
+        .map(|channel| (channel * 255.0).round() as u8)
+        .collect();
+    let image = images.add(Image::new(
+        Extent3d {
+            width: 2,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+    let mut atlas =
+        TextureAtlas::new_empty(image, Vec2::new(2.0, 1.0));
+    for x in [0.5, 1.5] {
+        let center = Vec2::new(x, 0.5);
+        atlas.add_texture(Rect {
+            min: center,
+            max: center,
+        });
+    }
+    atlas
+}
+
+fn list_themes(
+    mut events: EventReader<AssetEvent<Theme>>,
+    themes: Res<Assets<Theme>>,
+    mut names: ResMut<ThemeNames>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    let mut list = themes
+        .iter()
+        .map(|(_, theme)| theme.name.clone())
+        .collect::<Vec<String>>();
+    list.sort();
+    list.dedup();
+    if names.0 != list {
+        names.0 = list;
+    }
+}
+
+/// A theme that isn't loaded keeps the current one.
+fn apply_theme(
+    settings: Res<GameSettings>,
+    themes: Res<Assets<Theme>>,
+    mut events: EventReader<AssetEvent<Theme>>,
+    asset_server: Res<AssetServer>,
+    mut atlases: ResMut<Assets<TextureAtlas>>,
+    mut images: ResMut<Assets<Image>>,
+    mut active: ResMut<ActiveTheme>,
+) {
+    let loaded = events.iter().count() > 0;
+    if !loaded && !settings.is_changed() {
+        return;
+    }
+    let theme = themes
+        .iter()
+        .map(|(_, theme)| theme)
+        .find(|theme| theme.name == settings.theme)
+        .unwrap_or(&active.source)
+        .clone();
+    if active.source != theme
+        || active.accessibility != settings.accessibility
+        || active.plain_grid
+            != (settings.plain_grid
+                || settings.accessibility
+                    != Accessibility::Standard)
+    {
+        *active = ActiveTheme::new(
+            &theme,
+            settings.accessibility,
+            settings.plain_grid,
+            &asset_server,
+            &mut atlases,
+            &mut images,
+        );
+    }
+}
+
+/// Recolors the board and walls and swaps the apples
+/// already out for the new theme's. Snakes are redrawn
+/// by `render_snake_segments`.
+fn restyle_board(
+    theme: Res<ActiveTheme>,
+    mut clear_color: ResMut<ClearColor>,
+    mut boards: Query<
+        &mut Sprite,
+        (With<Board>, Without<Wall>, Without<Food>),
+    >,
+    mut walls: Query<
+        &mut Sprite,
+        (With<Wall>, Without<Board>, Without<Food>),
+    >,
+    mut apples: Query<
+        (&mut Handle<Image>, &mut Sprite),
+        (With<Food>, Without<Board>, Without<Wall>),
+    >,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    clear_color.0 = theme.palette.background;
+    for mut sprite in boards.iter_mut() {
+        sprite.color = theme.palette.board;
+    }
+    for mut sprite in walls.iter_mut() {
+        sprite.color = theme.palette.wall;
+    }
+    for (mut apple, mut sprite) in apples.iter_mut() {
+        *apple = theme.apple.clone();
+        sprite.color = theme.palette.food_tint;
+    }
+}
+
+/// Moves text already on screen over to the theme's
+/// font. The menus pick theirs up from `FontMapping`
+/// when they're next drawn.
+fn apply_theme_fonts(
+    theme: Res<ActiveTheme>,
+    mut font_spec: ResMut<FontSpec>,
+    mut font_mapping: ResMut<FontMapping>,
+    mut texts: Query<&mut Text>,
+) {
+    if !theme.is_changed() || theme.is_added() {
+        return;
+    }
+    font_mapping.set_default(theme.menu_font.clone());
+    if font_spec.family == theme.text_font {
+        return;
+    }
+    let old = std::mem::replace(
+        &mut font_spec.family,
+        theme.text_font.clone(),
+    );
+    for mut text in texts.iter_mut() {
+        for section in text.sections.iter_mut() {
+            if section.style.font == old {
+                section.style.font =
+                    theme.text_font.clone();
+            }
+        }
+    }
+}
@@ -1,14 +1,16 @@
-use crate::{colors::MATERIALS, common::Game};
+use crate::{
+    common::Paused, locale::Localization,
+    theme::ActiveTheme,
+};
 use bevy::prelude::*;
 
+mod hud;
 mod kayak;
+use hud::*;
 use kayak::*;
 
 #[derive(Component)]
-pub struct ScoreDisplay;
-
-#[derive(Component)]
-pub struct BestScoreDisplay;
+pub struct PausedDisplay;
 
 pub struct GameUiPlugin;
 
@@ -17,10 +19,23 @@ impl Plugin for GameUiPlugin {
         app.init_resource::<FontSpec>()
             .add_startup_system(setup_ui)
             .add_startup_system(new_game_ui_kayak)
-            .add_system(scoreboard)
+            .add_system(spawn_hud)
+            .add_system(update_hud.after("localize"))
+            .add_system(paused_display)
             .add_system(bind_gamestate)
             .add_system(bind_gamesettings)
-            .add_system(bind_speedruns);
+            .add_system(bind_speedruns)
+            .add_system(bind_daily_history)
+            .add_system(bind_lobby_status)
+            .add_system(bind_pad_assignments)
+            .add_system(bind_gamepad_config)
+            .add_system(bind_binding_profiles)
+            .add_system(bind_binding_capture)
+            .add_system(bind_audio_settings)
+            .add_system(bind_active_theme)
+            .add_system(bind_theme_names)
+            .add_system(bind_skins)
+            .add_system(bind_localization);
     }
 }
 
@@ -28,14 +43,14 @@ pub struct FontSpec {
     pub family: Handle<Font>,
 }
 
+/// Starts out with the font of the `ActiveTheme`, and
+/// follows it when the theme changes.
 impl FromWorld for FontSpec {
     fn from_world(world: &mut World) -> Self {
-        let asset_server = world
-            .get_resource_mut::<AssetServer>()
-            .unwrap();
+        let theme =
+            world.get_resource::<ActiveTheme>().unwrap();
         FontSpec {
-            family: asset_server
-                .load("fonts/FiraSans-Bold.ttf"),
+            family: theme.text_font.clone(),
         }
     }
 }
@@ -47,168 +62,43 @@ fn setup_ui(
     commands.spawn_bundle(UiCameraBundle::default());
 
     commands
-        .spawn_bundle(NodeBundle {
+        .spawn_bundle(TextBundle {
             style: Style {
-                size: Size::new(Val::Px(30.0*20.0), Val::Px(50.0)),
-                align_items: AlignItems::FlexStart,
-                margin: Rect { left: Val::Auto, right: Val::Auto, top: Val::Px(10.0), bottom: Val::Auto },
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Percent(45.0),
+                    top: Val::Percent(45.0),
+                    ..Default::default()
+                },
                 ..Default::default()
             },
-            color: UiColor(MATERIALS.none),
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: font_spec.family.clone(),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+                TextAlignment::default(),
+            ),
             ..Default::default()
         })
-        .with_children(|parent| {
-            parent.spawn_bundle(TextBundle {
-                text: Text::with_section(
-                    "Snake",
-                    TextStyle {
-                        font: font_spec.family.clone(),
-                        font_size: 40.0,
-                        color: Color::WHITE,
-                    },
-                    TextAlignment::default(),
-                ),
-                ..Default::default()
-            });
-
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        justify_content: JustifyContent::Center,
-                        size: Size::new(Val::Percent(100.0), Val::Auto),
-                        ..Default::default()
-                    },
-                    color: UiColor(MATERIALS.none),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    // scorebox
-                    parent
-                        .spawn_bundle(NodeBundle {
-                            style: Style {
-                                flex_direction: FlexDirection::ColumnReverse,
-                                align_items: AlignItems::Center,
-                                margin: Rect {
-                                    left: Val::Px(20.0),
-                                    right: Val::Px(20.0),
-                                    top: Val::Px(0.0),
-                                    bottom: Val::Px(0.0),
-                                },
-                                padding: Rect::all(Val::Px(10.0)),
-                                ..Default::default()
-                            },
-                            color: UiColor(MATERIALS.none),
-                            ..Default::default()
-                        })
-                        .with_children(|parent| {
-                            parent.spawn_bundle(TextBundle {
-                                text: Text::with_section(
-                                    "Score",
-                                    TextStyle {
-                                        font: font_spec.family.clone(),
-                                        font_size: 15.0,
-                                        color: Color::WHITE,
-                                    },
-                                    TextAlignment {
-                                        vertical: VerticalAlign::Center,
-                                        horizontal: HorizontalAlign::Center,
-                                    },
-                                ),
-                                ..Default::default()
-                            });
-                            parent
-                                .spawn_bundle(TextBundle {
-                                    text: Text::with_section(
-                                        "<score>",
-                                        TextStyle {
-                                            font: font_spec.family.clone(),
-                                            font_size: 20.0,
-                                            color: Color::WHITE,
-                                        },
-                                        TextAlignment {
-                                            vertical: VerticalAlign::Center,
-                                            horizontal: HorizontalAlign::Center,
-                                        },
-                                    ),
-                                    ..Default::default()
-                                })
-                                .insert(ScoreDisplay);
-                        });
-                    // end scorebox
-                    // best scorebox
-                    parent
-                        .spawn_bundle(NodeBundle {
-                            style: Style {
-                                flex_direction: FlexDirection::ColumnReverse,
-                                align_items: AlignItems::Center,
-                                padding: Rect::all(Val::Px(10.0)),
-                                ..Default::default()
-                            },
-                            color: UiColor(MATERIALS.none),
-                            ..Default::default()
-                        })
-                        .with_children(|parent| {
-                            parent.spawn_bundle(TextBundle {
-                                text: Text::with_section(
-                                    "Best",
-                                    TextStyle {
-                                        font: font_spec.family.clone(),
-                                        font_size: 15.0,
-                                        color: Color::WHITE,
-                                    },
-                                    TextAlignment {
-                                        vertical: VerticalAlign::Center,
-                                        horizontal: HorizontalAlign::Center,
-                                    },
-                                ),
-                                ..Default::default()
-                            });
-                            parent
-                                .spawn_bundle(TextBundle {
-                                    text: Text::with_section(
-                                        "<score>",
-                                        TextStyle {
-                                            font: font_spec.family.clone(),
-                                            font_size: 20.0,
-                                            color: Color::WHITE,
-                                        },
-                                        TextAlignment {
-                                            vertical: VerticalAlign::Center,
-                                            horizontal: HorizontalAlign::Center,
-                                        },
-                                    ),
-                                    ..Default::default()
-                                })
-                                .insert(BestScoreDisplay);
-                        });
-                    // end best scorebox
-                });
-        });
+        .insert(PausedDisplay);
 }
 
-fn scoreboard(
-    game: Res<Game>,
-    mut query_score: Query<
-        &mut Text,
-        (
-            With<ScoreDisplay>,
-            Without<BestScoreDisplay>,
-        ),
-    >,
-    mut query_high_score: Query<
-        &mut Text,
-        (
-            With<BestScoreDisplay>,
-            Without<ScoreDisplay>,
-        ),
-    >,
+fn paused_display(
+    paused: Res<Paused>,
+    localization: Res<Localization>,
+    mut query: Query<&mut Text, With<PausedDisplay>>,
 ) {
-    for mut text in query_score.iter_mut() {
-        text.sections[0].value = game.score.to_string();
+    if !paused.is_changed() && !localization.is_changed() {
+        return;
     }
-
-    for mut text in query_high_score.iter_mut() {
-        text.sections[0].value =
-            game.score_best.to_string();
+    for mut text in query.iter_mut() {
+        text.sections[0].value = if paused.0 {
+            localization.get("hud.paused")
+        } else {
+            String::new()
+        };
     }
 }
@@ -0,0 +1,278 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, Actions},
+    colors::MATERIALS,
+    common::{Game, LocalPlayer, Paused, RunState},
+    locale::{Localization, Localized},
+    rules::GameState,
+    scoring::Timer,
+    settings::{
+        GameSettings, HudElement, HudPosition, HudSettings,
+    },
+};
+
+use super::FontSpec;
+
+/// The window height the HUD's text sizes are picked
+/// for. It scales with the window from there.
+const BASE_HEIGHT: f32 = 720.0;
+const MARGIN: f32 = 10.0;
+
+#[derive(Component)]
+pub struct Hud;
+
+#[derive(Component)]
+pub struct HudValue(HudElement);
+
+/// Builds the HUD from the `HudSettings`, and builds it
+/// again when they change or the window's height does,
+/// so its text keeps its size relative to the board.
+pub fn spawn_hud(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    font_spec: Res<FontSpec>,
+    windows: Res<Windows>,
+    huds: Query<Entity, With<Hud>>,
+    mut built: Local<Option<(HudSettings, f32)>>,
+) {
+    let scale = windows
+        .get_primary()
+        .map_or(1.0, |window| window.height() / BASE_HEIGHT)
+        .clamp(0.5, 2.0);
+    let wanted = (settings.hud.clone(), scale);
+    if built.as_ref() == Some(&wanted) {
+        return;
+    }
+    *built = Some(wanted);
+
+    for hud in huds.iter() {
+        commands.entity(hud).despawn_recursive();
+    }
+
+    let hud = &settings.hud;
+    let row = matches!(
+        hud.position,
+        HudPosition::Top | HudPosition::Bottom
+    );
+    // Bevy's UI counts up from the bottom of the
+    // window, so `bottom` is the distance from the top
+    let at_top = matches!(
+        hud.position,
+        HudPosition::Top
+            | HudPosition::TopLeft
+            | HudPosition::TopRight
+    );
+    let edge = Val::Px(MARGIN * scale);
+    let mut position = Rect::default();
+    if at_top {
+        position.bottom = edge;
+    } else {
+        position.top = edge;
+    }
+    match hud.position {
+        HudPosition::TopLeft | HudPosition::BottomLeft => {
+            position.left = edge;
+        }
+        HudPosition::TopRight
+        | HudPosition::BottomRight => {
+            position.right = edge;
+        }
+        HudPosition::Top | HudPosition::Bottom => {
+            position.left = Val::Px(0.0);
+        }
+    }
+
+    let font = font_spec.family.clone();
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position,
+                size: Size::new(
+                    if row {
+                        Val::Percent(100.0)
+                    } else {
+                        Val::Auto
+                    },
+                    Val::Auto,
+                ),
+                flex_direction: if row {
+                    FlexDirection::Row
+                } else {
+                    FlexDirection::ColumnReverse
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: UiColor(MATERIALS.none),
+            ..Default::default()
+        })
+        .insert(Hud)
+        .with_children(|parent| {
+            for element in hud.elements.iter().copied() {
+                spawn_element(
+                    parent, element, &font, scale,
+                );
+            }
+        });
+}
+
+/// The title on its own, anything else as a label over
+/// its value.
+fn spawn_element(
+    parent: &mut ChildBuilder,
+    element: HudElement,
+    font: &Handle<Font>,
+    scale: f32,
+) {
+    let text = |size: f32| TextBundle {
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: font.clone(),
+                font_size: size * scale,
+                color: Color::WHITE,
+            },
+            TextAlignment {
+                vertical: VerticalAlign::Center,
+                horizontal: HorizontalAlign::Center,
+            },
+        ),
+        ..Default::default()
+    };
+
+    if element == HudElement::Title {
+        parent
+            .spawn_bundle(text(40.0))
+            .insert(Localized(element.key()));
+        return;
+    }
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                flex_direction:
+                    FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                padding: Rect::all(Val::Px(MARGIN * scale)),
+                ..Default::default()
+            },
+            color: UiColor(MATERIALS.none),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(text(15.0))
+                .insert(Localized(element.key()));
+            parent
+                .spawn_bundle(text(20.0))
+                .insert(HudValue(element));
+        });
+}
+
+/// Fills in the HUD's values. Text is only touched when
+/// it reads differently, so the layout isn't redone
+/// every frame for a timer that moves in tenths.
+pub fn update_hud(
+    game: Res<Game>,
+    timer: Res<Timer>,
+    run_state: Res<State<RunState>>,
+    state: Res<GameState>,
+    local: Res<LocalPlayer>,
+    paused: Res<Paused>,
+    actions: Res<Actions>,
+    localization: Res<Localization>,
+    mut values: Query<(&HudValue, &mut Text)>,
+) {
+    for (value, mut text) in values.iter_mut() {
+        let shown = match value.0 {
+            HudElement::Title => continue,
+            HudElement::Score => {
+                localization.number(u64::from(game.score))
+            }
+            HudElement::Best => localization
+                .number(u64::from(game.score_best)),
+            HudElement::Timer => {
+                let elapsed = match (
+                    run_state.current(),
+                    timer.elapsed(),
+                ) {
+                    (RunState::Playing, Some(elapsed)) => {
+                        elapsed
+                    }
+                    _ => timer.runtime.unwrap_or_default(),
+                };
+                localization.duration(elapsed)
+            }
+            HudElement::Length => localization.number(
+                state.snakes.get(local.0).map_or(
+                    0,
+                    |snake| {
+                        snake.body.segments.len() as u64
+                    },
+                ),
+            ),
+            HudElement::Speed => {
+                let speed = state.rules.speed;
+                let rate = 1.0
+                    / speed.tick_duration().as_secs_f32();
+                localization.format(
+                    "hud.speed_value",
+                    &[
+                        (
+                            "speed",
+                            &localization.get(speed.key()),
+                        ),
+                        (
+                            "rate",
+                            &localization.decimal(rate, 1),
+                        ),
+                    ],
+                )
+            }
+            HudElement::Effects => {
+                let effects = paused
+                    .0
+                    .then(|| localization.get("hud.paused"))
+                    .into_iter()
+                    .chain(
+                        state.rules.modifiers.iter().map(
+                            |modifier| {
+                                localization
+                                    .get(modifier.key())
+                            },
+                        ),
+                    )
+                    .collect::<Vec<String>>();
+                listed(&localization, effects)
+            }
+            HudElement::Input => listed(
+                &localization,
+                Action::ALL
+                    .into_iter()
+                    .filter(|action| {
+                        actions.pressed(*action)
+                    })
+                    .map(|action| {
+                        localization.get(action.key())
+                    })
+                    .collect(),
+            ),
+        };
+        if text.sections[0].value != shown {
+            text.sections[0].value = shown;
+        }
+    }
+}
+
+fn listed(
+    localization: &Localization,
+    items: Vec<String>,
+) -> String {
+    if items.is_empty() {
+        localization.get("hud.none")
+    } else {
+        items.join(", ")
+    }
+}
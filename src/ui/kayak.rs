@@ -10,19 +10,34 @@ use kayak_ui::{
             Edge, LayoutType, PositionType, Style,
             StyleProp, Units,
         },
-        use_state, widget, Binding, Bound, EventType,
-        Index, MutableBound, OnEvent, VecTracker,
-        WidgetProps,
+        use_state, widget, Binding, Bound, CursorEvent,
+        Event, EventType, Index, KayakContextRef, KeyCode,
+        MutableBound, OnEvent, VecTracker, WidgetProps,
+    },
+    widgets::{
+        App, Element, If, NinePatch, OnChange, Text,
+        TextBox,
     },
-    widgets::{App, Element, If, NinePatch, Text},
 };
 
 use crate::{
-    assets::{FontAssets, ImageAssets},
-    common::RunState,
+    actions::{
+        Action, BindingCapture, BindingProfiles,
+        CaptureMessage,
+    },
+    assets::ImageAssets,
+    common::{GameMode, RunState},
+    daily::{DailyChallenge, DailyDate, DailyHistory},
+    gamepad::{GamepadConfig, PadAssignments},
+    guidance::Announcement,
+    locale::{Languages, Localization},
+    net::{LobbyEvent, LobbySettings, LobbyStatus},
     scoring::Speedruns,
-    settings::GameSettings,
+    settings::{Accessibility, GameSettings, HudElement},
+    skins::Skins,
     snake::SnakeTextureSelection,
+    sound::{AudioSettings, Volume},
+    theme::{ActiveTheme, ThemeNames},
 };
 
 pub fn bind_gamestate(
@@ -56,6 +71,105 @@ pub fn bind_speedruns(
     }
 }
 
+pub fn bind_daily_history(
+    history: Res<DailyHistory>,
+    binding: Res<Binding<DailyHistory>>,
+) {
+    if history.is_changed() {
+        binding.set(history.clone());
+    }
+}
+
+pub fn bind_lobby_status(
+    status: Res<LobbyStatus>,
+    binding: Res<Binding<LobbyStatus>>,
+) {
+    if status.is_changed() {
+        binding.set(status.clone());
+    }
+}
+
+pub fn bind_pad_assignments(
+    assignments: Res<PadAssignments>,
+    binding: Res<Binding<PadAssignments>>,
+) {
+    if assignments.is_changed() {
+        binding.set(assignments.clone());
+    }
+}
+
+pub fn bind_gamepad_config(
+    config: Res<GamepadConfig>,
+    binding: Res<Binding<GamepadConfig>>,
+) {
+    if config.is_changed() {
+        binding.set(config.clone());
+    }
+}
+
+pub fn bind_binding_profiles(
+    profiles: Res<BindingProfiles>,
+    binding: Res<Binding<BindingProfiles>>,
+) {
+    if profiles.is_changed() {
+        binding.set(profiles.clone());
+    }
+}
+
+pub fn bind_audio_settings(
+    settings: Res<AudioSettings>,
+    binding: Res<Binding<AudioSettings>>,
+) {
+    if settings.is_changed() {
+        binding.set(settings.clone());
+    }
+}
+
+pub fn bind_active_theme(
+    theme: Res<ActiveTheme>,
+    binding: Res<Binding<ActiveTheme>>,
+) {
+    if theme.is_changed() {
+        binding.set(theme.clone());
+    }
+}
+
+pub fn bind_theme_names(
+    names: Res<ThemeNames>,
+    binding: Res<Binding<ThemeNames>>,
+) {
+    if names.is_changed() {
+        binding.set(names.clone());
+    }
+}
+
+pub fn bind_skins(
+    skins: Res<Skins>,
+    binding: Res<Binding<Skins>>,
+) {
+    if skins.is_changed() {
+        binding.set(skins.clone());
+    }
+}
+
+pub fn bind_localization(
+    localization: Res<Localization>,
+    binding: Res<Binding<Localization>>,
+) {
+    if localization.is_changed() {
+        binding.set(localization.clone());
+    }
+}
+
+pub fn bind_binding_capture(
+    capture: Res<BindingCapture>,
+    binding: Res<Binding<BindingCapture>>,
+) {
+    if capture.is_changed() {
+        binding.set(capture.clone());
+    }
+}
+
 // THIS ONLY RUNS ONCE. VERY IMPORTANT FACT.
 pub fn new_game_ui_kayak(
     mut font_mapping: ResMut<FontMapping>,
@@ -63,15 +177,37 @@ pub fn new_game_ui_kayak(
     runstate: Res<State<RunState>>,
     settings: Res<GameSettings>,
     runs: Res<Speedruns>,
-    fonts: Res<FontAssets>,
+    daily_history: Res<DailyHistory>,
+    lobby_status: Res<LobbyStatus>,
+    pad_assignments: Res<PadAssignments>,
+    gamepad_config: Res<GamepadConfig>,
+    binding_profiles: Res<BindingProfiles>,
+    binding_capture: Res<BindingCapture>,
+    audio_settings: Res<AudioSettings>,
+    theme: Res<ActiveTheme>,
+    theme_names: Res<ThemeNames>,
+    skins: Res<Skins>,
+    localization: Res<Localization>,
 ) {
     commands.spawn_bundle(UICameraBundle::new());
     commands
         .insert_resource(bind(runstate.current().clone()));
     commands.insert_resource(bind(settings.clone()));
     commands.insert_resource(bind(runs.clone()));
+    commands.insert_resource(bind(daily_history.clone()));
+    commands.insert_resource(bind(lobby_status.clone()));
+    commands.insert_resource(bind(pad_assignments.clone()));
+    commands.insert_resource(bind(gamepad_config.clone()));
+    commands
+        .insert_resource(bind(binding_profiles.clone()));
+    commands.insert_resource(bind(binding_capture.clone()));
+    commands.insert_resource(bind(audio_settings.clone()));
+    commands.insert_resource(bind(theme.clone()));
+    commands.insert_resource(bind(theme_names.clone()));
+    commands.insert_resource(bind(skins.clone()));
+    commands.insert_resource(bind(localization.clone()));
 
-    font_mapping.set_default(fonts.roboto.clone());
+    font_mapping.set_default(theme.menu_font.clone());
 
     let context = BevyContext::new(|context| {
         render! {
@@ -89,6 +225,13 @@ enum Menu {
     Main,
     Settings,
     SpeedRuns,
+    Daily,
+    Lan,
+    Gamepads,
+    Controls,
+    Audio,
+    Appearance,
+    Hud,
 }
 #[widget]
 fn GameMenu() {
@@ -110,175 +253,1667 @@ fn GameMenu() {
 
     let settings = {
         let settings = context
-        .query_world::<Res<Binding<GameSettings>>, _, _>(
-            move |settings| settings.clone(),
-        );
+        .query_world::<Res<Binding<GameSettings>>, _, _>(
+            move |settings| settings.clone(),
+        );
+
+        context.bind(&settings);
+        settings.get()
+    };
+
+    let theme = {
+        let theme = context
+            .query_world::<Res<Binding<ActiveTheme>>, _, _>(
+                move |theme| theme.clone(),
+            );
+
+        context.bind(&theme);
+        theme.get()
+    };
+
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let container = {
+        let mut world =
+            context.get_global_mut::<World>().unwrap();
+
+        let mut image_manager = world
+            .get_resource_mut::<ImageManager>()
+            .unwrap();
+        image_manager.get(&theme.panel)
+    };
+
+    let nine_patch_styles = Style {
+        width: StyleProp::Value(Units::Pixels(360.0)),
+        height: StyleProp::Value(Units::Pixels(500.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        left: StyleProp::Value(Units::Stretch(1.0)),
+        right: StyleProp::Value(Units::Stretch(1.0)),
+        top: StyleProp::Value(Units::Stretch(1.0)),
+        bottom: StyleProp::Value(Units::Stretch(1.0)),
+        padding: StyleProp::Value(Edge::all(
+            Units::Stretch(1.0),
+        )),
+        row_between: StyleProp::Value(Units::Pixels(20.0)),
+        ..Style::default()
+    };
+
+    let set_menu = set_menu_state.clone();
+    let on_click =
+        OnEvent::new(move |_, event| {
+            match event.event_type {
+                EventType::Click(..) => {
+                    set_menu(Menu::Settings)
+                }
+                _ => {}
+            }
+        });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_back = OnEvent::new(move |_, event| {
+        match event.event_type {
+            EventType::Click(..) => set_menu(Menu::Main),
+            _ => {}
+        }
+    });
+
+    let on_click_checkbox = OnEvent::new(
+        move |context, event| match event.event_type {
+            EventType::Click(..) => {
+                context.query_world::<ResMut<GameSettings>, _, _>(
+            |mut settings| {
+                settings.speedrun_mode = !settings.speedrun_mode;
+            },
+        );
+            }
+            _ => {}
+        },
+    );
+
+    let on_click_stepping = OnEvent::new(
+        move |context, event| match event.event_type {
+            EventType::Click(..) => {
+                context
+                    .query_world::<ResMut<GameSettings>, _, _>(
+                        |mut settings| {
+                            settings.classic_stepping =
+                                !settings.classic_stepping;
+                        },
+                    );
+            }
+            _ => {}
+        },
+    );
+
+    let on_click_motion = OnEvent::new(
+        move |context, event| match event.event_type {
+            EventType::Click(..) => {
+                context
+                    .query_world::<ResMut<GameSettings>, _, _>(
+                        |mut settings| {
+                            settings.reduce_motion =
+                                !settings.reduce_motion;
+                        },
+                    );
+            }
+            _ => {}
+        },
+    );
+
+    let set_menu = set_menu_state.clone();
+    let on_click_runs = OnEvent::new(move |_, event| {
+        match event.event_type {
+            EventType::Click(..) => {
+                set_menu(Menu::SpeedRuns)
+            }
+            _ => {}
+        }
+    });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_daily = OnEvent::new(move |_, event| {
+        match event.event_type {
+            EventType::Click(..) => set_menu(Menu::Daily),
+            _ => {}
+        }
+    });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_lan = OnEvent::new(move |_, event| {
+        match event.event_type {
+            EventType::Click(..) => set_menu(Menu::Lan),
+            _ => {}
+        }
+    });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_gamepads =
+        OnEvent::new(move |_, event| {
+            match event.event_type {
+                EventType::Click(..) => {
+                    set_menu(Menu::Gamepads)
+                }
+                _ => {}
+            }
+        });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_controls =
+        OnEvent::new(move |_, event| {
+            match event.event_type {
+                EventType::Click(..) => {
+                    set_menu(Menu::Controls)
+                }
+                _ => {}
+            }
+        });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_audio = OnEvent::new(move |_, event| {
+        match event.event_type {
+            EventType::Click(..) => set_menu(Menu::Audio),
+            _ => {}
+        }
+    });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_appearance =
+        OnEvent::new(move |_, event| {
+            match event.event_type {
+                EventType::Click(..) => {
+                    set_menu(Menu::Appearance)
+                }
+                _ => {}
+            }
+        });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_hud = OnEvent::new(move |_, event| {
+        match event.event_type {
+            EventType::Click(..) => set_menu(Menu::Hud),
+            _ => {}
+        }
+    });
+
+    let on_click_board_size = OnEvent::new(
+        move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                    .query_world::<ResMut<GameSettings>, _, _>(
+                        |mut settings| {
+                            settings.board_size =
+                                settings.next_board_size();
+                        },
+                    );
+            }
+        },
+    );
+
+    let on_click_language =
+        OnEvent::new(move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context.query_world::<(
+                    ResMut<GameSettings>,
+                    Res<Languages>,
+                ), _, _>(
+                    |(mut settings, languages)| {
+                        if let Some(next) = languages
+                            .after(&settings.language)
+                        {
+                            settings.language =
+                                next.to_string();
+                        }
+                    },
+                );
+            }
+        });
+
+    let set_menu = set_menu_state.clone();
+    let on_click_settings =
+        OnEvent::new(move |_, event| {
+            match event.event_type {
+                EventType::Click(..) => {
+                    set_menu(Menu::Settings)
+                }
+                _ => {}
+            }
+        });
+
+    let show_main = menu_state == Menu::Main;
+    let show_settings = menu_state == Menu::Settings;
+    let show_speedruns_page = menu_state == Menu::SpeedRuns;
+    let show_daily = menu_state == Menu::Daily;
+    let show_lan = menu_state == Menu::Lan;
+    let show_gamepads = menu_state == Menu::Gamepads;
+    let show_controls = menu_state == Menu::Controls;
+    let show_audio = menu_state == Menu::Audio;
+    let show_appearance = menu_state == Menu::Appearance;
+    let show_hud = menu_state == Menu::Hud;
+    let show_speedruns = settings.speedrun_mode;
+
+    let play = localization.get("menu.play");
+    let daily = localization.get("menu.daily");
+    let lan = localization.get("menu.lan");
+    let settings_label = localization.get("menu.settings");
+    let show_runs = localization.get("menu.show_runs");
+    let back = localization.get("menu.back");
+    let speedrun = localization.get("settings.speedrun");
+    let stepping =
+        localization.get("settings.classic_stepping");
+    let motion = localization.get("settings.reduce_motion");
+    let gamepads = localization.get("settings.gamepads");
+    let controls = localization.get("settings.controls");
+    let audio = localization.get("settings.audio");
+    let appearance =
+        localization.get("settings.appearance");
+    let hud = localization.get("settings.hud");
+    let board_size = localization.format(
+        "settings.board_size",
+        &[("size", &settings.board_size)],
+    );
+    let language = localization.format(
+        "settings.language",
+        &[("language", &localization.language().name)],
+    );
+    let best_runs = localization.get("runs.title");
+    let exit = localization.get("menu.exit");
+
+    rsx! {
+       <If condition={show_menus}>
+       <NinePatch
+               styles={Some(nine_patch_styles)}
+               border={Edge::all(50.0)}
+               handle={container}
+           >
+
+           <If condition={show_main}>
+                <NewGameButton label={play}/>
+                <BlueButton on_click={Some(on_click_daily)} label={daily}/>
+                <BlueButton on_click={Some(on_click_lan)} label={lan}/>
+                <BlueButton on_click={Some(on_click)} label={settings_label}/>
+                <If condition={show_speedruns}>
+                    <BlueButton on_click={Some(on_click_runs)} label={show_runs}/>
+                </If>
+                <QuitButton label={exit}/>
+           </If>
+
+           <If condition={show_settings}>
+                <BlueButton on_click={Some(on_click_back)} label={back.clone()}/>
+                <Text line_height={Some(50.0)} size={20.0} content={speedrun.clone()}/>
+                <Checkbox checked={settings.speedrun_mode} on_click={Some(on_click_checkbox)} label={speedrun}/>
+                <Text line_height={Some(50.0)} size={20.0} content={stepping.clone()}/>
+                <Checkbox checked={settings.classic_stepping} on_click={Some(on_click_stepping)} label={stepping}/>
+                <Text line_height={Some(50.0)} size={20.0} content={motion.clone()}/>
+                <Checkbox checked={settings.reduce_motion} on_click={Some(on_click_motion)} label={motion}/>
+                <SnakeSelector/>
+                <BlueButton on_click={Some(on_click_board_size)} label={board_size}/>
+                <BlueButton on_click={Some(on_click_language)} label={language}/>
+                <BlueButton on_click={Some(on_click_gamepads)} label={gamepads}/>
+                <BlueButton on_click={Some(on_click_controls)} label={controls}/>
+                <BlueButton on_click={Some(on_click_audio)} label={audio}/>
+                <BlueButton on_click={Some(on_click_appearance)} label={appearance}/>
+                <BlueButton on_click={Some(on_click_hud)} label={hud}/>
+           </If>
+
+           <If condition={show_speedruns_page}>
+                <BlueButton on_click={Some(on_click_back)} label={back.clone()}/>
+                <Text line_height={Some(50.0)} size={20.0} content={best_runs}/>
+                <SpeedrunsDisplay/>
+           </If>
+
+           <If condition={show_daily}>
+                <BlueButton on_click={Some(on_click_back)} label={back.clone()}/>
+                <DailyMenu/>
+           </If>
+
+           <If condition={show_lan}>
+                <BlueButton on_click={Some(on_click_back)} label={back.clone()}/>
+                <LanLobby/>
+           </If>
+
+           <If condition={show_gamepads}>
+                <BlueButton on_click={Some(on_click_settings)} label={back.clone()}/>
+                <GamepadMenu/>
+           </If>
+
+           <If condition={show_controls}>
+                <BlueButton on_click={Some(on_click_settings)} label={back.clone()}/>
+                <ControlsMenu/>
+           </If>
+
+           <If condition={show_audio}>
+                <BlueButton on_click={Some(on_click_settings)} label={back.clone()}/>
+                <AudioMenu/>
+           </If>
+
+           <If condition={show_appearance}>
+                <BlueButton on_click={Some(on_click_settings)} label={back.clone()}/>
+                <AppearanceMenu/>
+           </If>
+
+           <If condition={show_hud}>
+                <BlueButton on_click={Some(on_click_settings)} label={back}/>
+                <HudMenu/>
+           </If>
+       </NinePatch>
+
+       </If>
+    }
+}
+
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct NewGameButtonProps {
+    label: String,
+}
+#[widget]
+fn NewGameButton(props: NewGameButtonProps) {
+    let on_click = OnEvent::new(|context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context.query_world::<(
+                ResMut<GameMode>,
+                ResMut<State<RunState>>,
+            ), _, _>(
+                |(mut mode, mut state)| {
+                    *mode = GameMode::Classic;
+                    state.set(RunState::Playing).unwrap();
+                },
+            );
+        }
+    });
+    rsx! {
+    <BlueButton on_click={Some(on_click)} label={props.label.clone()}/>
+    }
+}
+
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct DailyButtonProps {
+    ranked: bool,
+    label: String,
+}
+#[widget]
+fn DailyButton(props: DailyButtonProps) {
+    let ranked = props.ranked;
+    let on_click = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context.query_world::<(
+                ResMut<GameMode>,
+                ResMut<State<RunState>>,
+            ), _, _>(
+                |(mut mode, mut state)| {
+                    *mode = GameMode::Daily { ranked };
+                    state.set(RunState::Playing).unwrap();
+                },
+            );
+        }
+    });
+    rsx! {
+    <BlueButton on_click={Some(on_click)} label={props.label.clone()}/>
+    }
+}
+
+#[widget]
+fn DailyMenu() {
+    let history = {
+        let history = context
+            .query_world::<Res<Binding<DailyHistory>>, _, _>(
+                move |history| history.clone(),
+            );
+
+        context.bind(&history);
+        history.get()
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let today = DailyDate::today();
+    let challenge = DailyChallenge::for_date(today);
+    let modifiers = if challenge.rules.modifiers.is_empty()
+    {
+        localization.get("daily.no_modifiers")
+    } else {
+        challenge
+            .rules
+            .modifiers
+            .iter()
+            .map(|modifier| {
+                localization.get(modifier.key())
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let description = localization.format(
+        "daily.description",
+        &[
+            ("size", &challenge.rules.board_size),
+            (
+                "speed",
+                &localization
+                    .get(challenge.rules.speed.key()),
+            ),
+            ("modifiers", &modifiers),
+        ],
+    );
+    let streak = localization.format(
+        "daily.streak",
+        &[
+            ("current", &history.current_streak(today)),
+            ("best", &history.best_streak()),
+        ],
+    );
+    let todays_result = history.result_for(today).cloned();
+    let can_play_ranked = todays_result.is_none();
+    let todays_score = todays_result
+        .map(|result| {
+            localization.format(
+                "daily.today",
+                &[
+                    (
+                        "apples",
+                        &localization.plural(
+                            "apples",
+                            u64::from(result.score),
+                        ),
+                    ),
+                    (
+                        "time",
+                        &localization.duration(result.time),
+                    ),
+                ],
+            )
+        })
+        .unwrap_or_default();
+    let title = localization
+        .format("daily.title", &[("date", &today)]);
+    let ranked = localization.get("daily.ranked");
+    let practice = localization.get("daily.practice");
+    let recent = history
+        .recent()
+        .into_iter()
+        .take(3)
+        .map(|result| {
+            localization.format(
+                "daily.entry",
+                &[
+                    ("date", &result.date),
+                    (
+                        "apples",
+                        &localization.plural(
+                            "apples",
+                            u64::from(result.score),
+                        ),
+                    ),
+                ],
+            )
+        })
+        .collect::<Vec<String>>();
+
+    let container_styles = Style {
+        left: StyleProp::Value(Units::Pixels(5.0)),
+        row_between: StyleProp::Value(Units::Pixels(10.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
+        )),
+        ..Default::default()
+    };
+
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            <Text line_height={Some(30.0)} size={20.0} content={title}/>
+            <Text line_height={Some(20.0)} size={14.0} content={description}/>
+            <Text line_height={Some(20.0)} size={14.0} content={streak}/>
+            <If condition={can_play_ranked}>
+                <DailyButton ranked={true} label={ranked}/>
+            </If>
+            <If condition={!can_play_ranked}>
+                <Text line_height={Some(20.0)} size={14.0} content={todays_score}/>
+            </If>
+            <DailyButton ranked={false} label={practice}/>
+            {VecTracker::from(
+                recent
+                    .into_iter()
+                    .map(|entry| constructor! {
+                        <Text line_height={Some(20.0)} size={14.0} content={entry}/>
+                    }),
+            )}
+        </Element>
+    }
+}
+
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct QuitButtonProps {
+    label: String,
+}
+#[widget]
+fn QuitButton(props: QuitButtonProps) {
+    let on_click = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<EventWriter<AppExit>, _, _>(
+                    |mut exit| {
+                        exit.send(AppExit);
+                    },
+                );
+        }
+    });
+    rsx! {
+    <BlueButton on_click={Some(on_click)} label={props.label.clone()}/>
+    }
+}
+
+#[widget]
+fn LanLobby() {
+    let status = {
+        let status = context
+            .query_world::<Res<Binding<LobbyStatus>>, _, _>(
+                move |status| status.clone(),
+            );
+
+        context.bind(&status);
+        status.get()
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let (host_addr, join_addr) = context
+        .query_world::<Res<LobbySettings>, _, _>(
+            |settings| {
+                (
+                    settings.host_addr.clone(),
+                    settings.join_addr.clone(),
+                )
+            },
+        );
+    let (address, set_address, ..) = use_state!(join_addr);
+
+    let send = |lobby_event: LobbyEvent| {
+        OnEvent::new(move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                    .query_world::<EventWriter<LobbyEvent>, _, _>(
+                        |mut events| {
+                            events.send(lobby_event.clone());
+                        },
+                    );
+            }
+        })
+    };
+    let on_host = send(LobbyEvent::Host(host_addr));
+    let on_join = send(LobbyEvent::Join(address.clone()));
+    let on_start = send(LobbyEvent::Start);
+    let on_leave = send(LobbyEvent::Leave);
+
+    let on_change = OnChange::new(move |event| {
+        set_address(event.value);
+    });
+
+    let address_label = localization
+        .format("lan.address", &[("address", &address)]);
+    let on_address = OnEvent::new(move |ctx, event| {
+        if let EventType::Focus = event.event_type {
+            announce(ctx, &address_label);
+        }
+    });
+
+    let players = |players: usize| {
+        localization.plural("players", players as u64)
+    };
+    let (status_text, offline, hosting) = match &status {
+        LobbyStatus::Offline => {
+            (localization.get("lan.offline"), true, false)
+        }
+        LobbyStatus::Hosting {
+            addr,
+            players: count,
+        } => (
+            localization.format(
+                "lan.hosting",
+                &[
+                    ("address", addr),
+                    ("players", &players(*count)),
+                ],
+            ),
+            false,
+            true,
+        ),
+        LobbyStatus::Joining { addr } => (
+            localization.format(
+                "lan.joining",
+                &[("address", addr)],
+            ),
+            false,
+            false,
+        ),
+        LobbyStatus::Joined { players: count } => (
+            localization.format(
+                "lan.joined",
+                &[("players", &players(*count))],
+            ),
+            false,
+            false,
+        ),
+        LobbyStatus::Error(error) => {
+            (error.clone(), true, false)
+        }
+    };
+    let host = localization.get("lan.host");
+    let join = localization.get("lan.join");
+    let start = localization.get("lan.start");
+    let leave = localization.get("lan.leave");
+
+    let input_styles = Style {
+        width: StyleProp::Value(Units::Pixels(200.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+
+    let container_styles = Style {
+        row_between: StyleProp::Value(Units::Pixels(10.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
+        )),
+        ..Default::default()
+    };
+
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            <Text line_height={Some(20.0)} size={14.0} content={status_text}/>
+            <If condition={offline}>
+                <BlueButton on_click={Some(on_host)} label={host}/>
+                <TextBox styles={Some(input_styles)} value={address} on_change={Some(on_change)} on_event={Some(on_address)}/>
+                <BlueButton on_click={Some(on_join)} label={join}/>
+            </If>
+            <If condition={hosting}>
+                <BlueButton on_click={Some(on_start)} label={start}/>
+            </If>
+            <If condition={!offline}>
+                <BlueButton on_click={Some(on_leave)} label={leave}/>
+            </If>
+        </Element>
+    }
+}
+
+#[widget]
+fn GamepadMenu() {
+    let assignments = {
+        let assignments = context.query_world::<Res<
+            Binding<PadAssignments>,
+        >, _, _>(
+            move |assignments| assignments.clone(),
+        );
+
+        context.bind(&assignments);
+        assignments.get()
+    };
+    let config = {
+        let config = context
+            .query_world::<Res<Binding<GamepadConfig>>, _, _>(
+                move |config| config.clone(),
+            );
+
+        context.bind(&config);
+        config.get()
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let adjust = |by: f32| {
+        OnEvent::new(move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                    .query_world::<ResMut<GamepadConfig>, _, _>(
+                        |mut config| {
+                            config.adjust_deadzone(by);
+                        },
+                    );
+            }
+        })
+    };
+    let on_less = adjust(-0.05);
+    let on_more = adjust(0.05);
+
+    let deadzone = localization.format(
+        "gamepads.deadzone",
+        &[(
+            "percent",
+            &localization.percent(config.deadzone),
+        )],
+    );
+    let hint = if assignments.pads.is_empty() {
+        localization.get("gamepads.none")
+    } else {
+        localization.get("gamepads.hint")
+    };
+    let pads = assignments
+        .pads
+        .iter()
+        .map(|(pad, snake)| {
+            let label = localization.format(
+                "gamepads.pad",
+                &[
+                    ("pad", &(pad.0 + 1)),
+                    ("snake", &(snake + 1)),
+                ],
+            );
+            (pad.0, label)
+        })
+        .collect::<Vec<(usize, String)>>();
+
+    let row_styles = Style {
+        layout_type: StyleProp::Value(LayoutType::Row),
+        col_between: StyleProp::Value(Units::Pixels(10.0)),
+        height: StyleProp::Value(Units::Pixels(50.0)),
+        ..Default::default()
+    };
+    let small_button = Style {
+        width: StyleProp::Value(Units::Pixels(50.0)),
+        ..Default::default()
+    };
+    let container_styles = Style {
+        row_between: StyleProp::Value(Units::Pixels(10.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
+        )),
+        ..Default::default()
+    };
+
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            <Text line_height={Some(20.0)} size={14.0} content={deadzone}/>
+            <Element styles={Some(row_styles)}>
+                <BlueButton styles={Some(small_button.clone())} on_click={Some(on_less)} label={"-".to_string()}/>
+                <BlueButton styles={Some(small_button)} on_click={Some(on_more)} label={"+".to_string()}/>
+            </Element>
+            <Text line_height={Some(20.0)} size={14.0} content={hint}/>
+            {VecTracker::from(
+                pads
+                    .into_iter()
+                    .map(|(pad, label)| constructor! {
+                        <PadButton pad={pad} label={label}/>
+                    }),
+            )}
+        </Element>
+    }
+}
+
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct PadButtonProps {
+    pad: usize,
+    label: String,
+}
+#[widget]
+fn PadButton(props: PadButtonProps) {
+    let pad = Gamepad(props.pad);
+    let on_click = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<ResMut<PadAssignments>, _, _>(
+                    |mut assignments| {
+                        assignments.cycle(pad);
+                    },
+                );
+        }
+    });
+    rsx! {
+    <BlueButton on_click={Some(on_click)} label={props.label.clone()}/>
+    }
+}
+
+#[widget]
+fn ControlsMenu() {
+    let profiles = {
+        let profiles = context
+            .query_world::<Res<Binding<BindingProfiles>>, _, _>(
+                move |profiles| profiles.clone(),
+            );
+
+        context.bind(&profiles);
+        profiles.get()
+    };
+    let capture = {
+        let capture = context
+            .query_world::<Res<Binding<BindingCapture>>, _, _>(
+                move |capture| capture.clone(),
+            );
+
+        context.bind(&capture);
+        capture.get()
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let on_profile = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context.query_world::<(
+                ResMut<BindingProfiles>,
+                ResMut<BindingCapture>,
+            ), _, _>(
+                |(mut profiles, mut capture)| {
+                    profiles.next_profile();
+                    *capture = BindingCapture::default();
+                },
+            );
+        }
+    });
+    let on_reset = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context.query_world::<(
+                ResMut<BindingProfiles>,
+                ResMut<BindingCapture>,
+            ), _, _>(
+                |(mut profiles, mut capture)| {
+                    profiles.reset();
+                    *capture = BindingCapture {
+                        action: None,
+                        message: Some(
+                            CaptureMessage::Reset,
+                        ),
+                    };
+                },
+            );
+        }
+    });
+
+    let on_scheme = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<ResMut<GameSettings>, _, _>(
+                    |mut settings| {
+                        settings.control_scheme = settings
+                            .control_scheme
+                            .toggled();
+                    },
+                );
+        }
+    });
+    let scheme = {
+        let settings = context
+            .query_world::<Res<Binding<GameSettings>>, _, _>(
+                move |settings| settings.clone(),
+            );
+
+        context.bind(&settings);
+        settings.get().control_scheme
+    };
+    let scheme = localization.format(
+        "controls.turning",
+        &[("scheme", &localization.get(scheme.key()))],
+    );
+
+    let profile = localization.format(
+        "controls.profile",
+        &[("number", &(profiles.active + 1))],
+    );
+    let bindings = profiles.current().clone();
+    let action =
+        |action: Action| localization.get(action.key());
+    let message = match capture.message {
+        _ if capture.action.is_some() => {
+            localization.get("controls.press")
+        }
+        None => String::new(),
+        Some(CaptureMessage::Cancelled) => {
+            localization.get("controls.cancelled")
+        }
+        Some(CaptureMessage::Reset) => {
+            localization.get("controls.reset_done")
+        }
+        Some(CaptureMessage::Bound {
+            trigger,
+            action: to,
+        }) => localization.format(
+            "controls.bound",
+            &[
+                ("trigger", &trigger.name()),
+                ("action", &action(to)),
+            ],
+        ),
+        Some(CaptureMessage::Moved {
+            trigger,
+            from,
+            to,
+        }) => localization.format(
+            "controls.moved",
+            &[
+                ("trigger", &trigger.name()),
+                ("from", &action(from)),
+                ("to", &action(to)),
+            ],
+        ),
+    };
+    let reset = localization.get("controls.reset");
+    // a label, and what the bind and clear buttons
+    // are announced as, for every action
+    let rows = Action::ALL
+        .into_iter()
+        .map(|bound| {
+            let triggers = bindings
+                .triggers(bound)
+                .iter()
+                .map(|trigger| trigger.name())
+                .collect::<Vec<String>>()
+                .join(", ");
+            let name = action(bound);
+            let label = if capture.action == Some(bound) {
+                localization.format(
+                    "controls.capturing",
+                    &[("action", &name)],
+                )
+            } else {
+                localization.format(
+                    "controls.row",
+                    &[
+                        ("action", &name),
+                        ("triggers", &triggers),
+                    ],
+                )
+            };
+            let bind = localization.format(
+                "controls.bind",
+                &[("action", &name)],
+            );
+            let clear = localization.format(
+                "controls.clear",
+                &[("action", &name)],
+            );
+            (label, bind, clear)
+        })
+        .collect::<Vec<(String, String, String)>>();
+
+    let container_styles = Style {
+        row_between: StyleProp::Value(Units::Pixels(5.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
+        )),
+        ..Default::default()
+    };
+
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            <BlueButton on_click={Some(on_scheme)} label={scheme}/>
+            <BlueButton on_click={Some(on_profile)} label={profile}/>
+            {VecTracker::from(
+                rows
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (label, bind, clear))| constructor! {
+                        <ActionRow action={index} label={label} bind={bind} clear={clear}/>
+                    }),
+            )}
+            <Text line_height={Some(20.0)} size={14.0} content={message}/>
+            <BlueButton on_click={Some(on_reset)} label={reset}/>
+        </Element>
+    }
+}
+
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct ActionRowProps {
+    /// index into `Action::ALL`
+    action: usize,
+    label: String,
+    /// what the bind and clear buttons are announced as
+    bind: String,
+    clear: String,
+}
+#[widget]
+fn ActionRow(props: ActionRowProps) {
+    let action = Action::ALL[props.action];
+    let on_capture = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<ResMut<BindingCapture>, _, _>(
+                    |mut capture| {
+                        *capture = BindingCapture {
+                            action: Some(action),
+                            message: String::new(),
+                        };
+                    },
+                );
+        }
+    });
+    let on_clear = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<ResMut<BindingProfiles>, _, _>(
+                    |mut profiles| {
+                        profiles.current_mut().clear(action);
+                    },
+                );
+        }
+    });
+
+    let label = props.label.clone();
+
+    let row_styles = Style {
+        layout_type: StyleProp::Value(LayoutType::Row),
+        col_between: StyleProp::Value(Units::Pixels(5.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+    let label_styles = Style {
+        width: StyleProp::Value(Units::Pixels(200.0)),
+        ..Default::default()
+    };
+    let small_button = Style {
+        width: StyleProp::Value(Units::Pixels(30.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+
+    rsx! {
+        <Element styles={Some(row_styles)}>
+            <Text styles={Some(label_styles)} line_height={Some(30.0)} size={12.0} content={label}/>
+            <BlueButton styles={Some(small_button.clone())} on_click={Some(on_capture)} label={props.bind.clone()}>
+                <Text line_height={Some(30.0)} size={16.0} content={"+".to_string()}/>
+            </BlueButton>
+            <BlueButton styles={Some(small_button)} on_click={Some(on_clear)} label={props.clear.clone()}>
+                <Text line_height={Some(30.0)} size={16.0} content={"x".to_string()}/>
+            </BlueButton>
+        </Element>
+    }
+}
+
+#[widget]
+fn AudioMenu() {
+    let settings = {
+        let settings = context
+            .query_world::<Res<Binding<AudioSettings>>, _, _>(
+                move |settings| settings.clone(),
+            );
+
+        context.bind(&settings);
+        settings.get()
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let on_mute = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<ResMut<AudioSettings>, _, _>(
+                    |mut settings| {
+                        settings.muted = !settings.muted;
+                    },
+                );
+        }
+    });
+    let on_guidance = OnEvent::new(
+        move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                .query_world::<ResMut<AudioSettings>, _, _>(
+                    |mut settings| {
+                        settings.guidance = !settings.guidance;
+                    },
+                );
+            }
+        },
+    );
+
+    let container_styles = Style {
+        row_between: StyleProp::Value(Units::Pixels(10.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
+        )),
+        ..Default::default()
+    };
+
+    let mute = localization.get("audio.mute");
+    let guidance = localization.get("audio.guidance");
+
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            {VecTracker::from(
+                Volume::ALL
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, volume)| {
+                        let value = settings.get(volume);
+                        constructor! {
+                            <VolumeSlider volume={index} value={value}/>
+                        }
+                    }),
+            )}
+            <Text line_height={Some(50.0)} size={20.0} content={mute.clone()}/>
+            <Checkbox checked={settings.muted} on_click={Some(on_mute)} label={mute}/>
+            <Text line_height={Some(50.0)} size={20.0} content={guidance.clone()}/>
+            <Checkbox checked={settings.guidance} on_click={Some(on_guidance)} label={guidance}/>
+        </Element>
+    }
+}
+
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct VolumeSliderProps {
+    /// index into `Volume::ALL`
+    volume: usize,
+    /// from 0 to 1
+    value: f32,
+}
+#[widget]
+fn VolumeSlider(props: VolumeSliderProps) {
+    const TRACK_WIDTH: f32 = 150.0;
+    const KNOB_WIDTH: f32 = 20.0;
+    // the track is cut into this many spots to click or
+    // drag over, from 0 to 100%
+    const STOPS: usize = 21;
+
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+    let volume = Volume::ALL[props.volume];
+    let (track, knob) = {
+        let mut world =
+            context.get_global_mut::<World>().unwrap();
+
+        let assets =
+            world.get_resource::<ImageAssets>().unwrap();
+        let track = assets.slider_track.clone();
+        let knob = assets.slider.clone();
+
+        let mut image_manager = world
+            .get_resource_mut::<ImageManager>()
+            .unwrap();
+        (
+            image_manager.get(&track),
+            image_manager.get(&knob),
+        )
+    };
+
+    let adjust = move |by: f32| {
+        OnEvent::new(move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                    .query_world::<ResMut<AudioSettings>, _, _>(
+                        |mut settings| {
+                            settings.adjust(volume, by);
+                        },
+                    );
+            }
+        })
+    };
+    let on_less = adjust(-0.1);
+    let on_more = adjust(0.1);
+
+    let name = localization.get(volume.key());
+    let label = localization.format(
+        "audio.volume",
+        &[
+            ("volume", &name),
+            ("percent", &localization.percent(props.value)),
+        ],
+    );
+    let down = localization
+        .format("audio.down", &[("volume", &name)]);
+    let up = localization
+        .format("audio.up", &[("volume", &name)]);
+    let on_track = {
+        let label = label.clone();
+        OnEvent::new(move |ctx, event| {
+            let by = match &event.event_type {
+                EventType::MouseIn(..)
+                | EventType::Focus => {
+                    announce(ctx, &label);
+                    return;
+                }
+                EventType::KeyDown(key) => {
+                    match key.key() {
+                        KeyCode::Left => -0.1,
+                        KeyCode::Right => 0.1,
+                        _ => return,
+                    }
+                }
+                _ => return,
+            };
+            ctx.query_world::<ResMut<AudioSettings>, _, _>(
+                |mut settings| settings.adjust(volume, by),
+            );
+        })
+    };
+    // clicking a stop, or dragging over it with the
+    // button held, sets the volume to it
+    let stops: Vec<OnEvent> = (0..STOPS)
+        .map(move |stop| {
+            let value = stop as f32 / (STOPS - 1) as f32;
+            OnEvent::new(move |ctx, event| {
+                let set = match &event.event_type {
+                    EventType::MouseDown(..) => true,
+                    EventType::MouseIn(cursor) => cursor.pressed,
+                    _ => false,
+                };
+                if set {
+                    ctx.query_world::<ResMut<AudioSettings>, _, _>(
+                        |mut settings| {
+                            settings.set(volume, value)
+                        },
+                    );
+                }
+            })
+        })
+        .collect();
+
+    let slider_styles = Style {
+        layout_type: StyleProp::Value(LayoutType::Column),
+        height: StyleProp::Value(Units::Pixels(50.0)),
+        ..Default::default()
+    };
+    let row_styles = Style {
+        layout_type: StyleProp::Value(LayoutType::Row),
+        col_between: StyleProp::Value(Units::Pixels(10.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+    let small_button = Style {
+        width: StyleProp::Value(Units::Pixels(30.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+    let track_styles = Style {
+        width: StyleProp::Value(Units::Pixels(TRACK_WIDTH)),
+        height: StyleProp::Value(Units::Pixels(10.0)),
+        top: StyleProp::Value(Units::Pixels(10.0)),
+        ..Default::default()
+    };
+    let stop_styles = Style {
+        position_type: StyleProp::Value(
+            PositionType::SelfDirected,
+        ),
+        width: StyleProp::Value(Units::Pixels(
+            TRACK_WIDTH / STOPS as f32,
+        )),
+        top: StyleProp::Value(Units::Pixels(-10.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+    let knob_styles = Style {
+        position_type: StyleProp::Value(
+            PositionType::SelfDirected,
+        ),
+        left: StyleProp::Value(Units::Pixels(
+            props.value * (TRACK_WIDTH - KNOB_WIDTH),
+        )),
+        top: StyleProp::Value(Units::Pixels(-6.0)),
+        width: StyleProp::Value(Units::Pixels(KNOB_WIDTH)),
+        height: StyleProp::Value(Units::Pixels(22.0)),
+        ..Default::default()
+    };
+
+    rsx! {
+        <Element styles={Some(slider_styles)}>
+            <Text line_height={Some(20.0)} size={14.0} content={label}/>
+            <Element styles={Some(row_styles)}>
+                <BlueButton styles={Some(small_button.clone())} on_click={Some(on_less)} label={down}>
+                    <Text line_height={Some(30.0)} size={16.0} content={"-".to_string()}/>
+                </BlueButton>
+                <NinePatch styles={Some(track_styles)} border={Edge::all(2.0)} handle={track} on_event={Some(on_track)} focusable={Some(true)}>
+                    {VecTracker::from(stops.iter().enumerate().map(|(stop, on_stop)| {
+                        let styles = Style {
+                            left: StyleProp::Value(Units::Pixels(
+                                stop as f32 * TRACK_WIDTH / STOPS as f32,
+                            )),
+                            ..stop_styles.clone()
+                        };
+                        constructor! {
+                            <Element styles={Some(styles)} on_event={Some(on_stop.clone())}/>
+                        }
+                    }))}
+                    <NinePatch styles={Some(knob_styles)} border={Edge::all(1.0)} handle={knob}/>
+                </NinePatch>
+                <BlueButton styles={Some(small_button)} on_click={Some(on_more)} label={up}>
+                    <Text line_height={Some(30.0)} size={16.0} content={"+".to_string()}/>
+                </BlueButton>
+            </Element>
+        </Element>
+    }
+}
+
+#[widget]
+fn AppearanceMenu() {
+    let names = {
+        let names = context
+            .query_world::<Res<Binding<ThemeNames>>, _, _>(
+                move |names| names.clone(),
+            );
+
+        context.bind(&names);
+        names.get()
+    };
+    let settings = {
+        let settings = context
+            .query_world::<Res<Binding<GameSettings>>, _, _>(
+                move |settings| settings.clone(),
+            );
+
+        context.bind(&settings);
+        settings.get()
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+
+    let container_styles = Style {
+        row_between: StyleProp::Value(Units::Pixels(10.0)),
+        layout_type: StyleProp::Value(LayoutType::Column),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
+        )),
+        ..Default::default()
+    };
+
+    let selected = |item: String, selected: bool| {
+        if selected {
+            localization
+                .format("menu.selected", &[("item", &item)])
+        } else {
+            item
+        }
+    };
+    let themes = names
+        .0
+        .into_iter()
+        .map(|name| {
+            let label = selected(
+                name.clone(),
+                name == settings.theme,
+            );
+            (name, label)
+        })
+        .collect::<Vec<(String, String)>>();
+    let presets = Accessibility::ALL
+        .into_iter()
+        .map(|preset| {
+            selected(
+                localization.get(preset.key()),
+                preset == settings.accessibility,
+            )
+        })
+        .collect::<Vec<String>>();
+    let theme = localization.get("appearance.theme");
+    let accessibility =
+        localization.get("appearance.accessibility");
+    let plain_grid =
+        localization.get("appearance.plain_grid");
+
+    let on_plain_grid =
+        OnEvent::new(move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                .query_world::<ResMut<GameSettings>, _, _>(
+                    |mut settings| {
+                        settings.plain_grid =
+                            !settings.plain_grid;
+                    },
+                );
+            }
+        });
+
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            <Text line_height={Some(50.0)} size={20.0} content={theme}/>
+            {VecTracker::from(
+                themes
+                    .into_iter()
+                    .map(|(name, label)| constructor! {
+                        <ThemeButton name={name} label={label}/>
+                    }),
+            )}
+            <Text line_height={Some(50.0)} size={20.0} content={accessibility}/>
+            {VecTracker::from(
+                presets
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, label)| constructor! {
+                        <PresetButton preset={index} label={label}/>
+                    }),
+            )}
+            <Text line_height={Some(50.0)} size={20.0} content={plain_grid.clone()}/>
+            <Checkbox checked={settings.plain_grid} on_click={Some(on_plain_grid)} label={plain_grid}/>
+        </Element>
+    }
+}
+
+#[widget]
+fn HudMenu() {
+    let settings = {
+        let settings = context
+            .query_world::<Res<Binding<GameSettings>>, _, _>(
+                move |settings| settings.clone(),
+            );
 
         context.bind(&settings);
         settings.get()
     };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
 
-    let container = {
-        let mut world =
-            context.get_global_mut::<World>().unwrap();
-
-        let green_panel = world
-            .get_resource::<ImageAssets>()
-            .unwrap()
-            .green_panel
-            .clone();
-
-        let mut image_manager = world
-            .get_resource_mut::<ImageManager>()
-            .unwrap();
-        image_manager.get(&green_panel)
+        context.bind(&localization);
+        localization.get()
     };
 
-    let nine_patch_styles = Style {
-        width: StyleProp::Value(Units::Pixels(360.0)),
-        height: StyleProp::Value(Units::Pixels(500.0)),
+    let container_styles = Style {
+        row_between: StyleProp::Value(Units::Pixels(10.0)),
         layout_type: StyleProp::Value(LayoutType::Column),
-        left: StyleProp::Value(Units::Stretch(1.0)),
-        right: StyleProp::Value(Units::Stretch(1.0)),
-        top: StyleProp::Value(Units::Stretch(1.0)),
-        bottom: StyleProp::Value(Units::Stretch(1.0)),
-        padding: StyleProp::Value(Edge::all(
-            Units::Stretch(1.0),
+        padding_left: StyleProp::Value(Units::Stretch(1.0)),
+        padding_right: StyleProp::Value(Units::Stretch(
+            1.0,
         )),
-        row_between: StyleProp::Value(Units::Pixels(20.0)),
-        ..Style::default()
+        ..Default::default()
     };
 
-    let set_menu = set_menu_state.clone();
-    let on_click =
-        OnEvent::new(move |_, event| {
-            match event.event_type {
-                EventType::Click(..) => {
-                    set_menu(Menu::Settings)
-                }
-                _ => {}
+    let position = localization.format(
+        "hud.position",
+        &[(
+            "position",
+            &localization.get(settings.hud.position.key()),
+        )],
+    );
+    let toggles = HudElement::ALL
+        .into_iter()
+        .map(|element| {
+            (
+                localization.get(element.key()),
+                settings.hud.shows(element),
+            )
+        })
+        .collect::<Vec<(String, bool)>>();
+
+    let on_position =
+        OnEvent::new(move |context, event| {
+            if let EventType::Click(..) = event.event_type {
+                context
+                .query_world::<ResMut<GameSettings>, _, _>(
+                    |mut settings| {
+                        settings.hud.position =
+                            settings.hud.position.next();
+                    },
+                );
             }
         });
 
-    let set_menu = set_menu_state.clone();
-    let on_click_back = OnEvent::new(move |_, event| {
-        match event.event_type {
-            EventType::Click(..) => set_menu(Menu::Main),
-            _ => {}
-        }
-    });
-
-    let on_click_checkbox = OnEvent::new(
-        move |context, event| match event.event_type {
-            EventType::Click(..) => {
-                context.query_world::<ResMut<GameSettings>, _, _>(
-            |mut settings| {
-                settings.speedrun_mode = !settings.speedrun_mode;
-            },
-        );
-            }
-            _ => {}
-        },
-    );
+    rsx! {
+        <Element styles={Some(container_styles)}>
+            <BlueButton on_click={Some(on_position)} label={position}/>
+            {VecTracker::from(
+                toggles
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (label, shown))| constructor! {
+                        <HudToggle element={index} label={label} shown={shown}/>
+                    }),
+            )}
+        </Element>
+    }
+}
 
-    let set_menu = set_menu_state.clone();
-    let on_click_runs = OnEvent::new(move |_, event| {
-        match event.event_type {
-            EventType::Click(..) => {
-                set_menu(Menu::SpeedRuns)
-            }
-            _ => {}
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct HudToggleProps {
+    /// index into `HudElement::ALL`
+    element: usize,
+    label: String,
+    shown: bool,
+}
+#[widget]
+fn HudToggle(props: HudToggleProps) {
+    let element = HudElement::ALL[props.element];
+    let on_click = OnEvent::new(move |context, event| {
+        if let EventType::Click(..) = event.event_type {
+            context
+                .query_world::<ResMut<GameSettings>, _, _>(
+                    |mut settings| {
+                        settings.hud.toggle(element);
+                    },
+                );
         }
     });
+    let label = props.label.clone();
 
-    let show_main = menu_state == Menu::Main;
-    let show_settings = menu_state == Menu::Settings;
-    let show_speedruns_page = menu_state == Menu::SpeedRuns;
-    let show_speedruns = settings.speedrun_mode;
+    let row_styles = Style {
+        layout_type: StyleProp::Value(LayoutType::Row),
+        col_between: StyleProp::Value(Units::Pixels(5.0)),
+        height: StyleProp::Value(Units::Pixels(30.0)),
+        ..Default::default()
+    };
+    let label_styles = Style {
+        width: StyleProp::Value(Units::Pixels(200.0)),
+        ..Default::default()
+    };
 
     rsx! {
-       <If condition={show_menus}>
-       <NinePatch
-               styles={Some(nine_patch_styles)}
-               border={Edge::all(50.0)}
-               handle={container}
-           >
-
-           <If condition={show_main}>
-                <NewGameButton/>
-                <BlueButton on_click={Some(on_click)}>
-                    <Text line_height={Some(50.0)} size={20.0} content={"Settings".to_string()}/>
-                </BlueButton>
-                <If condition={show_speedruns}>
-                    <BlueButton on_click={Some(on_click_runs)}>
-                        <Text line_height={Some(50.0)} size={20.0} content={"Show Runs".to_string()}/>
-                    </BlueButton>
-                </If>
-                <QuitButton/>
-           </If>
-
-           <If condition={show_settings}>
-                <BlueButton on_click={Some(on_click_back)}>
-                    <Text line_height={Some(50.0)} size={20.0} content={"Back".to_string()}/>
-                </BlueButton>
-                <Text line_height={Some(50.0)} size={20.0} content={"Speedrun!".to_string()}/>
-                <Checkbox checked={settings.speedrun_mode} on_click={Some(on_click_checkbox)}/>
-                <SnakeSelector/>
-           </If>
-
-           <If condition={show_speedruns_page}>
-                <BlueButton on_click={Some(on_click_back)}>
-                    <Text line_height={Some(50.0)} size={20.0} content={"Back".to_string()}/>
-                </BlueButton>
-                <Text line_height={Some(50.0)} size={20.0} content={"Best Runs".to_string()}/>
-                <SpeedrunsDisplay/>
-           </If>
-       </NinePatch>
-
-       </If>
+        <Element styles={Some(row_styles)}>
+            <Text styles={Some(label_styles)} line_height={Some(30.0)} size={16.0} content={label.clone()}/>
+            <Checkbox checked={props.shown} on_click={Some(on_click)} label={label}/>
+        </Element>
     }
 }
 
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct PresetButtonProps {
+    /// index into `Accessibility::ALL`
+    preset: usize,
+    label: String,
+}
 #[widget]
-fn NewGameButton() {
-    let on_click = OnEvent::new(|context, event| {
+fn PresetButton(props: PresetButtonProps) {
+    let preset = Accessibility::ALL[props.preset];
+    let on_click = OnEvent::new(move |context, event| {
         if let EventType::Click(..) = event.event_type {
             context
-            .query_world::<ResMut<State<RunState>>, _, _>(
-                |mut state| {
-                    state.set(RunState::Playing).unwrap();
-                },
-            );
+                .query_world::<ResMut<GameSettings>, _, _>(
+                    |mut settings| {
+                        settings.accessibility = preset;
+                    },
+                );
         }
     });
     rsx! {
-    <BlueButton on_click={Some(on_click)}>
-        <Text line_height={Some(50.0)} size={20.0} content={"Play".to_string()} />
-    </BlueButton>
+    <BlueButton on_click={Some(on_click)} label={props.label.clone()}/>
     }
 }
 
+#[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
+struct ThemeButtonProps {
+    name: String,
+    label: String,
+}
 #[widget]
-fn QuitButton() {
+fn ThemeButton(props: ThemeButtonProps) {
+    let name = props.name.clone();
     let on_click = OnEvent::new(move |context, event| {
         if let EventType::Click(..) = event.event_type {
             context
-                .query_world::<EventWriter<AppExit>, _, _>(
-                    |mut exit| {
-                        exit.send(AppExit);
+                .query_world::<ResMut<GameSettings>, _, _>(
+                    |mut settings| {
+                        settings.theme = name.clone();
                     },
                 );
         }
     });
     rsx! {
-    <BlueButton on_click={Some(on_click)}>
-        <Text line_height={Some(50.0)} size={20.0} content={"Exit".to_string()} />
-    </BlueButton>
+    <BlueButton on_click={Some(on_click)} label={props.label.clone()}/>
     }
 }
 
 #[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
 struct BlueButtonProps {
+    /// Shown on the button unless it has children, and
+    /// announced by audio guidance either way
+    label: String,
     #[prop_field(Styles)]
     styles: Option<Style>,
     #[prop_field(OnEvent)]
@@ -288,20 +1923,21 @@ struct BlueButtonProps {
 }
 #[widget]
 fn BlueButton(props: BlueButtonProps) {
+    let theme = {
+        let theme = context
+            .query_world::<Res<Binding<ActiveTheme>>, _, _>(
+                move |theme| theme.clone(),
+            );
+
+        context.bind(&theme);
+        theme.get()
+    };
     let (blue_button_handle, blue_button_hover_handle) = {
         let mut world =
             context.get_global_mut::<World>().unwrap();
 
-        let handle1 = world
-            .get_resource::<ImageAssets>()
-            .unwrap()
-            .blue_button09
-            .clone();
-        let handle2 = world
-            .get_resource::<ImageAssets>()
-            .unwrap()
-            .blue_button10
-            .clone();
+        let handle1 = theme.button.clone();
+        let handle2 = theme.button_pressed.clone();
 
         let mut image_manager = world
             .get_resource_mut::<ImageManager>()
@@ -317,9 +1953,12 @@ fn BlueButton(props: BlueButtonProps) {
         )
     };
 
-    let current_button_handle = context
-        .create_state::<u16>(blue_button_handle)
-        .unwrap();
+    // which image to show is picked on every render, so
+    // a new theme swaps both
+    let pressed =
+        context.create_state::<bool>(false).unwrap();
+    let focused =
+        context.create_state::<bool>(false).unwrap();
 
     let button_styles = Style {
         width: StyleProp::Value(Units::Pixels(200.0)),
@@ -330,96 +1969,69 @@ fn BlueButton(props: BlueButtonProps) {
         ..props.styles.clone().unwrap_or_default()
     };
 
-    let cloned_current_button_handle =
-        current_button_handle.clone();
+    let cloned_pressed = pressed.clone();
+    let cloned_focused = focused.clone();
     let on_click = props.on_click.clone();
+    let label = props.label.clone();
     let on_event = OnEvent::new(move |ctx, event| {
         match event.event_type {
+            EventType::MouseIn(..) => announce(ctx, &label),
+            EventType::Focus => {
+                cloned_focused.set(true);
+                announce(ctx, &label);
+            }
             EventType::MouseDown(..) => {
-                cloned_current_button_handle
-                    .set(blue_button_hover_handle);
+                cloned_pressed.set(true);
             }
             EventType::MouseUp(..) => {
-                cloned_current_button_handle
-                    .set(blue_button_handle);
+                cloned_pressed.set(false);
             }
+            EventType::Blur => cloned_focused.set(false),
             EventType::Click(..) => {
                 match &on_click {
                     Some(v) => v.try_call(ctx, event),
                     None => todo!(),
                 };
             }
+            _ if presses(event) => {
+                click(&on_click, ctx, event)
+            }
             _ => (),
         }
     });
 
+    let current_button_handle =
+        if pressed.get() || focused.get() {
+            blue_button_hover_handle
+        } else {
+            blue_button_handle
+        };
+    let has_children = props.children.is_some();
     let children = props.get_children();
+    let label = props.label.clone();
     rsx! {
         <NinePatch
             border={Edge::all(24.0)}
-            handle={current_button_handle.get()}
+            handle={current_button_handle}
             styles={Some(button_styles)}
             on_event={Some(on_event)}
+            focusable={Some(true)}
         >
-            {children}
-        </NinePatch>
-    }
-}
-
-#[widget]
-fn Speedrun() {
-    let container = {
-        let mut world =
-            context.get_global_mut::<World>().unwrap();
-
-        let green_panel = world
-            .get_resource::<ImageAssets>()
-            .unwrap()
-            .green_panel
-            .clone();
-
-        let mut image_manager = world
-            .get_resource_mut::<ImageManager>()
-            .unwrap();
-        let container = image_manager.get(&green_panel);
-
-        container
-    };
-
-    let nine_patch_styles = Style {
-        width: StyleProp::Value(Units::Pixels(100.0)),
-        height: StyleProp::Value(Units::Pixels(
-            500.0 / 10.0,
-        )),
-        layout_type: StyleProp::Value(LayoutType::Column),
-        left: StyleProp::Value(Units::Stretch(1.0)),
-        right: StyleProp::Value(Units::Stretch(1.0)),
-        top: StyleProp::Value(Units::Stretch(1.0)),
-        bottom: StyleProp::Value(Units::Stretch(1.0)),
-        padding: StyleProp::Value(Edge::all(
-            Units::Stretch(1.0),
-        )),
-        row_between: StyleProp::Value(Units::Pixels(20.0)),
-        ..Style::default()
-    };
-
-    rsx! {
-        <NinePatch
-                styles={Some(nine_patch_styles)}
-                border={Edge::all(50.0)}
-                handle={container}
-            >
-
-                <Text line_height={Some(50.0)} size={20.0} content={"Score".to_string()} />
-                <Text line_height={Some(50.0)} size={20.0} content={"Time".to_string()} />
+            <If condition={has_children}>
+                {children}
+            </If>
+            <If condition={!has_children}>
+                <Text line_height={Some(50.0)} size={20.0} content={label}/>
+            </If>
         </NinePatch>
-
     }
 }
 
 #[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
 struct CheckboxProps {
     checked: bool,
+    /// what audio guidance announces, with the state
+    label: String,
     #[prop_field(Styles)]
     styles: Option<Style>,
     #[prop_field(OnEvent)]
@@ -427,6 +2039,16 @@ struct CheckboxProps {
 }
 #[widget]
 fn Checkbox(props: CheckboxProps) {
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
     let (empty_box, checked_box) = {
         let mut world =
             context.get_global_mut::<World>().unwrap();
@@ -461,8 +2083,19 @@ fn Checkbox(props: CheckboxProps) {
     };
 
     let on_click = props.on_click.clone();
+    let label = localization.format(
+        if props.checked {
+            "checkbox.on"
+        } else {
+            "checkbox.off"
+        },
+        &[("label", &props.label)],
+    );
     let on_event = OnEvent::new(move |ctx, event| {
         match event.event_type {
+            EventType::MouseIn(..) | EventType::Focus => {
+                announce(ctx, &label)
+            }
             EventType::Click(..) => {
                 match &on_click {
                     Some(v) => {
@@ -471,6 +2104,9 @@ fn Checkbox(props: CheckboxProps) {
                     None => {}
                 };
             }
+            _ if presses(event) => {
+                click(&on_click, ctx, event)
+            }
             _ => (),
         }
     });
@@ -485,37 +2121,83 @@ fn Checkbox(props: CheckboxProps) {
             border={Edge::all(1.0)}
             styles={Some(button_styles)}
             handle={image}
+            focusable={Some(true)}
         />
     }
 }
 
+fn announce(ctx: &mut KayakContextRef, text: &str) {
+    let announcement = Announcement(text.to_string());
+    ctx.query_world::<EventWriter<Announcement>, _, _>(
+        move |mut announcements| {
+            announcements.send(announcement.clone())
+        },
+    );
+}
+
+/// Whether the event is Return or Space pressed on a
+/// focused item, which presses it like a click.
+fn presses(event: &Event) -> bool {
+    match &event.event_type {
+        EventType::KeyDown(key) => matches!(
+            key.key(),
+            KeyCode::Return | KeyCode::Space
+        ),
+        _ => false,
+    }
+}
+
+/// Hands a key press on to `on_click` as the click the
+/// handlers are written for.
+fn click(
+    on_click: &Option<OnEvent>,
+    ctx: &mut KayakContextRef,
+    event: &mut Event,
+) {
+    event.event_type = EventType::Click(CursorEvent {
+        pressed: false,
+        just_pressed: false,
+        just_released: true,
+        position: (0.0, 0.0),
+    });
+    if let Some(on_click) = on_click {
+        on_click.try_call(ctx, event);
+    }
+}
+
+const SKINS_PER_ROW: usize = 6;
+
 #[widget]
 fn SnakeSelector() {
-    let container: Vec<(usize, u16)> = (1..31)
-        .into_iter()
-        .map(|num| {
-            let mut world =
-                context.get_global_mut::<World>().unwrap();
-
-            let asset_server = world
-                .get_resource::<AssetServer>()
-                .unwrap();
-
-            let handle: Handle<
-                bevy::render::texture::Image,
-            > = asset_server.load(&format!(
-                "snake_heads/snake_sprites_{:02}.png",
-                num
-            ));
-
-            let mut image_manager = world
-                .get_resource_mut::<ImageManager>()
-                .unwrap();
-            let image = image_manager.get(&handle);
-            image
-        })
-        .enumerate()
-        .collect();
+    let skins = {
+        let skins = context
+            .query_world::<Res<Binding<Skins>>, _, _>(
+                move |skins| skins.clone(),
+            );
+
+        context.bind(&skins);
+        skins.get()
+    };
+
+    let thumbnails: Vec<(String, String, u16)> = {
+        let mut world =
+            context.get_global_mut::<World>().unwrap();
+
+        let mut image_manager = world
+            .get_resource_mut::<ImageManager>()
+            .unwrap();
+        skins
+            .0
+            .iter()
+            .map(|skin| {
+                (
+                    skin.id.clone(),
+                    skin.name.clone(),
+                    image_manager.get(&skin.thumbnail),
+                )
+            })
+            .collect()
+    };
 
     let row_styles = Style {
         col_between: StyleProp::Value(Units::Pixels(5.0)),
@@ -534,83 +2216,27 @@ fn SnakeSelector() {
         ..Default::default()
     };
 
-    let one = container[0].clone();
-    let two = container[1].clone();
-    let three = container[2].clone();
-    let four = container[3].clone();
-    let five = container[4].clone();
-
-    let six = container[5].clone();
-    let seven = container[6].clone();
-    let eight = container[7].clone();
-    let nine = container[8].clone();
-    let ten = container[9].clone();
-
-    let eleven = container[10].clone();
-    let twelve = container[11].clone();
-    let thirteen = container[12].clone();
-    let fourteen = container[13].clone();
-    let fifteen = container[14].clone();
-
-    let sixteen = container[15].clone();
-    let seventeen = container[16].clone();
-    let eighteen = container[17].clone();
-    let nineteen = container[18].clone();
-    let twenty = container[19].clone();
-
-    let twentyone = container[20].clone();
-    let twentytwo = container[21].clone();
-    let twentythree = container[22].clone();
-    let twentyfour = container[23].clone();
-    let twentyfive = container[24].clone();
-
-    let twentysix = container[25].clone();
-    let twentyseven = container[26].clone();
-    let twentyeight = container[27].clone();
-    let twentynine = container[28].clone();
-    let thirty = container[29].clone();
     rsx! {
     <Element styles={Some(snake_container_styles)}>
-        <Element styles={Some(row_styles)}>
-            <SnakeHead handle={one}/>
-            <SnakeHead handle={two}/>
-            <SnakeHead handle={three}/>
-            <SnakeHead handle={four}/>
-            <SnakeHead handle={five}/>
-            <SnakeHead handle={six}/>
-        </Element>
-        <Element styles={Some(row_styles)}>
-            <SnakeHead handle={seven}/>
-            <SnakeHead handle={eight}/>
-            <SnakeHead handle={nine}/>
-            <SnakeHead handle={ten}/>
-            <SnakeHead handle={eleven}/>
-            <SnakeHead handle={twelve}/>
-        </Element>
-        <Element styles={Some(row_styles)}>
-            <SnakeHead handle={thirteen}/>
-            <SnakeHead handle={fourteen}/>
-            <SnakeHead handle={fifteen}/>
-            <SnakeHead handle={sixteen}/>
-            <SnakeHead handle={seventeen}/>
-            <SnakeHead handle={eighteen}/>
-        </Element>
-        <Element styles={Some(row_styles)}>
-            <SnakeHead handle={nineteen}/>
-            <SnakeHead handle={twenty}/>
-            <SnakeHead handle={twentyone}/>
-            <SnakeHead handle={twentytwo}/>
-            <SnakeHead handle={twentythree}/>
-            <SnakeHead handle={twentyfour}/>
-        </Element>
-        <Element styles={Some(row_styles)}>
-            <SnakeHead handle={twentyfive}/>
-            <SnakeHead handle={twentysix}/>
-            <SnakeHead handle={twentyseven}/>
-            <SnakeHead handle={twentyeight}/>
-            <SnakeHead handle={twentynine}/>
-            <SnakeHead handle={thirty}/>
-        </Element>
+        {VecTracker::from(
+            thumbnails
+                .chunks(SKINS_PER_ROW)
+                .map(|row| {
+                    let row_styles = row_styles.clone();
+                    let row = row.to_vec();
+                    constructor! {
+                        <Element styles={Some(row_styles)}>
+                            {VecTracker::from(
+                                row.into_iter().map(|(id, name, image)| {
+                                    constructor! {
+                                        <SnakeHead id={id} name={name} image={image}/>
+                                    }
+                                }),
+                            )}
+                        </Element>
+                    }
+                }),
+        )}
     </Element>
     }
 }
@@ -619,7 +2245,11 @@ fn SnakeSelector() {
 struct SnakeHeadProps {
     #[prop_field(OnEvent)]
     on_event: Option<OnEvent>,
-    handle: (usize, u16),
+    /// the skin's id in `Skins`
+    id: String,
+    /// what audio guidance announces
+    name: String,
+    image: u16,
 }
 
 #[widget]
@@ -630,15 +2260,15 @@ fn SnakeHead(props: SnakeHeadProps) {
         ..Default::default()
     };
 
+    let id = props.id.clone();
+    let name = props.name.clone();
     let on_event = OnEvent::new(move |ctx, event| {
         match event.event_type {
-            EventType::Click(..) => {
-                ctx.query_world::<ResMut<SnakeTextureSelection>, _, _>(
-                    |mut selection| {
-                       selection.0 = props.handle.0 * 4;
-                    },
-                );
+            EventType::MouseIn(..) | EventType::Focus => {
+                announce(ctx, &name)
             }
+            EventType::Click(..) => select_skin(ctx, &id),
+            _ if presses(event) => select_skin(ctx, &id),
             _ => (),
         }
     });
@@ -647,11 +2277,21 @@ fn SnakeHead(props: SnakeHeadProps) {
             on_event={Some(on_event)}
             styles={Some(image_styles.clone())}
             border={Edge::all(1.0)}
-            handle={props.handle.1}
+            handle={props.image}
+            focusable={Some(true)}
         />
     }
 }
 
+fn select_skin(ctx: &mut KayakContextRef, id: &str) {
+    let id = id.to_string();
+    ctx.query_world::<ResMut<SnakeTextureSelection>, _, _>(
+        move |mut selection| {
+            selection.0 = id.clone();
+        },
+    );
+}
+
 #[derive(WidgetProps, Clone, Debug, Default, PartialEq)]
 struct SpeedrunsDisplayProps {
     #[prop_field(Styles)]
@@ -670,6 +2310,54 @@ fn SpeedrunsDisplay(props: SpeedrunsDisplayProps) {
         context.bind(&speedruns);
         speedruns.get()
     };
+    // only runs played with the current scheme compete
+    let scheme = {
+        let settings = context
+            .query_world::<Res<Binding<GameSettings>>, _, _>(
+                move |settings| settings.clone(),
+            );
+
+        context.bind(&settings);
+        settings.get().control_scheme
+    };
+    let localization = {
+        let localization = context.query_world::<Res<
+            Binding<Localization>,
+        >, _, _>(
+            move |localization| localization.clone(),
+        );
+
+        context.bind(&localization);
+        localization.get()
+    };
+    let category = localization.format(
+        "runs.category",
+        &[("scheme", &localization.get(scheme.key()))],
+    );
+    let runs = speedruns
+        .for_scheme(scheme)
+        .into_iter()
+        .enumerate()
+        .map(|(index, run)| {
+            localization.format(
+                "runs.entry",
+                &[
+                    ("rank", &(index + 1)),
+                    (
+                        "apples",
+                        &localization.plural(
+                            "apples",
+                            u64::from(run.score),
+                        ),
+                    ),
+                    (
+                        "time",
+                        &localization.duration(run.time),
+                    ),
+                ],
+            )
+        })
+        .collect::<Vec<String>>();
 
     // let button_styles = Style {
     //     position_type: StyleProp::Value(
@@ -694,12 +2382,12 @@ fn SpeedrunsDisplay(props: SpeedrunsDisplayProps) {
 
     rsx! {
         <Element styles={Some(container_styles)}>
+        <Text line_height={Some(20.0)} size={14.0} content={category}/>
         {VecTracker::from(
-            speedruns.sorted_by_run()
+            runs
                 .into_iter()
-                .enumerate()
-                .map(|(index, run)| constructor! {
-                    <Text line_height={Some(30.0)} size={20.0} content={format!("#{}: {} apples in {}s",index,run.score, run.time.as_secs().to_string())}/>
+                .map(|run| constructor! {
+                    <Text line_height={Some(30.0)} size={20.0} content={run}/>
                 }),
         )}
         </Element>